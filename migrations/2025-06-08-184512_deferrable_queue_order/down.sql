@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queue_rows
+    DROP CONSTRAINT queue_rows_queue_id_queue_order_key,
+    ADD CONSTRAINT queue_rows_queue_id_queue_order_key
+        UNIQUE (queue_id, queue_order);
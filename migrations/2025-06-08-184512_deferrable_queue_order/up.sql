@@ -0,0 +1,6 @@
+-- Check queue order uniqueness at the end of each statement instead of after
+-- every row, so rows can be renumbered with a single UPDATE.
+ALTER TABLE queue_rows
+    DROP CONSTRAINT queue_rows_queue_id_queue_order_key,
+    ADD CONSTRAINT queue_rows_queue_id_queue_order_key
+        UNIQUE (queue_id, queue_order) DEFERRABLE INITIALLY IMMEDIATE;
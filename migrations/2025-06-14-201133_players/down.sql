@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queue_rows
+    ADD COLUMN left_player_name TEXT,
+    ADD COLUMN right_player_name TEXT;
+
+UPDATE queue_rows SET left_player_name = players.display_name
+FROM players
+WHERE queue_rows.left_player_id = players.id;
+
+UPDATE queue_rows SET right_player_name = players.display_name
+FROM players
+WHERE queue_rows.right_player_id = players.id;
+
+ALTER TABLE queue_rows
+    DROP CONSTRAINT queue_rows_check,
+    DROP COLUMN left_player_id,
+    DROP COLUMN right_player_id,
+    ADD CONSTRAINT queue_rows_check
+        CHECK (left_player_name IS NOT NULL OR right_player_name IS NOT NULL);
+
+DROP TABLE players;
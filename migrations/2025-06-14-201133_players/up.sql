@@ -0,0 +1,42 @@
+CREATE TABLE players (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    display_name VARCHAR(255) NOT NULL,
+    -- Optional handle to tell players apart, e.g. a gamer tag
+    tag VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+);
+
+-- "Jon", "jon" and "JON" are the same player
+CREATE UNIQUE INDEX idx_players_display_name ON players (LOWER(display_name));
+
+-- Create a player for every name already in a queue, keeping the first
+-- spelling of each
+INSERT INTO players (display_name)
+SELECT DISTINCT ON (LOWER(name)) name
+FROM (
+    SELECT left_player_name AS name, created_at FROM queue_rows
+    UNION ALL
+    SELECT right_player_name AS name, created_at FROM queue_rows
+) AS names
+WHERE name IS NOT NULL
+ORDER BY LOWER(name), created_at;
+
+ALTER TABLE queue_rows
+    ADD COLUMN left_player_id UUID REFERENCES players(id),
+    ADD COLUMN right_player_id UUID REFERENCES players(id);
+
+UPDATE queue_rows SET left_player_id = players.id
+FROM players
+WHERE LOWER(queue_rows.left_player_name) = LOWER(players.display_name);
+
+UPDATE queue_rows SET right_player_id = players.id
+FROM players
+WHERE LOWER(queue_rows.right_player_name) = LOWER(players.display_name);
+
+ALTER TABLE queue_rows
+    DROP CONSTRAINT queue_rows_check,
+    DROP COLUMN left_player_name,
+    DROP COLUMN right_player_name,
+    -- Ensure rows are not empty
+    ADD CONSTRAINT queue_rows_check
+        CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER queue_rows_changed ON queue_rows;
+DROP FUNCTION notify_queue_rows_changed();
@@ -0,0 +1,17 @@
+-- Tell listeners which queue changed whenever its rows do. Postgres collapses
+-- identical notifications within a transaction, so a renumbering UPDATE only
+-- sends one.
+CREATE FUNCTION notify_queue_rows_changed() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify('queue_rows_changed', OLD.queue_id::TEXT);
+    ELSE
+        PERFORM pg_notify('queue_rows_changed', NEW.queue_id::TEXT);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER queue_rows_changed
+    AFTER INSERT OR UPDATE OR DELETE ON queue_rows
+    FOR EACH ROW EXECUTE PROCEDURE notify_queue_rows_changed();
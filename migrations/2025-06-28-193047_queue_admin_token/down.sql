@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queues DROP COLUMN admin_token_hash;
@@ -0,0 +1,3 @@
+-- Only a hash of each queue's admin token is stored. Queues created before
+-- ownership existed have no token and can still be managed by anyone.
+ALTER TABLE queues ADD COLUMN admin_token_hash BYTEA;
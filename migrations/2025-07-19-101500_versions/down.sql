@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queue_rows DROP COLUMN version;
+ALTER TABLE queues DROP COLUMN version;
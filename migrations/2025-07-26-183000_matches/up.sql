@@ -0,0 +1,16 @@
+-- One row per finished match, written when the front row of a queue is
+-- completed. Players are kept even if they leave the queue, so history
+-- survives the row it came from.
+CREATE TABLE matches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    queue_id UUID NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    left_player_id UUID REFERENCES players(id),
+    right_player_id UUID REFERENCES players(id),
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ended_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    -- The side that won, if anyone said
+    winner VARCHAR(5) CHECK (winner IN ('Left', 'Right')),
+    CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL)
+);
+
+CREATE INDEX idx_matches_queue_ended ON matches (queue_id, ended_at);
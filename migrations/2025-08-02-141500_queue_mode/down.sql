@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queues DROP COLUMN max_streak;
+ALTER TABLE queues DROP COLUMN mode;
@@ -0,0 +1,7 @@
+-- How the front row changes when its match ends, see `QueueMode`
+ALTER TABLE queues
+    ADD COLUMN mode VARCHAR(11) NOT NULL DEFAULT 'Pairs'
+        CHECK (mode IN ('Pairs', 'WinnerStays', 'LoserStays')),
+    -- Matches in a row a player can stay on for before going to the back.
+    -- No limit if NULL.
+    ADD COLUMN max_streak INT CHECK (max_streak > 0);
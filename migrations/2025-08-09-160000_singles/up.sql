@@ -0,0 +1,2 @@
+-- Players join alone and are paired up with whoever has waited longest
+ALTER TABLE queues ADD COLUMN singles BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,49 @@
+-- This file should undo anything in `up.sql`
+-- Only the first slot of each side fits back into the old columns
+ALTER TABLE matches
+    ADD COLUMN left_player_id UUID REFERENCES players(id),
+    ADD COLUMN right_player_id UUID REFERENCES players(id);
+
+UPDATE matches SET left_player_id = match_slots.player_id
+FROM match_slots
+WHERE match_slots.match_id = matches.id
+    AND match_slots.side = 'Left' AND match_slots.slot_index = 0;
+
+UPDATE matches SET right_player_id = match_slots.player_id
+FROM match_slots
+WHERE match_slots.match_id = matches.id
+    AND match_slots.side = 'Right' AND match_slots.slot_index = 0;
+
+DELETE FROM matches
+WHERE left_player_id IS NULL AND right_player_id IS NULL;
+
+ALTER TABLE matches
+    ADD CONSTRAINT matches_check
+        CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL);
+
+DROP TABLE match_slots;
+
+ALTER TABLE queue_rows
+    ADD COLUMN left_player_id UUID REFERENCES players(id),
+    ADD COLUMN right_player_id UUID REFERENCES players(id);
+
+UPDATE queue_rows SET left_player_id = row_slots.player_id
+FROM row_slots
+WHERE row_slots.row_id = queue_rows.id
+    AND row_slots.side = 'Left' AND row_slots.slot_index = 0;
+
+UPDATE queue_rows SET right_player_id = row_slots.player_id
+FROM row_slots
+WHERE row_slots.row_id = queue_rows.id
+    AND row_slots.side = 'Right' AND row_slots.slot_index = 0;
+
+DELETE FROM queue_rows
+WHERE left_player_id IS NULL AND right_player_id IS NULL;
+
+ALTER TABLE queue_rows
+    ADD CONSTRAINT queue_rows_check
+        CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL);
+
+DROP TABLE row_slots;
+
+ALTER TABLE queues DROP COLUMN team_size;
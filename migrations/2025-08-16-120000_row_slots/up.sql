@@ -0,0 +1,49 @@
+-- Players per side of a row, e.g. 2 for doubles
+ALTER TABLE queues
+    ADD COLUMN team_size INT NOT NULL DEFAULT 1
+        CHECK (team_size BETWEEN 1 AND 8);
+
+-- One row per filled slot. A row's slots run from 0 on each side, and rows
+-- without any slots are deleted by the app.
+CREATE TABLE row_slots (
+    row_id UUID NOT NULL REFERENCES queue_rows(id) ON DELETE CASCADE,
+    side VARCHAR(5) NOT NULL CHECK (side IN ('Left', 'Right')),
+    slot_index INT NOT NULL CHECK (slot_index >= 0),
+    player_id UUID NOT NULL REFERENCES players(id),
+    PRIMARY KEY (row_id, side, slot_index)
+);
+
+INSERT INTO row_slots (row_id, side, slot_index, player_id)
+SELECT id, 'Left', 0, left_player_id FROM queue_rows
+WHERE left_player_id IS NOT NULL
+UNION ALL
+SELECT id, 'Right', 0, right_player_id FROM queue_rows
+WHERE right_player_id IS NOT NULL;
+
+ALTER TABLE queue_rows
+    DROP CONSTRAINT queue_rows_check,
+    DROP COLUMN left_player_id,
+    DROP COLUMN right_player_id;
+
+-- Who played in each slot of a finished match, like `row_slots`
+CREATE TABLE match_slots (
+    match_id UUID NOT NULL REFERENCES matches(id) ON DELETE CASCADE,
+    side VARCHAR(5) NOT NULL CHECK (side IN ('Left', 'Right')),
+    slot_index INT NOT NULL CHECK (slot_index >= 0),
+    player_id UUID NOT NULL REFERENCES players(id),
+    PRIMARY KEY (match_id, side, slot_index)
+);
+
+CREATE INDEX idx_match_slots_player ON match_slots (player_id);
+
+INSERT INTO match_slots (match_id, side, slot_index, player_id)
+SELECT id, 'Left', 0, left_player_id FROM matches
+WHERE left_player_id IS NOT NULL
+UNION ALL
+SELECT id, 'Right', 0, right_player_id FROM matches
+WHERE right_player_id IS NOT NULL;
+
+ALTER TABLE matches
+    DROP CONSTRAINT matches_check,
+    DROP COLUMN left_player_id,
+    DROP COLUMN right_player_id;
@@ -0,0 +1,11 @@
+-- Rows are called when they reach the front, and their players have
+-- `no_show_timeout` seconds to check in. Rows that miss it move back
+-- `no_show_move_back` places, or leave the queue if that's NULL. No timeout
+-- means rows are never called.
+ALTER TABLE queues
+    ADD COLUMN no_show_timeout INT CHECK (no_show_timeout > 0),
+    ADD COLUMN no_show_move_back INT CHECK (no_show_move_back > 0);
+
+ALTER TABLE queue_rows ADD COLUMN called_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE row_slots ADD COLUMN checked_in BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,19 @@
+-- Setups a queue plays on at the same time. Each station plays at most one
+-- row, which stays in the queue until its match ends. Stations without a row
+-- are free.
+CREATE TABLE stations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    queue_id UUID NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    -- A row can't play on two stations at once
+    row_id UUID UNIQUE REFERENCES queue_rows(id) ON DELETE SET NULL,
+    started_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_stations_queue ON stations (queue_id, created_at);
+
+-- Stations are part of the queue's state, so listeners hear about them too.
+CREATE TRIGGER stations_changed
+    AFTER INSERT OR UPDATE OR DELETE ON stations
+    FOR EACH ROW EXECUTE PROCEDURE notify_queue_rows_changed();
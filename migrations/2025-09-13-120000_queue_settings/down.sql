@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queues
+    DROP COLUMN status,
+    DROP COLUMN max_entries_per_player,
+    DROP COLUMN max_rows;
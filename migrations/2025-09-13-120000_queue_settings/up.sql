@@ -0,0 +1,7 @@
+-- Limits on joining a queue, see `QueueSettings`. NULL means no limit.
+ALTER TABLE queues
+    ADD COLUMN max_rows INT CHECK (max_rows > 0),
+    ADD COLUMN max_entries_per_player INT
+        CHECK (max_entries_per_player > 0),
+    ADD COLUMN status VARCHAR(6) NOT NULL DEFAULT 'Open'
+        CHECK (status IN ('Open', 'Paused', 'Closed'));
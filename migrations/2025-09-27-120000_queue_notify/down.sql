@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER queue_changed ON queues;
+DROP FUNCTION notify_queue_changed();
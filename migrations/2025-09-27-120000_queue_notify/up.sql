@@ -0,0 +1,14 @@
+-- Changing a queue's settings bumps its version without touching its rows,
+-- so tell listeners whenever the version moves on as well. Changes to rows
+-- in the same transaction collapse into the same notification.
+CREATE FUNCTION notify_queue_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('queue_rows_changed', NEW.id::TEXT);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER queue_changed
+    AFTER UPDATE OF version ON queues
+    FOR EACH ROW WHEN (OLD.version IS DISTINCT FROM NEW.version)
+    EXECUTE PROCEDURE notify_queue_changed();
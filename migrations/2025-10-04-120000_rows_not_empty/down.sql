@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER row_slots_not_empty ON row_slots;
+DROP TRIGGER queue_rows_not_empty ON queue_rows;
+DROP FUNCTION check_row_not_empty();
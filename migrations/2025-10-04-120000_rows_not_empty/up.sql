@@ -0,0 +1,32 @@
+-- Rows used to need a player on one side or the other. With players in
+-- row_slots that can only be checked once a change is complete, since a row
+-- is inserted before its slots and its slots are rewritten when it changes.
+-- SQLite can't defer triggers, so there the app deletes rows that empty out.
+CREATE FUNCTION check_row_not_empty() RETURNS trigger AS $$
+DECLARE
+    checked_row_id UUID;
+BEGIN
+    IF TG_TABLE_NAME = 'queue_rows' THEN
+        checked_row_id := NEW.id;
+    ELSE
+        checked_row_id := OLD.row_id;
+    END IF;
+    IF EXISTS (SELECT 1 FROM queue_rows WHERE id = checked_row_id)
+        AND NOT EXISTS (SELECT 1 FROM row_slots WHERE row_id = checked_row_id)
+    THEN
+        RAISE EXCEPTION 'queue row % has no players', checked_row_id
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER queue_rows_not_empty
+    AFTER INSERT ON queue_rows
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE PROCEDURE check_row_not_empty();
+
+CREATE CONSTRAINT TRIGGER row_slots_not_empty
+    AFTER DELETE OR UPDATE OF row_id ON row_slots
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE PROCEDURE check_row_not_empty();
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- Fails if players now share a name and differ only by tag
+DROP INDEX idx_players_display_name;
+CREATE UNIQUE INDEX idx_players_display_name ON players (LOWER(display_name));
@@ -0,0 +1,5 @@
+-- A tag tells apart players who share a name, so names only need to be
+-- unique, ignoring case, among players with the same tag or without one.
+DROP INDEX idx_players_display_name;
+CREATE UNIQUE INDEX idx_players_display_name
+    ON players (LOWER(display_name), COALESCE(tag, ''));
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+UPDATE queues SET admin_token_hash = NULL
+FROM legacy_admin_tokens
+WHERE legacy_admin_tokens.queue_id = queues.id;
+
+DROP TABLE legacy_admin_tokens;
@@ -0,0 +1,19 @@
+-- Queues made before admin tokens existed could be managed by anyone. Give
+-- each of them a token instead, kept here so whoever runs the server can
+-- send the queue's organiser an admin link (`/queue/{url_name}?admin=...`).
+-- Delete a queue's row once its token has been passed on.
+CREATE TABLE legacy_admin_tokens (
+    queue_id UUID PRIMARY KEY REFERENCES queues(id) ON DELETE CASCADE,
+    admin_token TEXT NOT NULL
+);
+
+INSERT INTO legacy_admin_tokens (queue_id, admin_token)
+SELECT id, REPLACE(gen_random_uuid()::TEXT, '-', '')
+FROM queues
+WHERE admin_token_hash IS NULL;
+
+UPDATE queues
+SET admin_token_hash =
+    sha256(convert_to(legacy_admin_tokens.admin_token, 'UTF8'))
+FROM legacy_admin_tokens
+WHERE legacy_admin_tokens.queue_id = queues.id;
@@ -0,0 +1,5 @@
+DROP TABLE queue_rows;
+DROP TABLE players;
+DROP TABLE queues;
+DROP TABLE sessions;
+DROP TABLE users;
@@ -0,0 +1,60 @@
+-- SQLite has no UUID type or gen_random_uuid(), so ids are generated by the
+-- app and stored as hyphenated TEXT. Timestamps are TEXT in UTC.
+CREATE TABLE users (
+    id TEXT PRIMARY KEY NOT NULL,
+    username TEXT NOT NULL,
+    -- Argon2 hash in PHC string format, including its salt and parameters
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_users_username ON users (LOWER(username));
+
+-- Only a hash of each session token is stored, like queue admin tokens
+CREATE TABLE sessions (
+    token_hash BLOB PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions (user_id);
+
+CREATE TABLE queues (
+    id TEXT PRIMARY KEY NOT NULL,
+    url_name TEXT UNIQUE NOT NULL,
+    display_name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Queues without a token can be managed by anyone
+    admin_token_hash BLOB,
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_queues_created_by ON queues (created_by);
+
+CREATE TABLE players (
+    id TEXT PRIMARY KEY NOT NULL,
+    display_name TEXT NOT NULL,
+    -- Optional handle to tell players apart, e.g. a gamer tag
+    tag TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- "Jon", "jon" and "JON" are the same player
+CREATE UNIQUE INDEX idx_players_display_name ON players (LOWER(display_name));
+
+CREATE TABLE queue_rows (
+    id TEXT PRIMARY KEY NOT NULL,
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    -- Represents ordering of rows. SQLite can't defer the uniqueness check,
+    -- so rows are renumbered through negative orders.
+    queue_order INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    left_player_id TEXT REFERENCES players(id),
+    right_player_id TEXT REFERENCES players(id),
+    UNIQUE (queue_id, queue_order),
+    -- Ensure rows are not empty
+    CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL)
+);
+
+CREATE INDEX idx_queue_rows_order ON queue_rows (queue_id, queue_order);
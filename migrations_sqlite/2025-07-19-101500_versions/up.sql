@@ -0,0 +1,5 @@
+-- Bumped by every change, so clients can tell whether what they last saw is
+-- still current. A queue's version also changes whenever one of its rows
+-- does.
+ALTER TABLE queues ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE queue_rows ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
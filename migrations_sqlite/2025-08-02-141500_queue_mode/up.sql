@@ -0,0 +1,6 @@
+-- How the front row changes when its match ends, see `QueueMode`
+ALTER TABLE queues ADD COLUMN mode TEXT NOT NULL DEFAULT 'Pairs'
+    CHECK (mode IN ('Pairs', 'WinnerStays', 'LoserStays'));
+-- Matches in a row a player can stay on for before going to the back.
+-- No limit if NULL.
+ALTER TABLE queues ADD COLUMN max_streak INTEGER CHECK (max_streak > 0);
@@ -0,0 +1,66 @@
+-- This file should undo anything in `up.sql`
+-- Only the first slot of each side fits back into the old columns
+CREATE TABLE old_matches (
+    id TEXT PRIMARY KEY NOT NULL,
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    left_player_id TEXT REFERENCES players(id),
+    right_player_id TEXT REFERENCES players(id),
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ NOT NULL,
+    -- The side that won, if anyone said
+    winner TEXT CHECK (winner IN ('Left', 'Right')),
+    CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL)
+);
+
+INSERT INTO old_matches
+SELECT id, queue_id,
+    (SELECT player_id FROM match_slots
+        WHERE match_id = matches.id AND side = 'Left' AND slot_index = 0),
+    (SELECT player_id FROM match_slots
+        WHERE match_id = matches.id AND side = 'Right' AND slot_index = 0),
+    started_at, ended_at, winner
+FROM matches
+WHERE EXISTS (
+    SELECT 1 FROM match_slots
+    WHERE match_id = matches.id AND slot_index = 0
+);
+
+DROP TABLE match_slots;
+DROP TABLE matches;
+ALTER TABLE old_matches RENAME TO matches;
+CREATE INDEX idx_matches_queue_ended ON matches (queue_id, ended_at);
+
+CREATE TABLE old_queue_rows (
+    id TEXT PRIMARY KEY NOT NULL,
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    -- Represents ordering of rows. SQLite can't defer the uniqueness check,
+    -- so rows are renumbered through negative orders.
+    queue_order INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    left_player_id TEXT REFERENCES players(id),
+    right_player_id TEXT REFERENCES players(id),
+    version INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (queue_id, queue_order),
+    -- Ensure rows are not empty
+    CHECK (left_player_id IS NOT NULL OR right_player_id IS NOT NULL)
+);
+
+INSERT INTO old_queue_rows
+SELECT id, queue_id, queue_order, created_at,
+    (SELECT player_id FROM row_slots
+        WHERE row_id = queue_rows.id AND side = 'Left' AND slot_index = 0),
+    (SELECT player_id FROM row_slots
+        WHERE row_id = queue_rows.id AND side = 'Right' AND slot_index = 0),
+    version
+FROM queue_rows
+WHERE EXISTS (
+    SELECT 1 FROM row_slots
+    WHERE row_id = queue_rows.id AND slot_index = 0
+);
+
+DROP TABLE row_slots;
+DROP TABLE queue_rows;
+ALTER TABLE old_queue_rows RENAME TO queue_rows;
+CREATE INDEX idx_queue_rows_order ON queue_rows (queue_id, queue_order);
+
+ALTER TABLE queues DROP COLUMN team_size;
@@ -0,0 +1,80 @@
+-- Players per side of a row, e.g. 2 for doubles
+ALTER TABLE queues ADD COLUMN team_size INTEGER NOT NULL DEFAULT 1
+    CHECK (team_size BETWEEN 1 AND 8);
+
+-- SQLite can't drop columns used in a CHECK, so the slots are set aside
+-- while queue_rows and matches are rebuilt without their player columns.
+CREATE TABLE old_row_slots AS
+SELECT id AS row_id, 'Left' AS side, left_player_id AS player_id
+FROM queue_rows WHERE left_player_id IS NOT NULL
+UNION ALL
+SELECT id, 'Right', right_player_id
+FROM queue_rows WHERE right_player_id IS NOT NULL;
+
+CREATE TABLE new_queue_rows (
+    id TEXT PRIMARY KEY NOT NULL,
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    -- Represents ordering of rows. SQLite can't defer the uniqueness check,
+    -- so rows are renumbered through negative orders.
+    queue_order INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (queue_id, queue_order)
+);
+
+INSERT INTO new_queue_rows (id, queue_id, queue_order, created_at, version)
+SELECT id, queue_id, queue_order, created_at, version FROM queue_rows;
+DROP TABLE queue_rows;
+ALTER TABLE new_queue_rows RENAME TO queue_rows;
+CREATE INDEX idx_queue_rows_order ON queue_rows (queue_id, queue_order);
+
+-- One row per filled slot. A row's slots run from 0 on each side, and rows
+-- without any slots are deleted by the app.
+CREATE TABLE row_slots (
+    row_id TEXT NOT NULL REFERENCES queue_rows(id) ON DELETE CASCADE,
+    side TEXT NOT NULL CHECK (side IN ('Left', 'Right')),
+    slot_index INTEGER NOT NULL CHECK (slot_index >= 0),
+    player_id TEXT NOT NULL REFERENCES players(id),
+    PRIMARY KEY (row_id, side, slot_index)
+);
+
+INSERT INTO row_slots (row_id, side, slot_index, player_id)
+SELECT row_id, side, 0, player_id FROM old_row_slots;
+DROP TABLE old_row_slots;
+
+CREATE TABLE old_match_slots AS
+SELECT id AS match_id, 'Left' AS side, left_player_id AS player_id
+FROM matches WHERE left_player_id IS NOT NULL
+UNION ALL
+SELECT id, 'Right', right_player_id
+FROM matches WHERE right_player_id IS NOT NULL;
+
+CREATE TABLE new_matches (
+    id TEXT PRIMARY KEY NOT NULL,
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ NOT NULL,
+    -- The side that won, if anyone said
+    winner TEXT CHECK (winner IN ('Left', 'Right'))
+);
+
+INSERT INTO new_matches (id, queue_id, started_at, ended_at, winner)
+SELECT id, queue_id, started_at, ended_at, winner FROM matches;
+DROP TABLE matches;
+ALTER TABLE new_matches RENAME TO matches;
+CREATE INDEX idx_matches_queue_ended ON matches (queue_id, ended_at);
+
+-- Who played in each slot of a finished match, like `row_slots`
+CREATE TABLE match_slots (
+    match_id TEXT NOT NULL REFERENCES matches(id) ON DELETE CASCADE,
+    side TEXT NOT NULL CHECK (side IN ('Left', 'Right')),
+    slot_index INTEGER NOT NULL CHECK (slot_index >= 0),
+    player_id TEXT NOT NULL REFERENCES players(id),
+    PRIMARY KEY (match_id, side, slot_index)
+);
+
+CREATE INDEX idx_match_slots_player ON match_slots (player_id);
+
+INSERT INTO match_slots (match_id, side, slot_index, player_id)
+SELECT match_id, side, 0, player_id FROM old_match_slots;
+DROP TABLE old_match_slots;
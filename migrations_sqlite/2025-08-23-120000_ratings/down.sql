@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE rating_changes;
+DROP TABLE ratings;
@@ -0,0 +1,22 @@
+-- Each player's current Elo rating in a queue, updated whenever a match
+-- there ends with a winner. Players without a row haven't played a rated
+-- match yet.
+CREATE TABLE ratings (
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    player_id TEXT NOT NULL REFERENCES players(id),
+    rating REAL NOT NULL,
+    games INT NOT NULL DEFAULT 0,
+    wins INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (queue_id, player_id)
+);
+
+CREATE INDEX idx_ratings_queue_rating ON ratings (queue_id, rating);
+
+-- How each rated match moved its players' ratings, kept to chart progress.
+CREATE TABLE rating_changes (
+    match_id TEXT NOT NULL REFERENCES matches(id) ON DELETE CASCADE,
+    player_id TEXT NOT NULL REFERENCES players(id),
+    rating_before REAL NOT NULL,
+    rating_after REAL NOT NULL,
+    PRIMARY KEY (match_id, player_id)
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE row_slots DROP COLUMN checked_in;
+ALTER TABLE queue_rows DROP COLUMN called_at;
+ALTER TABLE queues DROP COLUMN no_show_move_back;
+ALTER TABLE queues DROP COLUMN no_show_timeout;
@@ -0,0 +1,14 @@
+-- Setups a queue plays on at the same time. Each station plays at most one
+-- row, which stays in the queue until its match ends. Stations without a row
+-- are free.
+CREATE TABLE stations (
+    id TEXT PRIMARY KEY NOT NULL,
+    queue_id TEXT NOT NULL REFERENCES queues(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- A row can't play on two stations at once
+    row_id TEXT UNIQUE REFERENCES queue_rows(id) ON DELETE SET NULL,
+    started_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_stations_queue ON stations (queue_id, created_at);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE queues DROP COLUMN status;
+ALTER TABLE queues DROP COLUMN max_entries_per_player;
+ALTER TABLE queues DROP COLUMN max_rows;
@@ -0,0 +1,6 @@
+-- Limits on joining a queue, see `QueueSettings`. NULL means no limit.
+ALTER TABLE queues ADD COLUMN max_rows INT CHECK (max_rows > 0);
+ALTER TABLE queues ADD COLUMN max_entries_per_player INT
+    CHECK (max_entries_per_player > 0);
+ALTER TABLE queues ADD COLUMN status TEXT NOT NULL DEFAULT 'Open'
+    CHECK (status IN ('Open', 'Paused', 'Closed'));
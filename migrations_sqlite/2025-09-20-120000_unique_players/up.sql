@@ -0,0 +1,3 @@
+-- Whether a player already waiting in a queue is turned away from joining it
+-- again, see `QueueSettings`
+ALTER TABLE queues ADD COLUMN unique_players BOOLEAN NOT NULL DEFAULT FALSE;
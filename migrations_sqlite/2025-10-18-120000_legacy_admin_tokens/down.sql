@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+UPDATE queues SET admin_token_hash = NULL
+WHERE id IN (SELECT queue_id FROM legacy_admin_tokens);
+
+DROP TABLE legacy_admin_tokens;
@@ -0,0 +1,22 @@
+-- Queues made before admin tokens existed could be managed by anyone. Give
+-- each of them a token instead, kept here so whoever runs the server can
+-- send the queue's organiser an admin link (`/queue/{url_name}?admin=...`).
+-- Delete a queue's row once its token has been passed on.
+CREATE TABLE legacy_admin_tokens (
+    queue_id TEXT PRIMARY KEY NOT NULL
+        REFERENCES queues(id) ON DELETE CASCADE,
+    admin_token TEXT NOT NULL
+);
+
+INSERT INTO legacy_admin_tokens (queue_id, admin_token)
+SELECT id, LOWER(HEX(RANDOMBLOB(16)))
+FROM queues
+WHERE admin_token_hash IS NULL;
+
+-- `sha256` is registered by the app, which runs these migrations.
+UPDATE queues
+SET admin_token_hash = (
+    SELECT sha256(admin_token) FROM legacy_admin_tokens
+    WHERE legacy_admin_tokens.queue_id = queues.id
+)
+WHERE id IN (SELECT queue_id FROM legacy_admin_tokens);
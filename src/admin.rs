@@ -0,0 +1,91 @@
+//! Admin access to queues. Creating a queue hands out a secret token, which
+//! later requests present in a cookie or the `x-admin-token` header. The user
+//! who created a queue can also manage it while logged in.
+
+use crate::auth::current_user;
+use crate::cookies::{read_cookie, set_cookie};
+use crate::db::{api::ApiError, Store};
+use axum::http::HeaderMap;
+use leptos::prelude::*;
+use leptos_axum::extract;
+use uuid::Uuid;
+
+/// Header API clients can send the admin token in instead of a cookie.
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+/// Admin cookies last a year.
+const ADMIN_COOKIE_MAX_AGE: i64 = 60 * 60 * 24 * 365;
+
+fn cookie_name(queue_id: Uuid) -> String {
+    format!("itq_admin_{}", queue_id.simple())
+}
+
+/// Reads the admin token for a queue from the request headers, preferring
+/// the `x-admin-token` header over the queue's cookie.
+pub fn admin_token(headers: &HeaderMap, queue_id: Uuid) -> Option<String> {
+    headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| read_cookie(headers, &cookie_name(queue_id)))
+}
+
+/// Remembers the admin token by setting a cookie on the response to the
+/// current server function.
+pub fn set_admin_cookie(queue_id: Uuid, admin_token: &str) {
+    set_cookie(
+        &cookie_name(queue_id),
+        admin_token,
+        ADMIN_COOKIE_MAX_AGE,
+    );
+}
+
+/// Whether the current request may manage the queue.
+pub async fn is_admin(queue_id: Uuid) -> Result<bool, ServerFnError> {
+    let headers: HeaderMap = extract().await?;
+    let user = current_user().await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store
+        .is_queue_admin(
+            queue_id,
+            admin_token(&headers, queue_id),
+            user.map(|u| u.id),
+        )
+        .await?)
+}
+
+/// Fails unless the current request may manage the queue.
+pub async fn require_admin(queue_id: Uuid) -> Result<(), ServerFnError> {
+    if is_admin(queue_id).await? {
+        Ok(())
+    } else {
+        Err(ApiError::NotAdmin { queue_id }.into())
+    }
+}
+
+/// Fails unless the current request may manage the queue the row is in.
+pub async fn require_row_admin(row_id: Uuid) -> Result<(), ServerFnError> {
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    require_admin(
+        store
+            .get_row_queue_id(row_id)
+            .await?,
+    )
+    .await
+}
+
+/// Fails unless the current request may manage the queue the station is in.
+pub async fn require_station_admin(
+    station_id: Uuid,
+) -> Result<(), ServerFnError> {
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    require_admin(
+        store
+            .get_station_queue_id(station_id)
+            .await?,
+    )
+    .await
+}
@@ -31,6 +31,7 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
+    provide_session();
 
     view! {
         // injects a stylesheet into the document <head>
@@ -42,11 +43,21 @@ pub fn App() -> impl IntoView {
 
         // content for this welcome page
         <Router>
+            <AccountNav />
             <main>
                 <Routes fallback=|| "Page not found.".into_view()>
                     <Route path=path!("") view=HomePage />
                     <Route path=path!("/queue/:url_name") view=QueuePage />
+                    <Route
+                        path=path!("/queue/:url_name/history")
+                        view=HistoryPage
+                    />
+                    <Route
+                        path=path!("/queue/:url_name/leaderboard")
+                        view=LeaderboardPage
+                    />
                     <Route path=path!("/add") view=AddQueuePage />
+                    <Route path=path!("/login") view=LoginPage />
                 </Routes>
             </main>
         </Router>
@@ -0,0 +1,73 @@
+//! Organizer accounts. Logging in starts a session stored in the database and
+//! identified by a cookie, and [`session_layer`] looks up the logged in user
+//! for every request.
+
+use crate::cookies::{read_cookie, set_cookie};
+use crate::db::Store;
+use crate::user::User;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use leptos::logging::error;
+use leptos::prelude::*;
+use leptos_axum::extract;
+
+const SESSION_COOKIE: &str = "itq_session";
+
+/// Session cookies last as long as the sessions themselves.
+const SESSION_COOKIE_MAX_AGE: i64 = 60 * 60 * 24 * 30;
+
+/// The logged in user, if any. Added to every request by [`session_layer`].
+#[derive(Clone, Debug)]
+pub struct CurrentUser(pub Option<User>);
+
+/// Middleware that resolves the session cookie to a [`CurrentUser`].
+pub async fn session_layer(
+    State(store): State<Store>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user = match read_cookie(request.headers(), SESSION_COOKIE) {
+        Some(token) => store
+            .get_session_user(token)
+            .await
+            .inspect_err(|e| error!("Error loading session: {}", e))
+            .ok()
+            .flatten(),
+        None => None,
+    };
+    request
+        .extensions_mut()
+        .insert(CurrentUser(user));
+    next.run(request).await
+}
+
+/// Gets the user logged in for the current server function call.
+pub async fn current_user() -> Result<Option<User>, ServerFnError> {
+    let Extension(CurrentUser(user)) = extract().await?;
+    Ok(user)
+}
+
+/// Logs a user in by starting a session and setting its cookie.
+pub async fn start_session(user: &User) -> Result<(), ServerFnError> {
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let token = store
+        .create_session(user.id)
+        .await?;
+    set_cookie(SESSION_COOKIE, &token, SESSION_COOKIE_MAX_AGE);
+    Ok(())
+}
+
+/// Logs out by ending the current session and clearing its cookie.
+pub async fn end_session() -> Result<(), ServerFnError> {
+    let headers: axum::http::HeaderMap = extract().await?;
+    if let Some(token) = read_cookie(&headers, SESSION_COOKIE) {
+        let store =
+            use_context::<Store>().expect("there to be a `store` provided.");
+        store.delete_session(token).await?;
+    }
+    set_cookie(SESSION_COOKIE, "", 0);
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Reading and setting the few cookies the server uses.
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use leptos::prelude::*;
+use leptos_axum::ResponseOptions;
+
+/// Gets a cookie's value from the request headers.
+pub fn read_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Sets an HTTP-only cookie on the response to the current server function.
+/// A `max_age` of zero removes the cookie.
+pub fn set_cookie(name: &str, value: &str, max_age: i64) {
+    let response = expect_context::<ResponseOptions>();
+    let cookie = format!(
+        "{name}={value}; Path=/; Max-Age={max_age}; HttpOnly; SameSite=Lax"
+    );
+    if let Ok(value) = HeaderValue::from_str(&cookie) {
+        response.append_header(header::SET_COOKIE, value);
+    }
+}
@@ -1,9 +1,16 @@
 use crate::db;
-use crate::db::QueueRow;
+use crate::db::{Lineup, QueueRow, RowRecord, RowSlot};
+use crate::estimate;
+use crate::history::{count_games, GamesPlayed, PlayedMatch};
 use crate::queue::*;
+use crate::rating::{RatingPoint, Standing};
+use crate::user::User;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use leptos::logging::error;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -12,17 +19,75 @@ pub enum ApiError {
     #[error("database connection pool error")]
     PoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
     #[error("diesel error: {0}")]
-    DieselError(#[from] diesel::result::Error),
-    #[error("player slot already occupied. row: {row_id}, order: {order}, side: {side}")]
+    DieselError(diesel::result::Error),
+    #[error("not found")]
+    NotFound,
+    #[error("player slot already occupied. row: {row_id}, order: {order}, slot: {slot}")]
     Occupied {
         row_id: Uuid,
         order: i32,
-        side: Side,
+        slot: Slot,
     },
     #[error("invalid order. expected: {expected}, got: {got}")]
     InvalidOrder { expected: i32, got: i32 },
+    #[error("player slot is empty. row: {row_id}, slot: {slot}")]
+    EmptySlot { row_id: Uuid, slot: Slot },
+    #[error("row {row_id} hasn't been called to play yet")]
+    NotCalled { row_id: Uuid },
+    #[error("queue {queue_id} plays on stations, finish a station instead")]
+    StationRequired { queue_id: Uuid },
+    #[error("players can only move between rows of the same queue")]
+    DifferentQueues,
+    #[error("no slot {slot} when teams have {team_size} players")]
+    InvalidSlot { slot: Slot, team_size: i32 },
+    #[error("player name can't be blank")]
+    BlankPlayerName,
+    #[error("queue is full, it holds at most {max_rows} rows")]
+    QueueFull { max_rows: i32 },
+    #[error("players can be in at most {max_entries} rows of this queue")]
+    PlayerLimit { max_entries: i32 },
+    #[error("queue isn't taking new players while it's {status}")]
+    QueueClosed { status: QueueStatus },
+    #[error(
+        "player is already waiting in the queue. row: {row_id}, order: {order}"
+    )]
+    AlreadyQueued { row_id: Uuid, order: i32 },
+    #[error("queue {queue_id} changed at the same time, try again")]
+    QueueBusy { queue_id: Uuid },
+    #[error("queue has changed, it's now at version {}", .current.version)]
+    Conflict { current: QueueState },
+    #[error("url name is already taken")]
+    UrlNameTaken,
+    #[error("not an admin of queue {queue_id}")]
+    NotAdmin { queue_id: Uuid },
+    #[error("username can't be blank")]
+    BlankUsername,
+    #[error("username is already taken")]
+    UsernameTaken,
+    #[error("password must be at least {MIN_PASSWORD_LENGTH} characters")]
+    WeakPassword,
+    #[error("incorrect username or password")]
+    InvalidCredentials,
+    #[error("password hashing error: {0}")]
+    PasswordHashError(String),
 }
 
+impl From<diesel::result::Error> for ApiError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => ApiError::NotFound,
+            e => ApiError::DieselError(e),
+        }
+    }
+}
+
+/// Shortest password accepted for new accounts.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// How long a login lasts before the user has to log in again.
+pub(crate) const SESSION_LENGTH: chrono::TimeDelta =
+    chrono::TimeDelta::days(30);
+
 pub async fn get_all_queues(
     pool: db::DbPool,
 ) -> Result<Vec<QueueInfo>, ApiError> {
@@ -56,25 +121,289 @@ pub async fn get_queue_info(
     Ok(dbq.into())
 }
 
-pub async fn get_queue_entries(
+/// Gets everyone waiting in a queue, along with the queue's version.
+pub async fn get_queue_state(
     queue_id: Uuid,
     pool: db::DbPool,
-) -> Result<Vec<QueueEntry>, ApiError> {
-    use db::schema::queue_rows::dsl;
+) -> Result<QueueState, ApiError> {
     let conn = &mut pool.get().await?;
+    load_state(queue_id, conn).await
+}
 
-    let db_rows = dsl::queue_rows
+/// Reads the version before the rows, so a change in between leaves the
+/// version looking older than the rows rather than newer. A change checked
+/// against it is then rejected instead of applied to stale rows.
+async fn load_state(
+    queue_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<QueueState, ApiError> {
+    use db::schema::{matches, queue_rows, queues};
+
+    let version = queues::table
+        .find(queue_id)
+        .select(queues::version)
+        .first::<i32>(conn)
+        .await?;
+    let records = queue_rows::table
+        .filter(queue_rows::queue_id.eq(queue_id))
+        .order(queue_rows::queue_order.asc())
+        .load::<RowRecord>(conn)
+        .await?;
+    let db_rows = load_rows(records, conn).await?;
+    let mut entries = load_entries(db_rows, conn).await?;
+    let stations: Vec<Station> = load_stations(queue_id, conn)
+        .await?
+        .into_iter()
+        .map(Station::from)
+        .collect();
+    let recent = matches::table
+        .filter(matches::queue_id.eq(queue_id))
+        .order(matches::ended_at.desc())
+        .limit(estimate::RECENT_MATCHES)
+        .select((matches::started_at, matches::ended_at))
+        .load::<(
+            chrono::DateTime<chrono::Utc>,
+            chrono::DateTime<chrono::Utc>,
+        )>(conn)
+        .await?;
+    // Not `first()`, which diesel also has
+    let last_ended_at = recent
+        .iter()
+        .map(|(_, ended_at)| *ended_at)
+        .next();
+    estimate::estimate_starts(
+        &mut entries,
+        &stations,
+        last_ended_at,
+        estimate::median_duration(&recent),
+        chrono::Utc::now(),
+    );
+    Ok(QueueState {
+        version,
+        entries,
+        stations,
+    })
+}
+
+/// Loads a queue's stations, oldest first.
+async fn load_stations(
+    queue_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<Vec<db::Station>, ApiError> {
+    use db::schema::stations::dsl;
+    Ok(dsl::stations
         .filter(dsl::queue_id.eq(queue_id))
-        .order(dsl::queue_order.asc())
-        .load::<db::QueueRow>(conn)
+        .order((dsl::created_at.asc(), dsl::id.asc()))
+        .load(conn)
+        .await?)
+}
+
+/// Loads the slots of stored rows, keeping the rows' order.
+async fn load_rows(
+    records: Vec<RowRecord>,
+    conn: &mut AsyncPgConnection,
+) -> Result<Vec<QueueRow>, ApiError> {
+    use db::schema::row_slots::dsl;
+
+    let row_ids: Vec<Uuid> = records
+        .iter()
+        .map(|r| r.id)
+        .collect();
+    let slots = dsl::row_slots
+        .filter(dsl::row_id.eq_any(row_ids))
+        .load::<RowSlot>(conn)
+        .await?;
+    Ok(QueueRow::assemble(records, slots))
+}
+
+/// Loads one row along with its slots.
+async fn load_row(
+    row_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<QueueRow, ApiError> {
+    use db::schema::queue_rows::dsl;
+
+    let record = dsl::queue_rows
+        .find(row_id)
+        .first::<RowRecord>(conn)
+        .await?;
+    load_rows(vec![record], conn)
+        .await?
+        .pop()
+        .ok_or(ApiError::NotFound)
+}
+
+/// How many players make up each side of a queue's rows.
+async fn team_size(
+    queue_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<i32, ApiError> {
+    use db::schema::queues::dsl;
+    Ok(dsl::queues
+        .find(queue_id)
+        .select(dsl::team_size)
+        .first(conn)
+        .await?)
+}
+
+/// Fails with [`ApiError::InvalidSlot`] unless rows have `slot` when their
+/// sides hold `team_size` players.
+pub(crate) fn check_slot(slot: Slot, team_size: i32) -> Result<(), ApiError> {
+    if !(0..team_size).contains(&slot.index) {
+        return Err(ApiError::InvalidSlot { slot, team_size });
+    }
+    Ok(())
+}
+
+/// Fails unless a queue with `settings` lets `player_id` into one more row,
+/// given the queue's current `rows`. `new_row` is whether joining adds a row
+/// rather than filling a slot of an existing one.
+///
+/// Callers hold the queue's lock, so two joins can't both pass. There's no
+/// database constraint behind [`ApiError::AlreadyQueued`]: moves write the
+/// row a player goes to before the one they leave, and rows that were
+/// already duplicated before the rule was turned on must stay editable.
+pub(crate) fn check_can_join(
+    settings: &QueueSettings,
+    rows: &[QueueRow],
+    player_id: Uuid,
+    new_row: bool,
+) -> Result<(), ApiError> {
+    if settings.status != QueueStatus::Open {
+        return Err(ApiError::QueueClosed {
+            status: settings.status,
+        });
+    }
+    if settings.unique_players {
+        let queued = rows
+            .iter()
+            .filter(|row| row.contains(player_id))
+            .min_by_key(|row| row.queue_order);
+        if let Some(row) = queued {
+            return Err(ApiError::AlreadyQueued {
+                row_id: row.id,
+                order: row.queue_order,
+            });
+        }
+    }
+    if let Some(max_rows) = settings.max_rows {
+        if new_row && rows.len() >= max_rows as usize {
+            return Err(ApiError::QueueFull { max_rows });
+        }
+    }
+    if let Some(max_entries) = settings.max_entries_per_player {
+        let entries = rows
+            .iter()
+            .filter(|row| row.contains(player_id))
+            .count();
+        if entries >= max_entries as usize {
+            return Err(ApiError::PlayerLimit { max_entries });
+        }
+    }
+    Ok(())
+}
+
+/// Loads a queue's rows and fails unless its settings let `player_id` join,
+/// as in [`check_can_join`]. The caller must hold the queue's lock, taken by
+/// [`bump_version`], so nobody else joins between the check and the insert.
+async fn check_queue_can_join(
+    queue_id: Uuid,
+    player_id: Uuid,
+    new_row: bool,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    use db::schema::queue_rows;
+    let settings = queue_settings(queue_id, conn).await?;
+    let records = queue_rows::table
+        .filter(queue_rows::queue_id.eq(queue_id))
+        .load::<RowRecord>(conn)
+        .await?;
+    let rows = load_rows(records, conn).await?;
+    check_can_join(&settings, &rows, player_id, new_row)
+}
+
+/// Who may join a queue, and how much of it they may take up.
+async fn queue_settings(
+    queue_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<QueueSettings, ApiError> {
+    use db::schema::queues::dsl;
+    let queue: QueueInfo = dsl::queues
+        .find(queue_id)
+        .first::<db::Queue>(conn)
+        .await?
+        .into();
+    Ok(queue.settings)
+}
+
+/// Locks a queue until the transaction ends, returning its version.
+async fn lock_queue(
+    queue_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<i32, ApiError> {
+    use db::schema::queues::dsl;
+    Ok(dsl::queues
+        .find(queue_id)
+        .select(dsl::version)
+        .for_update()
+        .first::<i32>(conn)
+        .await?)
+}
+
+/// Locks a queue until the transaction ends and returns its version.
+///
+/// Fails with [`ApiError::Conflict`] if `expected_version` is given and the
+/// queue has changed since.
+async fn check_version(
+    queue_id: Uuid,
+    expected_version: Option<i32>,
+    conn: &mut AsyncPgConnection,
+) -> Result<i32, ApiError> {
+    let version = lock_queue(queue_id, conn).await?;
+    if expected_version.is_some_and(|expected| expected != version) {
+        return Err(ApiError::Conflict {
+            current: load_state(queue_id, conn).await?,
+        });
+    }
+    Ok(version)
+}
+
+/// Locks a queue until the transaction ends and bumps its version, so
+/// changes to the same queue take turns. Checks `expected_version` like
+/// [`check_version`].
+async fn bump_version(
+    queue_id: Uuid,
+    expected_version: Option<i32>,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    use db::schema::queues::dsl;
+
+    let version = check_version(queue_id, expected_version, conn).await?;
+    diesel::update(dsl::queues.find(queue_id))
+        .set(dsl::version.eq(version + 1))
+        .execute(conn)
         .await?;
+    Ok(())
+}
+
+/// Converts database rows to entries, loading the players they reference.
+async fn load_entries(
+    db_rows: Vec<QueueRow>,
+    conn: &mut AsyncPgConnection,
+) -> Result<Vec<QueueEntry>, ApiError> {
+    let player_ids = db_rows
+        .iter()
+        .flat_map(|r| r.players.values().copied());
+    let players = load_players(player_ids, conn).await?;
 
     let rows: Vec<QueueEntry> = db_rows
         .into_iter()
         // Throw out empty rows.
-        // This should already be enforced by the database.
+        // These should already have been deleted.
         .filter_map(|r| {
-            r.try_into()
+            let slots = lineup_players(&r.players, &players);
+            (r, slots)
+                .try_into()
                 .inspect_err(|e| error!("{e}"))
                 .ok()
         })
@@ -82,26 +411,449 @@ pub async fn get_queue_entries(
     Ok(rows)
 }
 
+/// Converts database matches to played matches, loading their players.
+async fn load_matches(
+    db_matches: Vec<db::Match>,
+    conn: &mut AsyncPgConnection,
+) -> Result<Vec<PlayedMatch>, ApiError> {
+    let player_ids = db_matches
+        .iter()
+        .flat_map(|m| m.players.values().copied());
+    let players = load_players(player_ids, conn).await?;
+
+    Ok(db_matches
+        .into_iter()
+        .map(|m| {
+            let slots = lineup_players(&m.players, &players);
+            (m, slots).into()
+        })
+        .collect())
+}
+
+/// Loads stored matches along with their slots, keeping their order.
+async fn load_match_slots(
+    records: Vec<db::MatchRecord>,
+    conn: &mut AsyncPgConnection,
+) -> Result<Vec<db::Match>, ApiError> {
+    use db::schema::match_slots::dsl;
+
+    let match_ids: Vec<Uuid> = records
+        .iter()
+        .map(|m| m.id)
+        .collect();
+    let slots = dsl::match_slots
+        .filter(dsl::match_id.eq_any(match_ids))
+        .load::<db::MatchSlot>(conn)
+        .await?;
+    Ok(db::Match::assemble(records, slots))
+}
+
+/// Looks up the players in a lineup, in slot order.
+pub(crate) fn lineup_players(
+    lineup: &Lineup,
+    players: &HashMap<Uuid, db::Player>,
+) -> Vec<(Slot, db::Player)> {
+    lineup
+        .iter()
+        .filter_map(|(&slot, id)| Some((slot, players.get(id)?.clone())))
+        .collect()
+}
+
+/// Loads the players with the given ids.
+async fn load_players(
+    player_ids: impl Iterator<Item = Uuid>,
+    conn: &mut AsyncPgConnection,
+) -> Result<HashMap<Uuid, db::Player>, ApiError> {
+    use db::schema::players::dsl;
+
+    let player_ids: Vec<Uuid> = player_ids.collect();
+    Ok(dsl::players
+        .filter(dsl::id.eq_any(player_ids))
+        .load::<db::Player>(conn)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect())
+}
+
+/// Finds the player with the given name, ignoring case, and tag, or creates
+/// one. A blank tag is the same as none, see [`clean_player_tag`].
+pub async fn get_or_create_player(
+    display_name: String,
+    tag: Option<String>,
+    pool: db::DbPool,
+) -> Result<Player, ApiError> {
+    use diesel::sql_types::{Nullable, Text};
+    let display_name = clean_player_name(&display_name)?;
+    let tag = clean_player_tag(tag.as_deref());
+    let conn = &mut pool.get().await?;
+
+    // The no-op update makes RETURNING yield the existing player on conflict.
+    let player: db::Player = diesel::sql_query(
+        "INSERT INTO players (display_name, tag) VALUES ($1, $2)
+        ON CONFLICT (LOWER(display_name), COALESCE(tag, ''))
+            DO UPDATE SET display_name = players.display_name
+        RETURNING *",
+    )
+    .bind::<Text, _>(display_name)
+    .bind::<Nullable<Text>, _>(tag)
+    .get_result(conn)
+    .await?;
+    Ok(player.into())
+}
+
+/// Trims a player's name, which can't be blank.
+pub(crate) fn clean_player_name(display_name: &str) -> Result<&str, ApiError> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() {
+        return Err(ApiError::BlankPlayerName);
+    }
+    Ok(display_name)
+}
+
+/// Trims a player's tag. Blank tags are stored as none, which the players
+/// index treats like an empty tag, so every store finds the same player.
+pub(crate) fn clean_player_tag(tag: Option<&str>) -> Option<&str> {
+    tag.map(str::trim)
+        .filter(|tag| !tag.is_empty())
+}
+
+/// Finds players whose name contains `query`, ignoring case.
+pub async fn search_players(
+    query: String,
+    pool: db::DbPool,
+) -> Result<Vec<Player>, ApiError> {
+    use db::schema::players::dsl;
+    let conn = &mut pool.get().await?;
+
+    // Escape LIKE wildcards so they match literally.
+    let pattern = format!(
+        "%{}%",
+        query
+            .trim()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    let players = dsl::players
+        .filter(dsl::display_name.ilike(pattern))
+        .order(dsl::display_name.asc())
+        .limit(10)
+        .load::<db::Player>(conn)
+        .await?;
+    Ok(players
+        .into_iter()
+        .map(Player::from)
+        .collect())
+}
+
+/// Creates a queue, returning it along with its admin token. Only a hash of
+/// the token is stored, so this is the only time it is available.
 pub async fn add_queue(
     display_name: String,
     url_name: String,
+    created_by: Option<Uuid>,
     pool: db::DbPool,
-) -> Result<QueueInfo, ApiError> {
+) -> Result<CreatedQueue, ApiError> {
     use crate::db::{NewQueue, Queue};
     use db::schema::queues;
+    use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
     let conn = &mut pool.get().await?;
 
+    let admin_token = new_admin_token();
     let new_queue = NewQueue {
         display_name,
         url_name,
+        admin_token_hash: Some(hash_token(&admin_token)),
+        created_by,
     };
     let queue: Queue = diesel::insert_into(queues::table)
         .values(&new_queue)
         .get_result(conn)
+        .await
+        .map_err(|e| match e {
+            DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ApiError::UrlNameTaken
+            }
+            e => e.into(),
+        })?;
+    Ok(CreatedQueue {
+        info: queue.into(),
+        admin_token,
+    })
+}
+
+pub(crate) fn new_admin_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Hashes a random secret token for storage. The tokens are long enough that
+/// a fast hash is fine, unlike passwords.
+pub(crate) fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+/// Checks whether the holder of `admin_token`, or the user `user_id`, may
+/// manage the queue. The user who created a queue can always manage it.
+pub async fn is_queue_admin(
+    queue_id: Uuid,
+    admin_token: Option<String>,
+    user_id: Option<Uuid>,
+    pool: db::DbPool,
+) -> Result<bool, ApiError> {
+    use db::schema::queues::dsl;
+    let conn = &mut pool.get().await?;
+
+    let (stored_hash, created_by): (Option<Vec<u8>>, Option<Uuid>) =
+        dsl::queues
+            .find(queue_id)
+            .select((dsl::admin_token_hash, dsl::created_by))
+            .first(conn)
+            .await?;
+    Ok(grants_admin(
+        stored_hash.as_deref(),
+        created_by,
+        admin_token,
+        user_id,
+    ))
+}
+
+/// The rules behind [`is_queue_admin`], given what's stored for the queue.
+pub(crate) fn grants_admin(
+    stored_hash: Option<&[u8]>,
+    created_by: Option<Uuid>,
+    admin_token: Option<String>,
+    user_id: Option<Uuid>,
+) -> bool {
+    if created_by.is_some() && created_by == user_id {
+        return true;
+    }
+    // Queues made before admin tokens existed were given one when they were
+    // migrated, so a queue without a hash is never open to everyone.
+    match (stored_hash, admin_token) {
+        (Some(hash), Some(token)) => hash == hash_token(&token),
+        _ => false,
+    }
+}
+
+/// Gets the queues a user created.
+pub async fn get_user_queues(
+    user_id: Uuid,
+    pool: db::DbPool,
+) -> Result<Vec<QueueInfo>, ApiError> {
+    use crate::db::Queue;
+    use db::schema::queues::dsl;
+    let conn = &mut pool.get().await?;
+
+    let queues: Vec<Queue> = dsl::queues
+        .filter(dsl::created_by.eq(user_id))
+        .order(dsl::created_at.desc())
+        .get_results(conn)
+        .await?;
+    Ok(queues
+        .into_iter()
+        .map(QueueInfo::from)
+        .collect())
+}
+
+/// Creates an organizer account.
+pub async fn create_user(
+    username: String,
+    password: String,
+    pool: db::DbPool,
+) -> Result<User, ApiError> {
+    use crate::db::NewUser;
+    use db::schema::users;
+    use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+
+    let username = validate_new_user(&username, &password)?;
+    let password_hash = hash_password(password).await?;
+
+    let conn = &mut pool.get().await?;
+    let user: db::User = diesel::insert_into(users::table)
+        .values(&NewUser {
+            username,
+            password_hash,
+        })
+        .get_result(conn)
+        .await
+        .map_err(|e| match e {
+            DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ApiError::UsernameTaken
+            }
+            e => e.into(),
+        })?;
+    Ok(user.into())
+}
+
+/// Checks the details for a new account, returning the trimmed username.
+pub(crate) fn validate_new_user(
+    username: &str,
+    password: &str,
+) -> Result<String, ApiError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(ApiError::BlankUsername);
+    }
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ApiError::WeakPassword);
+    }
+    Ok(username.to_string())
+}
+
+/// Checks a username and password, ignoring the username's case.
+pub async fn verify_login(
+    username: String,
+    password: String,
+    pool: db::DbPool,
+) -> Result<User, ApiError> {
+    use db::schema::users::dsl;
+    use diesel::dsl::sql;
+    use diesel::sql_types::Text;
+    let conn = &mut pool.get().await?;
+
+    let user: db::User = dsl::users
+        .filter(
+            sql::<Text>("LOWER(username)").eq(username.trim().to_lowercase()),
+        )
+        .first(conn)
+        .await
+        .optional()?
+        .ok_or(ApiError::InvalidCredentials)?;
+    if !verify_password(password, user.password_hash.clone()).await? {
+        return Err(ApiError::InvalidCredentials);
+    }
+    Ok(user.into())
+}
+
+// Argon2 is deliberately slow, so keep it off the async runtime's threads.
+pub(crate) async fn hash_password(
+    password: String,
+) -> Result<String, ApiError> {
+    use argon2::password_hash::SaltString;
+    use argon2::{Argon2, PasswordHasher};
+
+    tokio::task::spawn_blocking(move || {
+        // v4 UUIDs are random enough for a salt
+        let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+            .map_err(|e| ApiError::PasswordHashError(e.to_string()))?;
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| ApiError::PasswordHashError(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::PasswordHashError(e.to_string()))?
+}
+
+pub(crate) async fn verify_password(
+    password: String,
+    password_hash: String,
+) -> Result<bool, ApiError> {
+    use argon2::{Argon2, PasswordHash, PasswordVerifier};
+
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&password_hash)
+            .map_err(|e| ApiError::PasswordHashError(e.to_string()))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|e| ApiError::PasswordHashError(e.to_string()))?
+}
+
+/// Starts a session for a user, returning the token that identifies it.
+/// Expired sessions are cleaned up along the way.
+pub async fn create_session(
+    user_id: Uuid,
+    pool: db::DbPool,
+) -> Result<String, ApiError> {
+    use crate::db::NewSession;
+    use db::schema::sessions::{self, dsl};
+    let conn = &mut pool.get().await?;
+
+    let now = chrono::Utc::now();
+    diesel::delete(dsl::sessions.filter(dsl::expires_at.le(now)))
+        .execute(conn)
+        .await?;
+    let token = new_session_token();
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            token_hash: hash_token(&token),
+            user_id,
+            expires_at: now + SESSION_LENGTH,
+        })
+        .execute(conn)
         .await?;
-    Ok(queue.into())
+    Ok(token)
 }
 
+pub(crate) fn new_session_token() -> String {
+    format!(
+        "{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Gets the user a session token belongs to, if the session hasn't expired.
+pub async fn get_session_user(
+    token: String,
+    pool: db::DbPool,
+) -> Result<Option<User>, ApiError> {
+    use db::schema::{sessions, users};
+    let conn = &mut pool.get().await?;
+
+    let user: Option<db::User> = sessions::table
+        .inner_join(users::table)
+        .filter(sessions::token_hash.eq(hash_token(&token)))
+        .filter(sessions::expires_at.gt(chrono::Utc::now()))
+        .select(db::User::as_select())
+        .first(conn)
+        .await
+        .optional()?;
+    Ok(user.map(User::from))
+}
+
+/// Ends a session.
+pub async fn delete_session(
+    token: String,
+    pool: db::DbPool,
+) -> Result<(), ApiError> {
+    use db::schema::sessions::dsl;
+    let conn = &mut pool.get().await?;
+    diesel::delete(
+        dsl::sessions.filter(dsl::token_hash.eq(hash_token(&token))),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Gets the id of the queue a row belongs to.
+pub async fn get_row_queue_id(
+    row_id: Uuid,
+    pool: db::DbPool,
+) -> Result<Uuid, ApiError> {
+    let conn = &mut pool.get().await?;
+    row_queue_id(row_id, conn).await
+}
+
+async fn row_queue_id(
+    row_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<Uuid, ApiError> {
+    use db::schema::queue_rows::dsl;
+    Ok(dsl::queue_rows
+        .find(row_id)
+        .select(dsl::queue_id)
+        .first(conn)
+        .await?)
+}
+
+/// Deletes a queue and everything in it. This has no expected version: the
+/// queue goes whatever state it's in, so there's nothing to be stale about.
 pub async fn delete_queue(
     queue_id: Uuid,
     pool: db::DbPool,
@@ -115,73 +867,1410 @@ pub async fn delete_queue(
     Ok(())
 }
 
-/// Adds a player to an existing queue row.
+/// Adds a player to an empty slot of an existing queue row, returning the row
+/// as it is afterwards.
+///
+/// The slot is only filled if it's still empty when the insert runs, so two
+/// people joining the same slot at once can't overwrite each other. Joining
+/// doesn't depend on the rest of the queue, so there's no expected version.
 pub async fn add_player_to_row(
     row_id: Uuid,
-    player: String,
-    side: Side,
+    player_id: Uuid,
+    slot: Slot,
+    pool: db::DbPool,
+) -> Result<QueueEntry, ApiError> {
+    use db::schema::{queue_rows::dsl, row_slots};
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = row_queue_id(row_id, conn).await?;
+            bump_version(queue_id, None, conn).await?;
+            check_slot(slot, team_size(queue_id, conn).await?)?;
+            check_queue_can_join(queue_id, player_id, false, conn).await?;
+            let inserted = diesel::insert_into(row_slots::table)
+                .values(RowSlot {
+                    row_id,
+                    side: slot.side.to_string(),
+                    slot_index: slot.index,
+                    player_id,
+                    checked_in: false,
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)
+                .await?;
+            // Bumping the row's version also tells listeners it changed.
+            let record: RowRecord =
+                diesel::update(dsl::queue_rows.find(row_id))
+                    .set(dsl::version.eq(dsl::version + 1))
+                    .returning(RowRecord::as_returning())
+                    .get_result(conn)
+                    .await?;
+            if inserted == 0 {
+                return Err(ApiError::Occupied {
+                    row_id,
+                    order: record.queue_order,
+                    slot,
+                });
+            }
+            let db_rows = load_rows(vec![record], conn).await?;
+            load_entries(db_rows, conn)
+                .await?
+                .pop()
+                .ok_or(ApiError::NotFound)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Adds a row with one player to the back of a queue. Like joining a row,
+/// this has no expected version.
+///
+/// The queue is locked while the row is numbered, so concurrent adds to the
+/// same queue take turns instead of picking the same order.
+pub async fn add_row(
+    queue_id: Uuid,
+    player_id: Uuid,
+    slot: Slot,
     pool: db::DbPool,
 ) -> Result<Uuid, ApiError> {
+    use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, None, conn).await?;
+            check_slot(slot, team_size(queue_id, conn).await?)?;
+            check_queue_can_join(queue_id, player_id, true, conn).await?;
+            let players = Lineup::from([(slot, player_id)]);
+            insert_row_at_back(queue_id, &players, conn).await
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(|e| match e {
+        // Shouldn't happen with the lock, but don't surface it raw if it does
+        ApiError::DieselError(DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            _,
+        )) => ApiError::QueueBusy { queue_id },
+        e => e,
+    })
+}
+
+/// Inserts a row with the given players after the last row of a queue. The
+/// queue must be locked by [`bump_version`] first.
+async fn insert_row_at_back(
+    queue_id: Uuid,
+    players: &Lineup,
+    conn: &mut AsyncPgConnection,
+) -> Result<Uuid, ApiError> {
+    use db::schema::row_slots;
+
+    let new_row = diesel::sql_query(
+        "INSERT INTO queue_rows (queue_id, queue_order)
+        SELECT $1, COALESCE(MAX(queue_order) + 1, 0)
+        FROM queue_rows
+        WHERE queue_id = $1
+        RETURNING id",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(queue_id)
+    .get_result::<NewRowId>(conn)
+    .await?;
+    let slots: Vec<RowSlot> = players
+        .iter()
+        .map(|(slot, &player_id)| RowSlot {
+            row_id: new_row.id,
+            side: slot.side.to_string(),
+            slot_index: slot.index,
+            player_id,
+            checked_in: false,
+        })
+        .collect();
+    diesel::insert_into(row_slots::table)
+        .values(slots)
+        .execute(conn)
+        .await?;
+    Ok(new_row.id)
+}
+
+#[derive(QueryableByName)]
+struct NewRowId {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    id: Uuid,
+}
+
+/// Adds a player to a singles queue, pairing them with whoever has been
+/// waiting for a partner the longest. If nobody is, they wait in a new row
+/// at the back. Returns the row they ended up in.
+///
+/// The queue is locked like in [`add_row`], so two players joining at once
+/// can't both take the same partner. Like any join, this has no expected
+/// version.
+pub async fn join_queue(
+    queue_id: Uuid,
+    player_id: Uuid,
+    pool: db::DbPool,
+) -> Result<QueueEntry, ApiError> {
     use db::schema::queue_rows::dsl;
     let conn = &mut pool.get().await?;
 
-    // If a row ID is provided, query it and attempt to add the player
-    let mut db_row: QueueRow = dsl::queue_rows
-        .filter(dsl::id.eq(row_id))
-        .first::<QueueRow>(conn)
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, None, conn).await?;
+            let team_size = team_size(queue_id, conn).await?;
+            let records = dsl::queue_rows
+                .filter(dsl::queue_id.eq(queue_id))
+                .order(dsl::queue_order)
+                .load::<RowRecord>(conn)
+                .await?;
+            let waiting = load_rows(records, conn).await?;
+            let settings = queue_settings(queue_id, conn).await?;
+            let partner = find_partner(waiting.clone(), player_id, team_size);
+            check_can_join(&settings, &waiting, player_id, partner.is_none())?;
+            let db_row = match partner {
+                Some((mut row, slot)) => {
+                    row.players.insert(slot, player_id);
+                    write_row(&mut row, conn).await?;
+                    row
+                }
+                None => {
+                    let players =
+                        Lineup::from([(Side::Left.into(), player_id)]);
+                    let row_id =
+                        insert_row_at_back(queue_id, &players, conn).await?;
+                    load_row(row_id, conn).await?
+                }
+            };
+            load_entries(vec![db_row], conn)
+                .await?
+                .pop()
+                .ok_or(ApiError::NotFound)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// The first of `rows`, front first, that still has room and doesn't
+/// already have `player_id` in it, along with the slot left open for them.
+/// Sides hold `team_size` players, and fill up left first.
+pub(crate) fn find_partner(
+    rows: Vec<QueueRow>,
+    player_id: Uuid,
+    team_size: i32,
+) -> Option<(QueueRow, Slot)> {
+    rows.into_iter()
+        .filter(|row| !row.contains(player_id))
+        .find_map(|row| {
+            let slot = row.open_slot(team_size)?;
+            Some((row, slot))
+        })
+}
+
+/// Clears one slot of a queue row.
+///
+/// Rows can't be empty, so if every other slot is already empty the whole row
+/// is deleted instead. Returns the updated entry, or `None` if the row was
+/// deleted.
+///
+/// This and the other changes below fail with [`ApiError::Conflict`] if
+/// `expected_version` is given and the queue has changed since.
+pub async fn remove_player_from_row(
+    row_id: Uuid,
+    slot: Slot,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<Option<QueueEntry>, ApiError> {
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = row_queue_id(row_id, conn).await?;
+            bump_version(queue_id, expected_version, conn).await?;
+            let mut db_row = load_row(row_id, conn).await?;
+            db_row.players.remove(&slot);
+            write_row(&mut db_row, conn).await?;
+            if db_row.is_empty() {
+                return Ok(None);
+            }
+            Ok(load_entries(vec![db_row], conn)
+                .await?
+                .pop())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Deletes a queue row and closes the gap it leaves in the queue order.
+pub async fn delete_row(
+    row_id: Uuid,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<(), ApiError> {
+    use db::schema::queue_rows::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = row_queue_id(row_id, conn).await?;
+            bump_version(queue_id, expected_version, conn).await?;
+            // Someone else may have deleted it while we waited for the lock
+            let deleted = diesel::delete(dsl::queue_rows.find(row_id))
+                .execute(conn)
+                .await?;
+            if deleted == 0 {
+                return Err(ApiError::NotFound);
+            }
+            renumber_rows(queue_id, conn).await
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Moves a row to `new_position` (0-indexed), shifting the rows in between.
+pub async fn move_row(
+    row_id: Uuid,
+    new_position: i32,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<(), ApiError> {
+    use db::schema::queue_rows::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = row_queue_id(row_id, conn).await?;
+            bump_version(queue_id, expected_version, conn).await?;
+            let orders = dsl::queue_rows
+                .filter(dsl::queue_id.eq(queue_id))
+                .select((dsl::id, dsl::queue_order))
+                .order(dsl::queue_order.asc())
+                .load::<(Uuid, i32)>(conn)
+                .await?;
+            // The row may have been deleted while we waited for the lock.
+            let old_position = orders
+                .iter()
+                .position(|(id, _)| *id == row_id)
+                .ok_or(ApiError::NotFound)?
+                as i32;
+            let last = orders.len() as i32 - 1;
+            if !(0..=last).contains(&new_position) {
+                return Err(ApiError::InvalidOrder {
+                    expected: last,
+                    got: new_position,
+                });
+            }
+            if old_position == new_position {
+                return Ok(());
+            }
+            renumber_rows(queue_id, conn).await?;
+            shift_row(queue_id, row_id, old_position, new_position, conn).await
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Moves a row from `old_position` to `new_position` in a contiguously
+/// numbered queue, shifting the rows in between.
+async fn shift_row(
+    queue_id: Uuid,
+    row_id: Uuid,
+    old_position: i32,
+    new_position: i32,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    // Uniqueness is checked at the end of the statement, so every row can be
+    // shifted at once.
+    diesel::sql_query(
+        "UPDATE queue_rows SET queue_order = CASE
+            WHEN id = $2 THEN $4
+            WHEN $3 < $4 THEN queue_order - 1
+            ELSE queue_order + 1
+        END,
+        version = version + 1
+        WHERE queue_id = $1
+            AND queue_order BETWEEN LEAST($3, $4) AND GREATEST($3, $4)",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(queue_id)
+    .bind::<diesel::sql_types::Uuid, _>(row_id)
+    .bind::<diesel::sql_types::Integer, _>(old_position)
+    .bind::<diesel::sql_types::Integer, _>(new_position)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Moves a player from one slot to another, possibly in a different row of
+/// the same queue. Moving into another queue would skip its join checks, so
+/// it fails with [`ApiError::DifferentQueues`].
+///
+/// If the target slot is occupied the two players are swapped when `swap` is
+/// set, and [`ApiError::Occupied`] is returned otherwise. A row left empty by
+/// the move is deleted.
+pub async fn move_player(
+    from_row: Uuid,
+    from_slot: Slot,
+    to_row: Uuid,
+    to_slot: Slot,
+    swap: bool,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<(), ApiError> {
+    use db::schema::queue_rows::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            if from_row == to_row && from_slot == to_slot {
+                return Ok(());
+            }
+            let queue_id = row_queue_id(from_row, conn).await?;
+            if row_queue_id(to_row, conn).await? != queue_id {
+                return Err(ApiError::DifferentQueues);
+            }
+            bump_version(queue_id, expected_version, conn).await?;
+            check_slot(to_slot, team_size(queue_id, conn).await?)?;
+            // Lock both rows in a consistent order to avoid deadlocks.
+            let records = dsl::queue_rows
+                .filter(dsl::id.eq_any([from_row, to_row]))
+                .order(dsl::id.asc())
+                .for_update()
+                .load::<RowRecord>(conn)
+                .await?;
+            let mut rows = load_rows(records, conn).await?;
+            let find_row = |rows: &[QueueRow], id| {
+                rows.iter()
+                    .position(|r| r.id == id)
+                    .ok_or(ApiError::NotFound)
+            };
+            let from_index = find_row(&rows, from_row)?;
+            let to_index = find_row(&rows, to_row)?;
+
+            let Some(player) = rows[from_index]
+                .players
+                .remove(&from_slot)
+            else {
+                return Err(ApiError::EmptySlot {
+                    row_id: from_row,
+                    slot: from_slot,
+                });
+            };
+            let displaced = rows[to_index]
+                .players
+                .insert(to_slot, player);
+            if displaced.is_some() && !swap {
+                return Err(ApiError::Occupied {
+                    row_id: to_row,
+                    order: rows[to_index].queue_order,
+                    slot: to_slot,
+                });
+            }
+            if let Some(displaced) = displaced {
+                rows[from_index]
+                    .players
+                    .insert(from_slot, displaced);
+            }
+
+            // Write the target first so a row that loses its last player is
+            // only deleted once the player has somewhere to go.
+            let mut to = rows[to_index].clone();
+            write_row(&mut to, conn).await?;
+            if from_index != to_index {
+                write_row(&mut rows[from_index], conn).await?;
+            }
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Ends the match at the front of a queue, records it with the side that
+/// won, if given, and moves the queue along as the queue's mode says. See
+/// [`rotate`].
+///
+/// Returns the entry as it was when it played, or `None` if the queue was
+/// already empty. Passing `expected_version` keeps a double-tapped "Match
+/// finished" from advancing twice.
+pub async fn complete_front_row(
+    queue_id: Uuid,
+    winner: Option<Side>,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<Option<QueueEntry>, ApiError> {
+    use db::schema::{matches, queue_rows::dsl, queues};
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, expected_version, conn).await?;
+            // The front row may be on a station, which would never be freed.
+            if !load_stations(queue_id, conn)
+                .await?
+                .is_empty()
+            {
+                return Err(ApiError::StationRequired { queue_id });
+            }
+            let queue: QueueInfo = queues::table
+                .find(queue_id)
+                .first::<db::Queue>(conn)
+                .await?
+                .into();
+            let records = dsl::queue_rows
+                .filter(dsl::queue_id.eq(queue_id))
+                .order(dsl::queue_order.asc())
+                .limit(2)
+                .load::<RowRecord>(conn)
+                .await?;
+            let mut front_rows = load_rows(records, conn)
+                .await?
+                .into_iter();
+            let Some(front) = front_rows.next() else {
+                return Ok(None);
+            };
+            let recent = matches::table
+                .filter(matches::queue_id.eq(queue_id))
+                .order(matches::ended_at.desc())
+                .limit(recent_matches_needed(&queue))
+                .load::<db::MatchRecord>(conn)
+                .await?;
+            let recent = load_match_slots(recent, conn).await?;
+            // Not `first()`, which diesel also has
+            let last_ended_at = recent
+                .iter()
+                .map(|m| m.ended_at)
+                .next();
+            let played = db::Match::finished(&front, winner, last_ended_at)?;
+            record_match(&played, conn).await?;
+            let entry = load_entries(vec![front.clone()], conn)
+                .await?
+                .pop();
+
+            let front_id = front.id;
+            let rotation =
+                rotate(&queue, front, front_rows.next(), winner, &recent);
+            match rotation.front {
+                Some(mut row) => write_row(&mut row, conn).await?,
+                None => {
+                    diesel::delete(dsl::queue_rows.find(front_id))
+                        .execute(conn)
+                        .await?;
+                    renumber_rows(queue_id, conn).await?;
+                }
+            }
+            if let Some(mut row) = rotation.next {
+                write_row(&mut row, conn).await?;
+            }
+            if let Some(players) = rotation.to_back {
+                insert_row_at_back(queue_id, &players, conn).await?;
+            }
+            Ok(entry)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Saves a match that just ended along with its players, and updates their
+/// ratings.
+async fn record_match(
+    played: &db::Match,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    use db::schema::{match_slots, matches};
+    diesel::insert_into(matches::table)
+        .values(played.record())
+        .execute(conn)
+        .await?;
+    diesel::insert_into(match_slots::table)
+        .values(played.slots())
+        .execute(conn)
+        .await?;
+    update_ratings(played, conn).await
+}
+
+/// Updates the ratings of everyone who played in a match that just ended,
+/// and records how each one moved.
+async fn update_ratings(
+    played: &db::Match,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    use db::schema::{rating_changes, ratings::dsl};
+    use diesel::upsert::excluded;
+    let current = dsl::ratings
+        .filter(dsl::queue_id.eq(played.queue_id))
+        .filter(dsl::player_id.eq_any(played.players.values().copied()))
+        .load::<db::Rating>(conn)
         .await?;
-    let target_slot = match side {
-        Side::Left => &mut db_row.left_player_name,
-        Side::Right => &mut db_row.right_player_name,
-    };
-    if target_slot.is_some() {
-        return Err(ApiError::Occupied {
-            row_id: db_row.id,
-            order: db_row.queue_order,
-            side,
-        });
+    let (ratings, changes): (Vec<_>, Vec<_>) = played
+        .rate(&current)
+        .into_iter()
+        .unzip();
+    if ratings.is_empty() {
+        return Ok(());
     }
-    *target_slot = Some(player);
-    // It might be possible to avoid this 2nd dbrt with some database
-    // shenanigans, but I don't think it's worth figuring out right now.
-    diesel::update(dsl::queue_rows.find(row_id))
-        .set(&db_row)
+    diesel::insert_into(dsl::ratings)
+        .values(ratings)
+        .on_conflict((dsl::queue_id, dsl::player_id))
+        .do_update()
+        .set((
+            dsl::rating.eq(excluded(dsl::rating)),
+            dsl::games.eq(excluded(dsl::games)),
+            dsl::wins.eq(excluded(dsl::wins)),
+        ))
+        .execute(conn)
+        .await?;
+    diesel::insert_into(rating_changes::table)
+        .values(changes)
         .execute(conn)
         .await?;
-    return Ok(db_row.id);
+    Ok(())
 }
 
-pub async fn add_row(
+/// How the front of a queue changes when its match ends, worked out by
+/// [`rotate`].
+pub(crate) struct Rotation {
+    /// The front row after the match, or `None` if everyone in it leaves.
+    pub front: Option<QueueRow>,
+    /// The row behind it, if a team moved up out of it. It may be left
+    /// empty.
+    pub next: Option<QueueRow>,
+    /// A team that goes to the back of the queue in a row of their own, in
+    /// the slots they had.
+    pub to_back: Option<Lineup>,
+}
+
+/// Works out who plays next when the match at the front of a queue ends.
+///
+/// Whoever stays on in the queue's mode keeps their place in the front row,
+/// and the first team waiting in the `next` row takes the other side,
+/// preferring the one already on that side. Once a team has stayed on for
+/// `max_streak` matches in a row they go to the back of the queue instead.
+/// When nobody stays on, the whole front row leaves and `next` plays.
+///
+/// `recent` holds the queue's earlier matches, newest first, which is where
+/// a streak is counted from. See [`recent_matches_needed`].
+pub(crate) fn rotate(
+    queue: &QueueInfo,
+    mut front: QueueRow,
+    mut next: Option<QueueRow>,
+    winner: Option<Side>,
+    recent: &[db::Match],
+) -> Rotation {
+    let everyone_leaves = Rotation {
+        front: None,
+        next: None,
+        to_back: None,
+    };
+    let Some(staying_side) = queue.mode.staying_side(winner) else {
+        return everyone_leaves;
+    };
+    let stayers = front.team(staying_side);
+    if stayers.is_empty() {
+        return everyone_leaves;
+    }
+    let stayed = |m: &db::Match| {
+        queue
+            .mode
+            .staying_side(m.winner)
+            .map(|side| m.team(side))
+            == Some(stayers.clone())
+    };
+    let streak = 1 + recent
+        .iter()
+        .take_while(|m| stayed(m))
+        .count();
+    if queue
+        .max_streak
+        .is_some_and(|max| streak >= max as usize)
+    {
+        return Rotation {
+            to_back: Some(front.take_team(staying_side)),
+            ..everyone_leaves
+        };
+    }
+
+    let open_side = staying_side.other();
+    let challengers = next
+        .as_mut()
+        .map(|next| {
+            let side = if next.team(open_side).is_empty() {
+                staying_side
+            } else {
+                open_side
+            };
+            next.take_team(side)
+        })
+        .unwrap_or_default();
+    let moved_up = !challengers.is_empty();
+    front.take_team(open_side);
+    for (slot, player_id) in challengers {
+        front
+            .players
+            .insert(Slot::new(open_side, slot.index), player_id);
+    }
+    // Whoever stays on is called again for the next match.
+    front.uncall();
+    Rotation {
+        front: Some(front),
+        next: next.filter(|_| moved_up),
+        to_back: None,
+    }
+}
+
+/// How many of a queue's latest matches [`rotate`] needs to count a streak.
+/// Always at least one, since the last match is when the next one starts.
+pub(crate) fn recent_matches_needed(queue: &QueueInfo) -> i64 {
+    queue
+        .max_streak
+        .map_or(1, |max| max.max(1) as i64)
+}
+
+/// Saves a row and its slots, bumping its version, or deletes it and closes
+/// the gap if it's empty.
+async fn write_row(
+    row: &mut QueueRow,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    use db::schema::{queue_rows::dsl, row_slots};
+    if row.is_empty() {
+        diesel::delete(dsl::queue_rows.find(row.id))
+            .execute(conn)
+            .await?;
+        renumber_rows(row.queue_id, conn).await
+    } else {
+        row.version += 1;
+        diesel::update(dsl::queue_rows.find(row.id))
+            .set(row.record())
+            .execute(conn)
+            .await?;
+        diesel::delete(row_slots::table.filter(row_slots::row_id.eq(row.id)))
+            .execute(conn)
+            .await?;
+        diesel::insert_into(row_slots::table)
+            .values(row.slots())
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Changes how a queue moves along when a match ends, whether players join
+/// it alone, and how many players make up a side. `max_streak` must be
+/// positive if given, and `team_size` between 1 and [`MAX_TEAM_SIZE`].
+pub async fn set_queue_mode(
     queue_id: Uuid,
-    player: String,
-    side: Side,
+    mode: QueueMode,
+    max_streak: Option<i32>,
+    singles: bool,
+    team_size: i32,
+    expected_version: Option<i32>,
     pool: db::DbPool,
-) -> Result<Uuid, ApiError> {
-    use db::schema::queue_rows::dsl;
+) -> Result<QueueInfo, ApiError> {
+    use db::schema::queues::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, expected_version, conn).await?;
+            let queue: db::Queue = diesel::update(dsl::queues.find(queue_id))
+                .set((
+                    dsl::mode.eq(mode.to_string()),
+                    dsl::max_streak.eq(max_streak),
+                    dsl::singles.eq(singles),
+                    dsl::team_size.eq(team_size),
+                ))
+                .get_result(conn)
+                .await?;
+            Ok(queue.into())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Sets how long the players of a queue's front row have to check in once
+/// they're called, and how many places a row moves back if they don't. With
+/// no `move_back` they leave the queue instead, and with no `timeout` rows
+/// aren't called at all. Both must be positive if given.
+///
+/// Any row already called is called afresh under the new rule.
+pub async fn set_no_show_rule(
+    queue_id: Uuid,
+    timeout: Option<i32>,
+    move_back: Option<i32>,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<QueueInfo, ApiError> {
+    use db::schema::{queue_rows, queues::dsl};
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, expected_version, conn).await?;
+            let queue: db::Queue = diesel::update(dsl::queues.find(queue_id))
+                .set((
+                    dsl::no_show_timeout.eq(timeout),
+                    dsl::no_show_move_back.eq(move_back),
+                ))
+                .get_result(conn)
+                .await?;
+            let called = queue_rows::table
+                .filter(queue_rows::queue_id.eq(queue_id))
+                .filter(queue_rows::called_at.is_not_null());
+            diesel::update(called)
+                .set((
+                    queue_rows::called_at
+                        .eq(None::<chrono::DateTime<chrono::Utc>>),
+                    queue_rows::version.eq(queue_rows::version + 1),
+                ))
+                .execute(conn)
+                .await?;
+            Ok(queue.into())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Sets who may join a queue and how much of it they may take up. Limits
+/// must be positive if given. Rows already in the queue stay, even if there
+/// are more of them than the new limits allow.
+pub async fn set_queue_settings(
+    queue_id: Uuid,
+    settings: QueueSettings,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<QueueInfo, ApiError> {
+    use db::schema::queues::dsl;
     let conn = &mut pool.get().await?;
 
-    // Create new row
-    let (left, right) = match side {
-        Side::Left => (Some(player), None),
-        Side::Right => (None, Some(player)),
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, expected_version, conn).await?;
+            let queue: db::Queue = diesel::update(dsl::queues.find(queue_id))
+                .set((
+                    dsl::max_rows.eq(settings.max_rows),
+                    dsl::max_entries_per_player
+                        .eq(settings.max_entries_per_player),
+                    dsl::status.eq(settings.status.to_string()),
+                    dsl::unique_players.eq(settings.unique_players),
+                ))
+                .get_result(conn)
+                .await?;
+            Ok(queue.into())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Checks in the player in one slot of a row that's been called, returning
+/// the row as it is afterwards. Like joining a row, this has no expected
+/// version.
+pub async fn check_in(
+    row_id: Uuid,
+    slot: Slot,
+    pool: db::DbPool,
+) -> Result<QueueEntry, ApiError> {
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = row_queue_id(row_id, conn).await?;
+            bump_version(queue_id, None, conn).await?;
+            let mut db_row = load_row(row_id, conn).await?;
+            check_in_player(&mut db_row, slot)?;
+            write_row(&mut db_row, conn).await?;
+            load_entries(vec![db_row], conn)
+                .await?
+                .pop()
+                .ok_or(ApiError::NotFound)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Marks the player in `slot` of a called row as checked in.
+pub(crate) fn check_in_player(
+    row: &mut QueueRow,
+    slot: Slot,
+) -> Result<(), ApiError> {
+    if row.called_at.is_none() {
+        return Err(ApiError::NotCalled { row_id: row.id });
+    }
+    let player_id = row
+        .player(slot)
+        .ok_or(ApiError::EmptySlot {
+            row_id: row.id,
+            slot,
+        })?;
+    row.checked_in.insert(player_id);
+    Ok(())
+}
+
+/// Calls the first waiting row of every queue with a no-show timeout, and
+/// moves back or drops the ones that haven't all checked in by `now`. See
+/// [`no_shows`].
+///
+/// A queue's version is only bumped if something in it changed, so checking
+/// doesn't get in the way of anyone's changes. A queue that can't be checked
+/// is logged and skipped, so it doesn't hold up the others.
+pub async fn check_no_shows(
+    now: chrono::DateTime<chrono::Utc>,
+    pool: db::DbPool,
+) -> Result<(), ApiError> {
+    use db::schema::{queue_rows::dsl, queues};
+    let conn = &mut pool.get().await?;
+
+    let timed: Vec<db::Queue> = queues::table
+        .filter(queues::no_show_timeout.is_not_null())
+        .load(conn)
+        .await?;
+    for queue in timed {
+        let queue_id = queue.id;
+        let checked = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let version = lock_queue(queue.id, conn).await?;
+                    let records = dsl::queue_rows
+                        .filter(dsl::queue_id.eq(queue.id))
+                        .order(dsl::queue_order.asc())
+                        .load::<RowRecord>(conn)
+                        .await?;
+                    let rows = load_rows(records, conn).await?;
+                    let stations = load_stations(queue.id, conn).await?;
+                    let check =
+                        no_shows(&queue.clone().into(), rows, &stations, now);
+                    if check.is_empty() {
+                        return Ok(());
+                    }
+                    diesel::update(queues::table.find(queue.id))
+                        .set(queues::version.eq(version + 1))
+                        .execute(conn)
+                        .await?;
+                    for mut row in check.changed {
+                        write_row(&mut row, conn).await?;
+                    }
+                    if let Some((row_id, from, to)) = check.move_back {
+                        shift_row(queue.id, row_id, from, to, conn).await?;
+                    }
+                    if let Some(row_id) = check.dropped {
+                        diesel::delete(dsl::queue_rows.find(row_id))
+                            .execute(conn)
+                            .await?;
+                        renumber_rows(queue.id, conn).await?;
+                    }
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await;
+        if let Err(e) = checked {
+            error!("Error checking queue {queue_id} for no-shows: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// What [`no_shows`] found needs doing to a queue.
+#[derive(Default)]
+pub(crate) struct NoShowCheck {
+    /// Rows called or uncalled, to be saved as they are.
+    pub changed: Vec<QueueRow>,
+    /// The first waiting row, which didn't check in in time and moves from
+    /// the first position given to the second. It's also in `changed`,
+    /// uncalled.
+    pub move_back: Option<(Uuid, i32, i32)>,
+    /// The first waiting row, which didn't check in in time and leaves the
+    /// queue.
+    pub dropped: Option<Uuid>,
+}
+
+impl NoShowCheck {
+    /// Whether the queue is fine as it is.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+            && self.move_back.is_none()
+            && self.dropped.is_none()
+    }
+}
+
+/// Works out who to call in a queue, front first in `rows`, and what happens
+/// to a called row that hasn't checked in by `now`.
+///
+/// The first row waiting, which is the front row unless rows are playing on
+/// `stations`, is called once both its sides have someone in them, and any
+/// other waiting row that was called, say because it was moved back, is
+/// uncalled. Rows on a station are left alone. When the queue's no-show
+/// timeout has passed since the row was called and anyone in it still
+/// hasn't checked in, the row moves back `no_show_move_back` places, or to
+/// the back if there aren't that many, and leaves the queue if the queue
+/// doesn't move rows back.
+pub(crate) fn no_shows(
+    queue: &QueueInfo,
+    rows: Vec<QueueRow>,
+    stations: &[db::Station],
+    now: chrono::DateTime<chrono::Utc>,
+) -> NoShowCheck {
+    let mut check = NoShowCheck::default();
+    let Some(timeout) = queue.no_show_timeout else {
+        return check;
     };
-    let max_order = dsl::queue_rows
-        .filter(dsl::queue_id.eq(queue_id))
-        .select(dsl::queue_order)
-        .order(dsl::queue_order.desc())
-        .first::<i32>(conn)
-        .await
-        .optional()?;
-    let new_row = db::NewQueueRow {
-        queue_id,
-        left_player_name: left,
-        right_player_name: right,
-        queue_order: max_order.map_or(0, |o| o + 1),
+    let last = rows.len() as i32 - 1;
+    let mut waiting = rows
+        .into_iter()
+        .enumerate()
+        .filter(|(_, r)| !on_station(r.id, stations));
+    let Some((position, mut front)) = waiting.next() else {
+        return check;
     };
-    let new_row_id = diesel::insert_into(dsl::queue_rows)
-        .values(&new_row)
-        .returning(dsl::id)
-        .get_result(conn)
+    for (_, mut row) in waiting.filter(|(_, r)| r.called_at.is_some()) {
+        row.uncall();
+        check.changed.push(row);
+    }
+    let ready = !front.team(Side::Left).is_empty()
+        && !front.team(Side::Right).is_empty();
+    match front.called_at {
+        None if ready => {
+            front.called_at = Some(now);
+            front.checked_in.clear();
+            check.changed.push(front);
+        }
+        Some(_) if !ready => {
+            front.uncall();
+            check.changed.push(front);
+        }
+        Some(called_at)
+            if now
+                >= called_at + chrono::TimeDelta::seconds(timeout.into())
+                && !front.all_checked_in() =>
+        {
+            match queue.no_show_move_back {
+                Some(places) => {
+                    let position = position as i32;
+                    check.move_back = Some((
+                        front.id,
+                        position,
+                        (position + places).min(last),
+                    ));
+                    front.uncall();
+                    check.changed.push(front);
+                }
+                None => check.dropped = Some(front.id),
+            }
+        }
+        _ => (),
+    }
+    check
+}
+
+/// Whether a row is playing on one of `stations`.
+pub(crate) fn on_station(row_id: Uuid, stations: &[db::Station]) -> bool {
+    stations
+        .iter()
+        .any(|s| s.row_id == Some(row_id))
+}
+
+/// The name a new station gets, counting the queue's `existing` ones if
+/// `name` is blank.
+pub(crate) fn station_name(name: &str, existing: usize) -> String {
+    match name.trim() {
+        "" => format!("Station {}", existing + 1),
+        name => name.to_string(),
+    }
+}
+
+/// Adds a free station to a queue.
+pub async fn add_station(
+    queue_id: Uuid,
+    name: String,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<Station, ApiError> {
+    use db::schema::stations::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            bump_version(queue_id, expected_version, conn).await?;
+            let existing = load_stations(queue_id, conn).await?;
+            let station = db::Station {
+                id: Uuid::new_v4(),
+                queue_id,
+                name: station_name(&name, existing.len()),
+                created_at: chrono::Utc::now(),
+                row_id: None,
+                started_at: None,
+            };
+            diesel::insert_into(dsl::stations)
+                .values(&station)
+                .execute(conn)
+                .await?;
+            Ok(station.into())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Removes a station. A row playing there waits again in its place.
+pub async fn delete_station(
+    station_id: Uuid,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<(), ApiError> {
+    use db::schema::stations::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = station_queue_id(station_id, conn).await?;
+            bump_version(queue_id, expected_version, conn).await?;
+            diesel::delete(dsl::stations.find(station_id))
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Gets the id of the queue a station belongs to.
+pub async fn get_station_queue_id(
+    station_id: Uuid,
+    pool: db::DbPool,
+) -> Result<Uuid, ApiError> {
+    let conn = &mut pool.get().await?;
+    station_queue_id(station_id, conn).await
+}
+
+async fn station_queue_id(
+    station_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<Uuid, ApiError> {
+    use db::schema::stations::dsl;
+    Ok(dsl::stations
+        .find(station_id)
+        .select(dsl::queue_id)
+        .first(conn)
+        .await?)
+}
+
+/// Starts the first waiting row of a queue on a free station, returning the
+/// row playing there, or `None` if nobody's waiting.
+///
+/// A station that's already playing keeps its row, so starting it twice is
+/// harmless. Changes to a queue take turns, so two stations started at once
+/// never get the same row.
+pub async fn assign_station(
+    station_id: Uuid,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<Option<QueueEntry>, ApiError> {
+    use db::schema::stations::dsl;
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = station_queue_id(station_id, conn).await?;
+            check_version(queue_id, expected_version, conn).await?;
+            let mut station = dsl::stations
+                .find(station_id)
+                .first::<db::Station>(conn)
+                .await?;
+            if station.row_id.is_none() {
+                fill_station(&mut station, conn).await?;
+                if station.row_id.is_some() {
+                    bump_version(queue_id, None, conn).await?;
+                }
+            }
+            let Some(row_id) = station.row_id else {
+                return Ok(None);
+            };
+            let row = load_row(row_id, conn).await?;
+            Ok(load_entries(vec![row], conn)
+                .await?
+                .pop())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Ends the match on a station, records it with the side that won, if
+/// given, and moves the station along as the queue's mode says.
+///
+/// Whoever stays on keeps the station and the first team waiting takes the
+/// other side, like at the front of a queue without stations. See
+/// [`rotate`]. Otherwise the row leaves the queue and the first waiting row
+/// starts on the station, which is left free if nobody's waiting.
+///
+/// Returns the entry as it was when it played, or `None` if the station was
+/// free, in which case the first waiting row starts there.
+pub async fn free_station(
+    station_id: Uuid,
+    winner: Option<Side>,
+    expected_version: Option<i32>,
+    pool: db::DbPool,
+) -> Result<Option<QueueEntry>, ApiError> {
+    use db::schema::{match_slots, matches, queue_rows::dsl, queues, stations};
+    let conn = &mut pool.get().await?;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            let queue_id = station_queue_id(station_id, conn).await?;
+            bump_version(queue_id, expected_version, conn).await?;
+            let mut station = stations::table
+                .find(station_id)
+                .first::<db::Station>(conn)
+                .await?;
+            let queue: QueueInfo = queues::table
+                .find(queue_id)
+                .first::<db::Queue>(conn)
+                .await?
+                .into();
+            let records = dsl::queue_rows
+                .filter(dsl::queue_id.eq(queue_id))
+                .order(dsl::queue_order.asc())
+                .load::<RowRecord>(conn)
+                .await?;
+            let all_stations = load_stations(queue_id, conn).await?;
+            let (playing, waiting): (Vec<_>, Vec<_>) = load_rows(records, conn)
+                .await?
+                .into_iter()
+                .partition(|r| on_station(r.id, &all_stations));
+            let Some(front) = playing
+                .into_iter()
+                .find(|r| station.row_id == Some(r.id))
+            else {
+                fill_station(&mut station, conn).await?;
+                return Ok(None);
+            };
+            // Other stations' matches don't break a streak, so only count
+            // the ones these players were in.
+            let player_ids: Vec<Uuid> = front
+                .players
+                .values()
+                .copied()
+                .collect();
+            let recent = matches::table
+                .filter(matches::queue_id.eq(queue_id))
+                .filter(
+                    matches::id.eq_any(
+                        match_slots::table
+                            .filter(match_slots::player_id.eq_any(player_ids))
+                            .select(match_slots::match_id),
+                    ),
+                )
+                .order(matches::ended_at.desc())
+                .limit(recent_matches_needed(&queue))
+                .load::<db::MatchRecord>(conn)
+                .await?;
+            let recent = load_match_slots(recent, conn).await?;
+            let played =
+                db::Match::finished(&front, winner, station.started_at)?;
+            record_match(&played, conn).await?;
+            let entry = load_entries(vec![front.clone()], conn)
+                .await?
+                .pop();
+
+            let front_id = front.id;
+            let rotation = rotate(
+                &queue,
+                front,
+                waiting.into_iter().next(),
+                winner,
+                &recent,
+            );
+            let stayed = match rotation.front {
+                Some(mut row) => {
+                    write_row(&mut row, conn).await?;
+                    true
+                }
+                None => {
+                    // Frees the station too.
+                    diesel::delete(dsl::queue_rows.find(front_id))
+                        .execute(conn)
+                        .await?;
+                    renumber_rows(queue_id, conn).await?;
+                    false
+                }
+            };
+            if let Some(mut row) = rotation.next {
+                write_row(&mut row, conn).await?;
+            }
+            if let Some(players) = rotation.to_back {
+                insert_row_at_back(queue_id, &players, conn).await?;
+            }
+            if stayed {
+                station.started_at = Some(chrono::Utc::now());
+                diesel::update(stations::table.find(station_id))
+                    .set(&station)
+                    .execute(conn)
+                    .await?;
+            } else {
+                fill_station(&mut station, conn).await?;
+            }
+            Ok(entry)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Starts the first row waiting in a station's queue on it, uncalling it
+/// now that it's playing, or frees the station if nobody's waiting. The
+/// queue must be locked.
+async fn fill_station(
+    station: &mut db::Station,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    use db::schema::{queue_rows, stations::dsl};
+    let stations = load_stations(station.queue_id, conn).await?;
+    let next_id = queue_rows::table
+        .filter(queue_rows::queue_id.eq(station.queue_id))
+        .order(queue_rows::queue_order.asc())
+        .select(queue_rows::id)
+        .load::<Uuid>(conn)
+        .await?
+        .into_iter()
+        .find(|&id| !on_station(id, &stations));
+    if let Some(row_id) = next_id {
+        let mut row = load_row(row_id, conn).await?;
+        if row.called_at.is_some() {
+            row.uncall();
+            write_row(&mut row, conn).await?;
+        }
+    }
+    station.row_id = next_id;
+    station.started_at = next_id.map(|_| chrono::Utc::now());
+    diesel::update(dsl::stations.find(station.id))
+        .set(&*station)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Gets a page of the matches played in a queue, most recent first.
+pub async fn get_matches(
+    queue_id: Uuid,
+    offset: i64,
+    limit: i64,
+    pool: db::DbPool,
+) -> Result<Vec<PlayedMatch>, ApiError> {
+    use db::schema::matches::dsl;
+    let conn = &mut pool.get().await?;
+
+    let records = dsl::matches
+        .filter(dsl::queue_id.eq(queue_id))
+        .order((dsl::ended_at.desc(), dsl::id.desc()))
+        .offset(offset)
+        .limit(limit)
+        .load::<db::MatchRecord>(conn)
+        .await?;
+    let db_matches = load_match_slots(records, conn).await?;
+    load_matches(db_matches, conn).await
+}
+
+/// Counts the matches each player finished in a queue since `since`.
+pub async fn get_games_played(
+    queue_id: Uuid,
+    since: chrono::DateTime<chrono::Utc>,
+    pool: db::DbPool,
+) -> Result<Vec<GamesPlayed>, ApiError> {
+    use db::schema::matches::dsl;
+    let conn = &mut pool.get().await?;
+
+    let records = dsl::matches
+        .filter(dsl::queue_id.eq(queue_id))
+        .filter(dsl::ended_at.ge(since))
+        .load::<db::MatchRecord>(conn)
         .await?;
-    Ok(new_row_id)
+    let db_matches = load_match_slots(records, conn).await?;
+    Ok(count_games(
+        &load_matches(db_matches, conn).await?,
+    ))
+}
+
+/// Gets everyone rated in a queue, highest rating first.
+pub async fn get_leaderboard(
+    queue_id: Uuid,
+    pool: db::DbPool,
+) -> Result<Vec<Standing>, ApiError> {
+    use db::schema::{players, ratings::dsl};
+    let conn = &mut pool.get().await?;
+
+    Ok(dsl::ratings
+        .inner_join(players::table)
+        .filter(dsl::queue_id.eq(queue_id))
+        .order((dsl::rating.desc(), players::display_name.asc()))
+        .select((db::Rating::as_select(), db::Player::as_select()))
+        .load::<(db::Rating, db::Player)>(conn)
+        .await?
+        .into_iter()
+        .map(Standing::from)
+        .collect())
+}
+
+/// Gets every rating change in a queue, oldest first.
+pub async fn get_rating_history(
+    queue_id: Uuid,
+    pool: db::DbPool,
+) -> Result<Vec<RatingPoint>, ApiError> {
+    use db::schema::{matches, rating_changes::dsl};
+    let conn = &mut pool.get().await?;
+
+    Ok(dsl::rating_changes
+        .inner_join(matches::table)
+        .filter(matches::queue_id.eq(queue_id))
+        .order((matches::ended_at.asc(), matches::id.asc()))
+        .select((
+            dsl::player_id,
+            matches::ended_at,
+            dsl::rating_after,
+        ))
+        .load::<(Uuid, chrono::DateTime<chrono::Utc>, f64)>(conn)
+        .await?
+        .into_iter()
+        .map(|(player_id, ended_at, rating)| RatingPoint {
+            player_id,
+            ended_at,
+            rating,
+        })
+        .collect())
+}
+
+/// Renumbers the rows of a queue so `queue_order` runs contiguously from 0.
+///
+/// Relies on the `(queue_id, queue_order)` unique constraint being checked at
+/// the end of the statement rather than per row.
+async fn renumber_rows(
+    queue_id: Uuid,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), ApiError> {
+    diesel::sql_query(
+        "UPDATE queue_rows
+        SET queue_order = ranked.new_order, version = queue_rows.version + 1
+        FROM (
+            SELECT id,
+                (ROW_NUMBER() OVER (ORDER BY queue_order) - 1)::INT
+                    AS new_order
+            FROM queue_rows
+            WHERE queue_id = $1
+        ) AS ranked
+        WHERE queue_rows.id = ranked.id
+            AND queue_rows.queue_order <> ranked.new_order",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(queue_id)
+    .execute(conn)
+    .await?;
+    Ok(())
 }
@@ -0,0 +1,1358 @@
+use crate::db::api::{self, ApiError};
+use crate::db::notify::QueueChanges;
+use crate::db::store::QueueStore;
+use crate::db::{self, Lineup, QueueRow};
+use crate::estimate;
+use crate::history::{count_games, GamesPlayed, PlayedMatch};
+use crate::queue::{
+    CreatedQueue, Player, QueueEntry, QueueInfo, QueueMode, QueueSettings,
+    QueueState, QueueStatus, Side, Slot, Station,
+};
+use crate::rating::{RatingPoint, Standing};
+use crate::user::User;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use leptos::logging::error;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Keeps everything in memory, for tests and running without a database.
+///
+/// Enforces the same rules as the Postgres schema: url names and player
+/// names are unique, rows in a queue are numbered contiguously from 0, and
+/// rows are never empty.
+pub struct MemoryStore {
+    state: Mutex<State>,
+    changes: QueueChanges,
+}
+
+#[derive(Default)]
+struct State {
+    queues: Vec<db::Queue>,
+    rows: Vec<QueueRow>,
+    stations: Vec<db::Station>,
+    matches: Vec<db::Match>,
+    ratings: Vec<db::Rating>,
+    rating_changes: Vec<db::RatingChange>,
+    players: Vec<db::Player>,
+    users: Vec<db::User>,
+    /// Session token hashes mapped to their user and expiry.
+    sessions: HashMap<Vec<u8>, (Uuid, DateTime<Utc>)>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::new()
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let (changes, _) = broadcast::channel(64);
+        MemoryStore {
+            state: Mutex::new(State::default()),
+            changes,
+        }
+    }
+
+    /// Announces which queue changed whenever its rows do, like the Postgres
+    /// trigger does.
+    pub fn changes(&self) -> QueueChanges {
+        self.changes.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Every change is made on copies and written back in one go, so a
+        // panic can't leave the state half updated.
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn notify(&self, queue_id: Uuid) {
+        // Nobody listening is fine.
+        let _ = self.changes.send(queue_id);
+    }
+}
+
+impl State {
+    fn queue(&self, queue_id: Uuid) -> Result<&db::Queue, ApiError> {
+        self.queues
+            .iter()
+            .find(|q| q.id == queue_id)
+            .ok_or(ApiError::NotFound)
+    }
+
+    fn queue_state(&self, queue_id: Uuid) -> Result<QueueState, ApiError> {
+        let mut entries = self.entries(self.queue_rows(queue_id));
+        let stations: Vec<Station> = self
+            .queue_stations(queue_id)
+            .into_iter()
+            .map(Station::from)
+            .collect();
+        let mut recent: Vec<(DateTime<Utc>, DateTime<Utc>)> = self
+            .matches
+            .iter()
+            .filter(|m| m.queue_id == queue_id)
+            .map(|m| (m.started_at, m.ended_at))
+            .collect();
+        recent.sort_by_key(|(_, ended_at)| Reverse(*ended_at));
+        recent.truncate(estimate::RECENT_MATCHES as usize);
+        estimate::estimate_starts(
+            &mut entries,
+            &stations,
+            recent
+                .first()
+                .map(|(_, ended_at)| *ended_at),
+            estimate::median_duration(&recent),
+            Utc::now(),
+        );
+        Ok(QueueState {
+            version: self.queue(queue_id)?.version,
+            entries,
+            stations,
+        })
+    }
+
+    /// Fails with [`ApiError::Conflict`] if `expected_version` is given and
+    /// the queue has moved past it. Changes check this before touching
+    /// anything and call [`State::bump_version`] once they're done.
+    fn check_version(
+        &self,
+        queue_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let version = self.queue(queue_id)?.version;
+        if expected_version.is_some_and(|expected| expected != version) {
+            return Err(ApiError::Conflict {
+                current: self.queue_state(queue_id)?,
+            });
+        }
+        Ok(())
+    }
+
+    fn bump_version(&mut self, queue_id: Uuid) {
+        if let Some(queue) = self
+            .queues
+            .iter_mut()
+            .find(|q| q.id == queue_id)
+        {
+            queue.version += 1;
+        }
+    }
+
+    fn row(&self, row_id: Uuid) -> Result<&QueueRow, ApiError> {
+        self.rows
+            .iter()
+            .find(|r| r.id == row_id)
+            .ok_or(ApiError::NotFound)
+    }
+
+    /// A queue's rows, front first.
+    fn queue_rows(&self, queue_id: Uuid) -> Vec<QueueRow> {
+        let mut rows: Vec<QueueRow> = self
+            .rows
+            .iter()
+            .filter(|r| r.queue_id == queue_id)
+            .cloned()
+            .collect();
+        rows.sort_by_key(|r| r.queue_order);
+        rows
+    }
+
+    /// Replaces a row, bumping its version, or deletes it if it's empty.
+    fn write_row(&mut self, mut row: QueueRow) {
+        row.version += 1;
+        if row.is_empty() {
+            self.remove_row(row.id);
+            self.renumber(row.queue_id);
+        } else if let Some(existing) = self
+            .rows
+            .iter_mut()
+            .find(|r| r.id == row.id)
+        {
+            *existing = row;
+        }
+    }
+
+    /// Adds a row with the given players after the last row of a queue.
+    fn push_row(&mut self, queue_id: Uuid, players: Lineup) -> Uuid {
+        let row = QueueRow {
+            id: Uuid::new_v4(),
+            queue_id,
+            queue_order: self.queue_rows(queue_id).len() as i32,
+            created_at: Utc::now(),
+            version: 0,
+            called_at: None,
+            players,
+            checked_in: Default::default(),
+        };
+        let row_id = row.id;
+        self.rows.push(row);
+        row_id
+    }
+
+    fn delete_row(&mut self, row_id: Uuid) -> Result<QueueRow, ApiError> {
+        let row = self.row(row_id)?.clone();
+        self.remove_row(row_id);
+        self.renumber(row.queue_id);
+        Ok(row)
+    }
+
+    /// Removes a row without renumbering, freeing any station it was playing
+    /// on like the Postgres foreign key does.
+    fn remove_row(&mut self, row_id: Uuid) {
+        self.rows
+            .retain(|r| r.id != row_id);
+        for station in &mut self.stations {
+            if station.row_id == Some(row_id) {
+                station.row_id = None;
+            }
+        }
+    }
+
+    fn station(&self, station_id: Uuid) -> Result<&db::Station, ApiError> {
+        self.stations
+            .iter()
+            .find(|s| s.id == station_id)
+            .ok_or(ApiError::NotFound)
+    }
+
+    /// A queue's stations, oldest first.
+    fn queue_stations(&self, queue_id: Uuid) -> Vec<db::Station> {
+        self.stations
+            .iter()
+            .filter(|s| s.queue_id == queue_id)
+            .cloned()
+            .collect()
+    }
+
+    fn write_station(&mut self, station: db::Station) {
+        if let Some(existing) = self
+            .stations
+            .iter_mut()
+            .find(|s| s.id == station.id)
+        {
+            *existing = station;
+        }
+    }
+
+    /// Starts the first row waiting in a station's queue on it, uncalling it
+    /// now that it's playing, or frees the station if nobody's waiting.
+    fn fill_station(&mut self, station: &mut db::Station) {
+        let stations = self.queue_stations(station.queue_id);
+        let next = self
+            .queue_rows(station.queue_id)
+            .into_iter()
+            .find(|r| !api::on_station(r.id, &stations));
+        station.row_id = next.as_ref().map(|r| r.id);
+        station.started_at = next.as_ref().map(|_| Utc::now());
+        if let Some(mut row) = next.filter(|r| r.called_at.is_some()) {
+            row.uncall();
+            self.write_row(row);
+        }
+        self.write_station(station.clone());
+    }
+
+    /// Saves a match that just ended and updates its players' ratings.
+    fn record_match(&mut self, played: db::Match) {
+        let current: Vec<db::Rating> = self
+            .ratings
+            .iter()
+            .filter(|r| {
+                r.queue_id == played.queue_id
+                    && played
+                        .players
+                        .values()
+                        .any(|&id| id == r.player_id)
+            })
+            .cloned()
+            .collect();
+        for (rating, change) in played.rate(&current) {
+            self.ratings.retain(|r| {
+                r.queue_id != rating.queue_id || r.player_id != rating.player_id
+            });
+            self.ratings.push(rating);
+            self.rating_changes.push(change);
+        }
+        self.matches.push(played);
+    }
+
+    /// Moves a row to `new_position` in its queue, shifting the rows in
+    /// between.
+    fn place_row(&mut self, queue_id: Uuid, row_id: Uuid, new_position: usize) {
+        let mut ids: Vec<Uuid> = self
+            .queue_rows(queue_id)
+            .iter()
+            .map(|r| r.id)
+            .filter(|id| *id != row_id)
+            .collect();
+        ids.insert(new_position.min(ids.len()), row_id);
+        for row in &mut self.rows {
+            if let Some(order) = ids
+                .iter()
+                .position(|id| *id == row.id)
+            {
+                if row.queue_order != order as i32 {
+                    row.queue_order = order as i32;
+                    row.version += 1;
+                }
+            }
+        }
+    }
+
+    /// Renumbers a queue's rows so `queue_order` runs contiguously from 0,
+    /// keeping their order.
+    fn renumber(&mut self, queue_id: Uuid) {
+        let order: HashMap<Uuid, i32> = self
+            .queue_rows(queue_id)
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.id, i as i32))
+            .collect();
+        for row in &mut self.rows {
+            match order.get(&row.id) {
+                Some(&new_order) if new_order != row.queue_order => {
+                    row.queue_order = new_order;
+                    row.version += 1;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn player(&self, player_id: Uuid) -> Option<db::Player> {
+        self.players
+            .iter()
+            .find(|p| p.id == player_id)
+            .cloned()
+    }
+
+    /// The players in a lineup, in slot order.
+    fn lineup_players(&self, lineup: &Lineup) -> Vec<(Slot, db::Player)> {
+        lineup
+            .iter()
+            .filter_map(|(&slot, &id)| Some((slot, self.player(id)?)))
+            .collect()
+    }
+
+    fn team_size(&self, queue_id: Uuid) -> Result<i32, ApiError> {
+        Ok(self.queue(queue_id)?.team_size)
+    }
+
+    /// Fails unless the queue's settings let `player_id` join, as in
+    /// [`api::check_can_join`].
+    fn check_can_join(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+        new_row: bool,
+    ) -> Result<(), ApiError> {
+        let queue = QueueInfo::from(self.queue(queue_id)?.clone());
+        api::check_can_join(
+            &queue.settings,
+            &self.queue_rows(queue_id),
+            player_id,
+            new_row,
+        )
+    }
+
+    fn entries(&self, rows: Vec<QueueRow>) -> Vec<QueueEntry> {
+        rows.into_iter()
+            .filter_map(|r| {
+                let players = self.lineup_players(&r.players);
+                (r, players)
+                    .try_into()
+                    .inspect_err(|e| error!("{e}"))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn played_matches(&self, matches: Vec<db::Match>) -> Vec<PlayedMatch> {
+        matches
+            .into_iter()
+            .map(|m| {
+                let players = self.lineup_players(&m.players);
+                (m, players).into()
+            })
+            .collect()
+    }
+
+    fn user_named(&self, username: &str) -> Option<&db::User> {
+        let username = username.trim().to_lowercase();
+        self.users
+            .iter()
+            .find(|u| u.username.to_lowercase() == username)
+    }
+}
+
+#[async_trait]
+impl QueueStore for MemoryStore {
+    async fn get_all_queues(&self) -> Result<Vec<QueueInfo>, ApiError> {
+        Ok(self
+            .state()
+            .queues
+            .iter()
+            .cloned()
+            .map(QueueInfo::from)
+            .collect())
+    }
+
+    async fn get_queue_info(
+        &self,
+        url_name: String,
+    ) -> Result<QueueInfo, ApiError> {
+        self.state()
+            .queues
+            .iter()
+            .find(|q| q.url_name == url_name)
+            .cloned()
+            .map(QueueInfo::from)
+            .ok_or(ApiError::NotFound)
+    }
+
+    async fn get_queue_state(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<QueueState, ApiError> {
+        self.state().queue_state(queue_id)
+    }
+
+    async fn get_user_queues(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<QueueInfo>, ApiError> {
+        Ok(self
+            .state()
+            .queues
+            .iter()
+            .rev()
+            .filter(|q| q.created_by == Some(user_id))
+            .cloned()
+            .map(QueueInfo::from)
+            .collect())
+    }
+
+    async fn add_queue(
+        &self,
+        display_name: String,
+        url_name: String,
+        created_by: Option<Uuid>,
+    ) -> Result<CreatedQueue, ApiError> {
+        let mut state = self.state();
+        if state
+            .queues
+            .iter()
+            .any(|q| q.url_name == url_name)
+        {
+            return Err(ApiError::UrlNameTaken);
+        }
+        let admin_token = api::new_admin_token();
+        let queue = db::Queue {
+            id: Uuid::new_v4(),
+            url_name,
+            display_name,
+            created_at: Utc::now(),
+            admin_token_hash: Some(api::hash_token(&admin_token)),
+            created_by,
+            version: 0,
+            mode: QueueMode::default().to_string(),
+            max_streak: None,
+            singles: false,
+            team_size: 1,
+            no_show_timeout: None,
+            no_show_move_back: None,
+            max_rows: None,
+            max_entries_per_player: None,
+            status: QueueStatus::default().to_string(),
+            unique_players: false,
+        };
+        state.queues.push(queue.clone());
+        Ok(CreatedQueue {
+            info: queue.into(),
+            admin_token,
+        })
+    }
+
+    async fn delete_queue(&self, queue_id: Uuid) -> Result<(), ApiError> {
+        let mut state = self.state();
+        state
+            .queues
+            .retain(|q| q.id != queue_id);
+        state
+            .rows
+            .retain(|r| r.queue_id != queue_id);
+        state
+            .stations
+            .retain(|s| s.queue_id != queue_id);
+        let State {
+            matches,
+            rating_changes,
+            ..
+        } = &mut *state;
+        rating_changes.retain(|c| {
+            matches
+                .iter()
+                .any(|m| m.id == c.match_id && m.queue_id != queue_id)
+        });
+        state
+            .matches
+            .retain(|m| m.queue_id != queue_id);
+        state
+            .ratings
+            .retain(|r| r.queue_id != queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn set_queue_mode(
+        &self,
+        queue_id: Uuid,
+        mode: QueueMode,
+        max_streak: Option<i32>,
+        singles: bool,
+        team_size: i32,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        let mut state = self.state();
+        state.check_version(queue_id, expected_version)?;
+        let queue = state
+            .queues
+            .iter_mut()
+            .find(|q| q.id == queue_id)
+            .ok_or(ApiError::NotFound)?;
+        queue.mode = mode.to_string();
+        queue.max_streak = max_streak;
+        queue.singles = singles;
+        queue.team_size = team_size;
+        state.bump_version(queue_id);
+        let queue = state.queue(queue_id)?.clone();
+        drop(state);
+        self.notify(queue_id);
+        Ok(queue.into())
+    }
+
+    async fn set_no_show_rule(
+        &self,
+        queue_id: Uuid,
+        timeout: Option<i32>,
+        move_back: Option<i32>,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        let mut state = self.state();
+        state.check_version(queue_id, expected_version)?;
+        let queue = state
+            .queues
+            .iter_mut()
+            .find(|q| q.id == queue_id)
+            .ok_or(ApiError::NotFound)?;
+        queue.no_show_timeout = timeout;
+        queue.no_show_move_back = move_back;
+        state.bump_version(queue_id);
+        let queue = state.queue(queue_id)?.clone();
+        for mut row in state
+            .queue_rows(queue_id)
+            .into_iter()
+            .filter(|r| r.called_at.is_some())
+        {
+            row.uncall();
+            state.write_row(row);
+        }
+        drop(state);
+        self.notify(queue_id);
+        Ok(queue.into())
+    }
+
+    async fn set_queue_settings(
+        &self,
+        queue_id: Uuid,
+        settings: QueueSettings,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        let mut state = self.state();
+        state.check_version(queue_id, expected_version)?;
+        let queue = state
+            .queues
+            .iter_mut()
+            .find(|q| q.id == queue_id)
+            .ok_or(ApiError::NotFound)?;
+        queue.max_rows = settings.max_rows;
+        queue.max_entries_per_player = settings.max_entries_per_player;
+        queue.status = settings.status.to_string();
+        queue.unique_players = settings.unique_players;
+        state.bump_version(queue_id);
+        let queue = state.queue(queue_id)?.clone();
+        drop(state);
+        self.notify(queue_id);
+        Ok(queue.into())
+    }
+
+    async fn is_queue_admin(
+        &self,
+        queue_id: Uuid,
+        admin_token: Option<String>,
+        user_id: Option<Uuid>,
+    ) -> Result<bool, ApiError> {
+        let state = self.state();
+        let queue = state.queue(queue_id)?;
+        Ok(api::grants_admin(
+            queue.admin_token_hash.as_deref(),
+            queue.created_by,
+            admin_token,
+            user_id,
+        ))
+    }
+
+    async fn get_or_create_player(
+        &self,
+        display_name: String,
+        tag: Option<String>,
+    ) -> Result<Player, ApiError> {
+        let display_name = api::clean_player_name(&display_name)?;
+        let tag = api::clean_player_tag(tag.as_deref());
+        let lowercase = display_name.to_lowercase();
+        let mut state = self.state();
+        if let Some(player) = state.players.iter().find(|p| {
+            p.tag.as_deref() == tag
+                && p.display_name.to_lowercase() == lowercase
+        }) {
+            return Ok(player.clone().into());
+        }
+        let player = db::Player {
+            id: Uuid::new_v4(),
+            display_name: display_name.to_string(),
+            tag: tag.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        state.players.push(player.clone());
+        Ok(player.into())
+    }
+
+    async fn search_players(
+        &self,
+        query: String,
+    ) -> Result<Vec<Player>, ApiError> {
+        let query = query.trim().to_lowercase();
+        let mut players: Vec<db::Player> = self
+            .state()
+            .players
+            .iter()
+            .filter(|p| {
+                p.display_name
+                    .to_lowercase()
+                    .contains(&query)
+            })
+            .cloned()
+            .collect();
+        players.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+        Ok(players
+            .into_iter()
+            .take(10)
+            .map(Player::from)
+            .collect())
+    }
+
+    async fn get_row_queue_id(&self, row_id: Uuid) -> Result<Uuid, ApiError> {
+        Ok(self.state().row(row_id)?.queue_id)
+    }
+
+    async fn add_row(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<Uuid, ApiError> {
+        let mut state = self.state();
+        api::check_slot(slot, state.team_size(queue_id)?)?;
+        state
+            .player(player_id)
+            .ok_or(ApiError::NotFound)?;
+        state.check_can_join(queue_id, player_id, true)?;
+        let row_id =
+            state.push_row(queue_id, Lineup::from([(slot, player_id)]));
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(row_id)
+    }
+
+    async fn add_player_to_row(
+        &self,
+        row_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError> {
+        let mut state = self.state();
+        state
+            .player(player_id)
+            .ok_or(ApiError::NotFound)?;
+        let mut row = state.row(row_id)?.clone();
+        let queue_id = row.queue_id;
+        api::check_slot(slot, state.team_size(queue_id)?)?;
+        state.check_can_join(queue_id, player_id, false)?;
+        if row.player(slot).is_some() {
+            return Err(ApiError::Occupied {
+                row_id,
+                order: row.queue_order,
+                slot,
+            });
+        }
+        row.players.insert(slot, player_id);
+        state.write_row(row);
+        state.bump_version(queue_id);
+        let entry = state
+            .entries(vec![state.row(row_id)?.clone()])
+            .pop()
+            .ok_or(ApiError::NotFound)?;
+        drop(state);
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn join_queue(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<QueueEntry, ApiError> {
+        let mut state = self.state();
+        let team_size = state.team_size(queue_id)?;
+        state
+            .player(player_id)
+            .ok_or(ApiError::NotFound)?;
+        let waiting = state.queue_rows(queue_id);
+        let partner = api::find_partner(waiting, player_id, team_size);
+        state.check_can_join(queue_id, player_id, partner.is_none())?;
+        let row_id = match partner {
+            Some((mut row, slot)) => {
+                row.players.insert(slot, player_id);
+                let row_id = row.id;
+                state.write_row(row);
+                row_id
+            }
+            None => state.push_row(
+                queue_id,
+                Lineup::from([(Side::Left.into(), player_id)]),
+            ),
+        };
+        state.bump_version(queue_id);
+        let entry = state
+            .entries(vec![state.row(row_id)?.clone()])
+            .pop()
+            .ok_or(ApiError::NotFound)?;
+        drop(state);
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn remove_player_from_row(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        let mut state = self.state();
+        let mut row = state.row(row_id)?.clone();
+        let queue_id = row.queue_id;
+        state.check_version(queue_id, expected_version)?;
+        row.players.remove(&slot);
+        state.write_row(row);
+        state.bump_version(queue_id);
+        let entry = match state.row(row_id) {
+            Ok(row) => state
+                .entries(vec![row.clone()])
+                .pop(),
+            Err(_) => None,
+        };
+        drop(state);
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn delete_row(
+        &self,
+        row_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let mut state = self.state();
+        let queue_id = state.row(row_id)?.queue_id;
+        state.check_version(queue_id, expected_version)?;
+        state.delete_row(row_id)?;
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn move_row(
+        &self,
+        row_id: Uuid,
+        new_position: i32,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let mut state = self.state();
+        let queue_id = state.row(row_id)?.queue_id;
+        state.check_version(queue_id, expected_version)?;
+        let ids: Vec<Uuid> = state
+            .queue_rows(queue_id)
+            .iter()
+            .map(|r| r.id)
+            .collect();
+        let old_position = ids
+            .iter()
+            .position(|id| *id == row_id)
+            .ok_or(ApiError::NotFound)?;
+        let last = ids.len() as i32 - 1;
+        if !(0..=last).contains(&new_position) {
+            return Err(ApiError::InvalidOrder {
+                expected: last,
+                got: new_position,
+            });
+        }
+        if old_position == new_position as usize {
+            state.bump_version(queue_id);
+            return Ok(());
+        }
+        state.place_row(queue_id, row_id, new_position as usize);
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn move_player(
+        &self,
+        from_row: Uuid,
+        from_slot: Slot,
+        to_row: Uuid,
+        to_slot: Slot,
+        swap: bool,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        if from_row == to_row && from_slot == to_slot {
+            return Ok(());
+        }
+        let mut state = self.state();
+        let mut rows = vec![state.row(from_row)?.clone()];
+        if to_row != from_row {
+            rows.push(state.row(to_row)?.clone());
+        }
+        let to_index = rows.len() - 1;
+        let queue_id = rows[0].queue_id;
+        if rows[to_index].queue_id != queue_id {
+            return Err(ApiError::DifferentQueues);
+        }
+        state.check_version(queue_id, expected_version)?;
+        api::check_slot(to_slot, state.team_size(queue_id)?)?;
+
+        let Some(player) = rows[0].players.remove(&from_slot) else {
+            return Err(ApiError::EmptySlot {
+                row_id: from_row,
+                slot: from_slot,
+            });
+        };
+        let displaced = rows[to_index]
+            .players
+            .insert(to_slot, player);
+        if displaced.is_some() && !swap {
+            return Err(ApiError::Occupied {
+                row_id: to_row,
+                order: rows[to_index].queue_order,
+                slot: to_slot,
+            });
+        }
+        if let Some(displaced) = displaced {
+            rows[0]
+                .players
+                .insert(from_slot, displaced);
+        }
+
+        // Write the target first, like the Postgres version.
+        for row in rows.into_iter().rev() {
+            state.write_row(row);
+        }
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn complete_front_row(
+        &self,
+        queue_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        let mut state = self.state();
+        state.check_version(queue_id, expected_version)?;
+        // The front row may be on a station, which would never be freed.
+        if !state
+            .queue_stations(queue_id)
+            .is_empty()
+        {
+            return Err(ApiError::StationRequired { queue_id });
+        }
+        let queue = QueueInfo::from(state.queue(queue_id)?.clone());
+        let mut front_rows = state
+            .queue_rows(queue_id)
+            .into_iter();
+        let Some(front) = front_rows.next() else {
+            state.bump_version(queue_id);
+            return Ok(None);
+        };
+        let mut recent: Vec<db::Match> = state
+            .matches
+            .iter()
+            .filter(|m| m.queue_id == queue_id)
+            .cloned()
+            .collect();
+        recent.sort_by_key(|m| Reverse(m.ended_at));
+        recent.truncate(api::recent_matches_needed(&queue) as usize);
+        let last_ended_at = recent.first().map(|m| m.ended_at);
+        let played = db::Match::finished(&front, winner, last_ended_at)?;
+        let entry = state
+            .entries(vec![front.clone()])
+            .pop();
+        state.record_match(played);
+        let front_id = front.id;
+        let rotation =
+            api::rotate(&queue, front, front_rows.next(), winner, &recent);
+        match rotation.front {
+            Some(row) => state.write_row(row),
+            None => {
+                state.delete_row(front_id)?;
+            }
+        }
+        if let Some(row) = rotation.next {
+            state.write_row(row);
+        }
+        if let Some(players) = rotation.to_back {
+            state.push_row(queue_id, players);
+        }
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn check_in(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError> {
+        let mut state = self.state();
+        let mut row = state.row(row_id)?.clone();
+        let queue_id = row.queue_id;
+        api::check_in_player(&mut row, slot)?;
+        state.write_row(row);
+        state.bump_version(queue_id);
+        let entry = state
+            .entries(vec![state.row(row_id)?.clone()])
+            .pop()
+            .ok_or(ApiError::NotFound)?;
+        drop(state);
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn check_no_shows(&self, now: DateTime<Utc>) -> Result<(), ApiError> {
+        let mut state = self.state();
+        let timed: Vec<QueueInfo> = state
+            .queues
+            .iter()
+            .filter(|q| q.no_show_timeout.is_some())
+            .cloned()
+            .map(QueueInfo::from)
+            .collect();
+        let mut changed = Vec::new();
+        for queue in timed {
+            let check = api::no_shows(
+                &queue,
+                state.queue_rows(queue.id),
+                &state.queue_stations(queue.id),
+                now,
+            );
+            if check.is_empty() {
+                continue;
+            }
+            for row in check.changed {
+                state.write_row(row);
+            }
+            if let Some((row_id, _, position)) = check.move_back {
+                state.place_row(queue.id, row_id, position as usize);
+            }
+            if let Some(row_id) = check.dropped {
+                if let Err(e) = state.delete_row(row_id) {
+                    error!(
+                        "Error checking queue {} for no-shows: {e}",
+                        queue.id
+                    );
+                }
+            }
+            state.bump_version(queue.id);
+            changed.push(queue.id);
+        }
+        drop(state);
+        for queue_id in changed {
+            self.notify(queue_id);
+        }
+        Ok(())
+    }
+
+    async fn add_station(
+        &self,
+        queue_id: Uuid,
+        name: String,
+        expected_version: Option<i32>,
+    ) -> Result<Station, ApiError> {
+        let mut state = self.state();
+        state.check_version(queue_id, expected_version)?;
+        let station = db::Station {
+            id: Uuid::new_v4(),
+            queue_id,
+            name: api::station_name(
+                &name,
+                state
+                    .queue_stations(queue_id)
+                    .len(),
+            ),
+            created_at: Utc::now(),
+            row_id: None,
+            started_at: None,
+        };
+        state
+            .stations
+            .push(station.clone());
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(station.into())
+    }
+
+    async fn delete_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let mut state = self.state();
+        let queue_id = state.station(station_id)?.queue_id;
+        state.check_version(queue_id, expected_version)?;
+        state
+            .stations
+            .retain(|s| s.id != station_id);
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn get_station_queue_id(
+        &self,
+        station_id: Uuid,
+    ) -> Result<Uuid, ApiError> {
+        Ok(self
+            .state()
+            .station(station_id)?
+            .queue_id)
+    }
+
+    async fn assign_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        let mut state = self.state();
+        let mut station = state.station(station_id)?.clone();
+        let queue_id = station.queue_id;
+        state.check_version(queue_id, expected_version)?;
+        let assigned = station.row_id.is_none();
+        if assigned {
+            state.fill_station(&mut station);
+            state.bump_version(queue_id);
+        }
+        let entry = match station.row_id {
+            Some(row_id) => state
+                .entries(vec![state.row(row_id)?.clone()])
+                .pop(),
+            None => None,
+        };
+        drop(state);
+        if assigned {
+            self.notify(queue_id);
+        }
+        Ok(entry)
+    }
+
+    async fn free_station(
+        &self,
+        station_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        let mut state = self.state();
+        let mut station = state.station(station_id)?.clone();
+        let queue_id = station.queue_id;
+        state.check_version(queue_id, expected_version)?;
+        let queue = QueueInfo::from(state.queue(queue_id)?.clone());
+        let stations = state.queue_stations(queue_id);
+        let (playing, waiting): (Vec<_>, Vec<_>) = state
+            .queue_rows(queue_id)
+            .into_iter()
+            .partition(|r| api::on_station(r.id, &stations));
+        let Some(front) = playing
+            .into_iter()
+            .find(|r| station.row_id == Some(r.id))
+        else {
+            state.fill_station(&mut station);
+            state.bump_version(queue_id);
+            drop(state);
+            self.notify(queue_id);
+            return Ok(None);
+        };
+        // Other stations' matches don't break a streak, so only count the
+        // ones these players were in.
+        let mut recent: Vec<db::Match> = state
+            .matches
+            .iter()
+            .filter(|m| {
+                m.queue_id == queue_id
+                    && m.players.values().any(|id| {
+                        front
+                            .players
+                            .values()
+                            .any(|p| p == id)
+                    })
+            })
+            .cloned()
+            .collect();
+        recent.sort_by_key(|m| Reverse(m.ended_at));
+        recent.truncate(api::recent_matches_needed(&queue) as usize);
+        let played = db::Match::finished(&front, winner, station.started_at)?;
+        let entry = state
+            .entries(vec![front.clone()])
+            .pop();
+        state.record_match(played);
+
+        let front_id = front.id;
+        let rotation = api::rotate(
+            &queue,
+            front,
+            waiting.into_iter().next(),
+            winner,
+            &recent,
+        );
+        let stayed = match rotation.front {
+            Some(row) => {
+                state.write_row(row);
+                true
+            }
+            None => {
+                state.delete_row(front_id)?;
+                false
+            }
+        };
+        if let Some(row) = rotation.next {
+            state.write_row(row);
+        }
+        if let Some(players) = rotation.to_back {
+            state.push_row(queue_id, players);
+        }
+        if stayed {
+            station.started_at = Some(Utc::now());
+            state.write_station(station);
+        } else {
+            state.fill_station(&mut station);
+        }
+        state.bump_version(queue_id);
+        drop(state);
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn get_matches(
+        &self,
+        queue_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayedMatch>, ApiError> {
+        let state = self.state();
+        let mut matches: Vec<db::Match> = state
+            .matches
+            .iter()
+            .filter(|m| m.queue_id == queue_id)
+            .cloned()
+            .collect();
+        matches.sort_by_key(|m| Reverse((m.ended_at, m.id)));
+        let page = matches
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect();
+        Ok(state.played_matches(page))
+    }
+
+    async fn get_games_played(
+        &self,
+        queue_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<GamesPlayed>, ApiError> {
+        let state = self.state();
+        let matches = state
+            .matches
+            .iter()
+            .filter(|m| m.queue_id == queue_id && m.ended_at >= since)
+            .cloned()
+            .collect();
+        Ok(count_games(&state.played_matches(matches)))
+    }
+
+    async fn get_leaderboard(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<Standing>, ApiError> {
+        let state = self.state();
+        let mut standings: Vec<Standing> = state
+            .ratings
+            .iter()
+            .filter(|r| r.queue_id == queue_id)
+            .filter_map(|r| {
+                let player = state.player(r.player_id)?;
+                Some(Standing::from((r.clone(), player)))
+            })
+            .collect();
+        standings.sort_by(|a, b| {
+            b.rating
+                .total_cmp(&a.rating)
+                .then_with(|| {
+                    a.player
+                        .display_name
+                        .cmp(&b.player.display_name)
+                })
+        });
+        Ok(standings)
+    }
+
+    async fn get_rating_history(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<RatingPoint>, ApiError> {
+        let state = self.state();
+        let mut history: Vec<(Uuid, RatingPoint)> = state
+            .rating_changes
+            .iter()
+            .filter_map(|c| {
+                let played = state
+                    .matches
+                    .iter()
+                    .find(|m| m.id == c.match_id && m.queue_id == queue_id)?;
+                let point = RatingPoint {
+                    player_id: c.player_id,
+                    ended_at: played.ended_at,
+                    rating: c.rating_after,
+                };
+                Some((played.id, point))
+            })
+            .collect();
+        history.sort_by_key(|(match_id, point)| (point.ended_at, *match_id));
+        Ok(history
+            .into_iter()
+            .map(|(_, point)| point)
+            .collect())
+    }
+
+    async fn create_user(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError> {
+        let username = api::validate_new_user(&username, &password)?;
+        if self
+            .state()
+            .user_named(&username)
+            .is_some()
+        {
+            return Err(ApiError::UsernameTaken);
+        }
+        let password_hash = api::hash_password(password).await?;
+
+        // Someone may have taken the name while the password was hashing.
+        let mut state = self.state();
+        if state
+            .user_named(&username)
+            .is_some()
+        {
+            return Err(ApiError::UsernameTaken);
+        }
+        let user = db::User {
+            id: Uuid::new_v4(),
+            username,
+            password_hash,
+            created_at: Utc::now(),
+        };
+        state.users.push(user.clone());
+        Ok(user.into())
+    }
+
+    async fn verify_login(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError> {
+        let user = self
+            .state()
+            .user_named(&username)
+            .cloned()
+            .ok_or(ApiError::InvalidCredentials)?;
+        if !api::verify_password(password, user.password_hash.clone()).await? {
+            return Err(ApiError::InvalidCredentials);
+        }
+        Ok(user.into())
+    }
+
+    async fn create_session(&self, user_id: Uuid) -> Result<String, ApiError> {
+        let now = Utc::now();
+        let token = api::new_session_token();
+        let mut state = self.state();
+        state
+            .sessions
+            .retain(|_, (_, expires_at)| *expires_at > now);
+        state.sessions.insert(
+            api::hash_token(&token),
+            (user_id, now + api::SESSION_LENGTH),
+        );
+        Ok(token)
+    }
+
+    async fn get_session_user(
+        &self,
+        token: String,
+    ) -> Result<Option<User>, ApiError> {
+        let state = self.state();
+        let Some((user_id, expires_at)) = state
+            .sessions
+            .get(&api::hash_token(&token))
+        else {
+            return Ok(None);
+        };
+        if *expires_at <= Utc::now() {
+            return Ok(None);
+        }
+        Ok(state
+            .users
+            .iter()
+            .find(|u| u.id == *user_id)
+            .cloned()
+            .map(User::from))
+    }
+
+    async fn delete_session(&self, token: String) -> Result<(), ApiError> {
+        self.state()
+            .sessions
+            .remove(&api::hash_token(&token));
+        Ok(())
+    }
+}
@@ -1,12 +1,21 @@
 pub mod api;
+pub mod memory;
 mod models;
 pub use models::*;
+pub mod notify;
 pub mod schema;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod store;
+pub use store::{PgStore, QueueStore, Store};
 
 use diesel_async::{
     pooled_connection::{deadpool::Pool, AsyncDieselConnectionManager},
     AsyncPgConnection,
 };
+use leptos::logging::log;
+use notify::QueueChanges;
+use std::sync::Arc;
 
 pub type DbPool = Pool<AsyncPgConnection>;
 
@@ -17,3 +26,49 @@ pub async fn create_pool(database_url: &str) -> DbPool {
         .build()
         .expect("Failed to create pool")
 }
+
+/// Opens the store named by the `DATABASE_URL` scheme, along with the
+/// channel announcing changes to its queues:
+///
+/// - `postgres://...` uses Postgres.
+/// - `sqlite://<path>` uses a SQLite file, with the `sqlite` feature.
+/// - `memory:` keeps everything in memory, for demos.
+pub async fn open_store(database_url: String) -> (Store, QueueChanges) {
+    let scheme = database_url
+        .split_once(':')
+        .map_or("", |(scheme, _)| scheme);
+    match scheme {
+        "postgres" | "postgresql" => {
+            let pool = create_pool(&database_url).await;
+            let changes = notify::listen_for_changes(database_url);
+            (Arc::new(PgStore::new(pool)), changes)
+        }
+        "sqlite" => {
+            let path = database_url
+                .strip_prefix("sqlite://")
+                .or_else(|| database_url.strip_prefix("sqlite:"))
+                .unwrap_or_default();
+            open_sqlite(path)
+        }
+        "memory" => {
+            log!("keeping queues in memory, they will be lost on restart");
+            let store = memory::MemoryStore::new();
+            let changes = store.changes();
+            (Arc::new(store), changes)
+        }
+        _ => panic!("Unsupported DATABASE_URL scheme: {scheme:?}"),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(path: &str) -> (Store, QueueChanges) {
+    let store = sqlite::SqliteStore::open(path)
+        .expect("Failed to open SQLite database");
+    let changes = store.changes();
+    (Arc::new(store), changes)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(_path: &str) -> (Store, QueueChanges) {
+    panic!("SQLite support needs itq to be built with the `sqlite` feature");
+}
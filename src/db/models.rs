@@ -1,9 +1,16 @@
-use crate::db::schema::{queue_rows, queues};
+use crate::db::api::ApiError;
+use crate::db::schema::{
+    match_slots, matches, players, queue_rows, queues, rating_changes, ratings,
+    row_slots, sessions, stations, users,
+};
+use crate::queue::{Side, Slot};
+use crate::rating::rate_match;
 use chrono::Utc;
 use diesel::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable)]
+#[derive(Queryable, Selectable, Clone)]
 #[diesel(table_name = queues)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Queue {
@@ -11,6 +18,21 @@ pub struct Queue {
     pub url_name: String,
     pub display_name: String,
     pub created_at: chrono::DateTime<Utc>,
+    pub admin_token_hash: Option<Vec<u8>>,
+    pub created_by: Option<Uuid>,
+    pub version: i32,
+    /// A [`QueueMode`](crate::queue::QueueMode) by name.
+    pub mode: String,
+    pub max_streak: Option<i32>,
+    pub singles: bool,
+    pub team_size: i32,
+    pub no_show_timeout: Option<i32>,
+    pub no_show_move_back: Option<i32>,
+    pub max_rows: Option<i32>,
+    pub max_entries_per_player: Option<i32>,
+    /// A [`QueueStatus`](crate::queue::QueueStatus) by name.
+    pub status: String,
+    pub unique_players: bool,
 }
 
 #[derive(Insertable)]
@@ -19,27 +41,445 @@ pub struct Queue {
 pub struct NewQueue {
     pub url_name: String,
     pub display_name: String,
+    pub admin_token_hash: Option<Vec<u8>>,
+    pub created_by: Option<Uuid>,
 }
 
-#[derive(Queryable, Selectable, AsChangeset)]
+/// The players in the filled slots of a row or match, by slot.
+pub type Lineup = BTreeMap<Slot, Uuid>;
+
+/// The players on one side of a lineup, in slot order.
+fn team(players: &Lineup, side: Side) -> Vec<Uuid> {
+    players
+        .iter()
+        .filter(|(slot, _)| slot.side == side)
+        .map(|(_, &player_id)| player_id)
+        .collect()
+}
+
+/// The [`Side`] stored under `name`.
+pub(crate) fn side_named(name: &str) -> Option<Side> {
+    match name {
+        "Left" => Some(Side::Left),
+        "Right" => Some(Side::Right),
+        _ => None,
+    }
+}
+
+/// Groups stored slots by the row or match they belong to, skipping any
+/// with an unknown side.
+fn lineups(
+    slots: impl IntoIterator<Item = (Uuid, String, i32, Uuid)>,
+) -> HashMap<Uuid, Lineup> {
+    let mut lineups: HashMap<Uuid, Lineup> = HashMap::new();
+    for (owner_id, side, index, player_id) in slots {
+        if let Some(side) = side_named(&side) {
+            lineups
+                .entry(owner_id)
+                .or_default()
+                .insert(Slot::new(side, index), player_id);
+        }
+    }
+    lineups
+}
+
+/// A queue row as stored, without its players. See [`QueueRow`].
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone)]
 #[diesel(table_name = queue_rows)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(treat_none_as_null = true)]
-pub struct QueueRow {
+pub struct RowRecord {
     pub id: Uuid,
     pub queue_id: Uuid,
-    pub left_player_name: Option<String>,
-    pub right_player_name: Option<String>,
     pub queue_order: i32,
     pub created_at: chrono::DateTime<Utc>,
+    pub version: i32,
+    pub called_at: Option<chrono::DateTime<Utc>>,
 }
 
-#[derive(Insertable)]
-#[diesel(table_name = queue_rows)]
+/// A filled slot of a queue row. `side` holds a [`Side`] by name.
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = row_slots)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-pub struct NewQueueRow {
+pub struct RowSlot {
+    pub row_id: Uuid,
+    pub side: String,
+    pub slot_index: i32,
+    pub player_id: Uuid,
+    pub checked_in: bool,
+}
+
+/// A queue row along with the players in its slots.
+#[derive(Clone)]
+pub struct QueueRow {
+    pub id: Uuid,
     pub queue_id: Uuid,
-    pub left_player_name: Option<String>,
-    pub right_player_name: Option<String>,
     pub queue_order: i32,
+    pub created_at: chrono::DateTime<Utc>,
+    pub version: i32,
+    /// When the row was called to play, while it's at the front.
+    pub called_at: Option<chrono::DateTime<Utc>>,
+    pub players: Lineup,
+    /// The players who checked in since the row was called.
+    pub checked_in: BTreeSet<Uuid>,
+}
+
+impl QueueRow {
+    /// Puts stored rows back together with their slots, keeping their order.
+    pub fn assemble(records: Vec<RowRecord>, slots: Vec<RowSlot>) -> Vec<Self> {
+        let mut checked_in: HashMap<Uuid, BTreeSet<Uuid>> = HashMap::new();
+        for slot in slots
+            .iter()
+            .filter(|s| s.checked_in)
+        {
+            checked_in
+                .entry(slot.row_id)
+                .or_default()
+                .insert(slot.player_id);
+        }
+        let mut lineups = lineups(
+            slots
+                .into_iter()
+                .map(|s| (s.row_id, s.side, s.slot_index, s.player_id)),
+        );
+        records
+            .into_iter()
+            .map(|r| QueueRow {
+                players: lineups
+                    .remove(&r.id)
+                    .unwrap_or_default(),
+                checked_in: checked_in
+                    .remove(&r.id)
+                    .unwrap_or_default(),
+                id: r.id,
+                queue_id: r.queue_id,
+                queue_order: r.queue_order,
+                created_at: r.created_at,
+                version: r.version,
+                called_at: r.called_at,
+            })
+            .collect()
+    }
+
+    /// The row as stored, without its players.
+    pub fn record(&self) -> RowRecord {
+        RowRecord {
+            id: self.id,
+            queue_id: self.queue_id,
+            queue_order: self.queue_order,
+            created_at: self.created_at,
+            version: self.version,
+            called_at: self.called_at,
+        }
+    }
+
+    /// The row's filled slots as stored.
+    pub fn slots(&self) -> Vec<RowSlot> {
+        self.players
+            .iter()
+            .map(|(slot, &player_id)| RowSlot {
+                row_id: self.id,
+                side: slot.side.to_string(),
+                slot_index: slot.index,
+                player_id,
+                checked_in: self
+                    .checked_in
+                    .contains(&player_id),
+            })
+            .collect()
+    }
+
+    /// Whether everyone in the row has checked in.
+    pub fn all_checked_in(&self) -> bool {
+        self.players
+            .values()
+            .all(|id| self.checked_in.contains(id))
+    }
+
+    /// Ends the row's call, so it's called afresh the next time it's at the
+    /// front.
+    pub fn uncall(&mut self) {
+        self.called_at = None;
+        self.checked_in.clear();
+    }
+
+    /// The player in the given slot of this row.
+    pub fn player(&self, slot: Slot) -> Option<Uuid> {
+        self.players.get(&slot).copied()
+    }
+
+    /// The players on one side of this row, in slot order.
+    pub fn team(&self, side: Side) -> Vec<Uuid> {
+        team(&self.players, side)
+    }
+
+    /// Empties one side of this row, returning who was on it.
+    pub fn take_team(&mut self, side: Side) -> Lineup {
+        let (taken, kept) = std::mem::take(&mut self.players)
+            .into_iter()
+            .partition(|(slot, _)| slot.side == side);
+        self.players = kept;
+        taken
+    }
+
+    /// The first empty slot of this row when each side holds `team_size`
+    /// players, filling the left side first.
+    pub fn open_slot(&self, team_size: i32) -> Option<Slot> {
+        Slot::all(team_size).find(|slot| !self.players.contains_key(slot))
+    }
+
+    /// Whether the player is in any slot of this row.
+    pub fn contains(&self, player_id: Uuid) -> bool {
+        self.players
+            .values()
+            .any(|&id| id == player_id)
+    }
+
+    /// Whether none of the row's slots has a player.
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+}
+
+/// A setup a queue plays on, along with the row playing there, if any.
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone)]
+#[diesel(table_name = stations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct Station {
+    pub id: Uuid,
+    pub queue_id: Uuid,
+    pub name: String,
+    pub created_at: chrono::DateTime<Utc>,
+    pub row_id: Option<Uuid>,
+    pub started_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// A finished match as stored, without its players. See [`Match`].
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = matches)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MatchRecord {
+    pub id: Uuid,
+    pub queue_id: Uuid,
+    pub started_at: chrono::DateTime<Utc>,
+    pub ended_at: chrono::DateTime<Utc>,
+    /// A [`Side`] by name.
+    pub winner: Option<String>,
+}
+
+/// A slot someone played in during a match, like a [`RowSlot`].
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = match_slots)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MatchSlot {
+    pub match_id: Uuid,
+    pub side: String,
+    pub slot_index: i32,
+    pub player_id: Uuid,
+}
+
+/// A finished match along with who played in it.
+#[derive(Clone)]
+pub struct Match {
+    pub id: Uuid,
+    pub queue_id: Uuid,
+    pub started_at: chrono::DateTime<Utc>,
+    pub ended_at: chrono::DateTime<Utc>,
+    /// The side that won, if anyone said.
+    pub winner: Option<Side>,
+    pub players: Lineup,
+}
+
+impl Match {
+    /// Puts stored matches back together with their slots, keeping their
+    /// order.
+    pub fn assemble(
+        records: Vec<MatchRecord>,
+        slots: Vec<MatchSlot>,
+    ) -> Vec<Self> {
+        let mut lineups = lineups(
+            slots
+                .into_iter()
+                .map(|s| (s.match_id, s.side, s.slot_index, s.player_id)),
+        );
+        records
+            .into_iter()
+            .map(|m| Match {
+                players: lineups
+                    .remove(&m.id)
+                    .unwrap_or_default(),
+                winner: m
+                    .winner
+                    .as_deref()
+                    .and_then(side_named),
+                id: m.id,
+                queue_id: m.queue_id,
+                started_at: m.started_at,
+                ended_at: m.ended_at,
+            })
+            .collect()
+    }
+
+    /// The match as stored, without its players.
+    pub fn record(&self) -> MatchRecord {
+        MatchRecord {
+            id: self.id,
+            queue_id: self.queue_id,
+            started_at: self.started_at,
+            ended_at: self.ended_at,
+            winner: self
+                .winner
+                .map(|side| side.to_string()),
+        }
+    }
+
+    /// The slots played in as stored.
+    pub fn slots(&self) -> Vec<MatchSlot> {
+        self.players
+            .iter()
+            .map(|(slot, &player_id)| MatchSlot {
+                match_id: self.id,
+                side: slot.side.to_string(),
+                slot_index: slot.index,
+                player_id,
+            })
+            .collect()
+    }
+
+    /// The players on one side of the match, in slot order.
+    pub fn team(&self, side: Side) -> Vec<Uuid> {
+        team(&self.players, side)
+    }
+
+    /// The match played by a row that just left the front of its queue.
+    ///
+    /// It started at `since`, which is when the last match in the queue
+    /// ended or when its station started it, or when the row joined or was
+    /// called to play if either was later, so a queue sitting idle doesn't
+    /// count towards the match. Fails with [`ApiError::EmptySlot`] if
+    /// `winner` has no players.
+    pub fn finished(
+        row: &QueueRow,
+        winner: Option<Side>,
+        since: Option<chrono::DateTime<Utc>>,
+    ) -> Result<Self, ApiError> {
+        if let Some(side) = winner.filter(|&side| row.team(side).is_empty()) {
+            return Err(ApiError::EmptySlot {
+                row_id: row.id,
+                slot: side.into(),
+            });
+        }
+        Ok(Match {
+            id: Uuid::new_v4(),
+            queue_id: row.queue_id,
+            started_at: [since, row.called_at]
+                .into_iter()
+                .flatten()
+                .fold(row.created_at, chrono::DateTime::max),
+            ended_at: Utc::now(),
+            winner,
+            players: row.players.clone(),
+        })
+    }
+
+    /// The ratings of everyone who played after this match, given the
+    /// `current` ratings of any who were rated before, along with how each
+    /// one moved. Empty if nobody won.
+    pub fn rate(&self, current: &[Rating]) -> Vec<(Rating, RatingChange)> {
+        let Some(winner) = self.winner else {
+            return Vec::new();
+        };
+        let ratings = current
+            .iter()
+            .map(|r| (r.player_id, r.rating))
+            .collect();
+        rate_match(
+            &self.team(winner),
+            &self.team(winner.other()),
+            &ratings,
+        )
+        .into_iter()
+        .map(|update| {
+            let (games, wins) = current
+                .iter()
+                .find(|r| r.player_id == update.player_id)
+                .map_or((0, 0), |r| (r.games, r.wins));
+            let rating = Rating {
+                queue_id: self.queue_id,
+                player_id: update.player_id,
+                rating: update.after,
+                games: games + 1,
+                wins: wins + i32::from(update.won),
+            };
+            let change = RatingChange {
+                match_id: self.id,
+                player_id: update.player_id,
+                rating_before: update.before,
+                rating_after: update.after,
+            };
+            (rating, change)
+        })
+        .collect()
+    }
+}
+
+/// A player's current rating in a queue. See [`crate::rating`].
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = ratings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Rating {
+    pub queue_id: Uuid,
+    pub player_id: Uuid,
+    pub rating: f64,
+    pub games: i32,
+    pub wins: i32,
+}
+
+/// How a rated match moved one player's rating.
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = rating_changes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RatingChange {
+    pub match_id: Uuid,
+    pub player_id: Uuid,
+    pub rating_before: f64,
+    pub rating_after: f64,
+}
+
+#[derive(Queryable, QueryableByName, Selectable, Clone)]
+#[diesel(table_name = players)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Player {
+    pub id: Uuid,
+    pub display_name: String,
+    pub tag: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Clone)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+    pub password_hash: String,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewUser {
+    pub username: String,
+    pub password_hash: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSession {
+    pub token_hash: Vec<u8>,
+    pub user_id: Uuid,
+    pub expires_at: chrono::DateTime<Utc>,
 }
@@ -0,0 +1,64 @@
+use futures::{stream, StreamExt};
+use leptos::logging::{error, log};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_postgres::{AsyncMessage, NoTls};
+use uuid::Uuid;
+
+/// Channel the `queue_rows` trigger notifies with the id of the changed queue.
+const CHANNEL: &str = "queue_rows_changed";
+
+/// Broadcasts the ids of queues whose rows have changed.
+pub type QueueChanges = broadcast::Sender<Uuid>;
+
+/// Starts listening for queue row changes in the background, reconnecting if
+/// the connection drops.
+pub fn listen_for_changes(database_url: String) -> QueueChanges {
+    let (changes, _) = broadcast::channel(64);
+    tokio::spawn({
+        let changes = changes.clone();
+        async move {
+            loop {
+                if let Err(e) = forward_changes(&database_url, &changes).await {
+                    error!("Error listening for queue changes: {e}");
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    });
+    changes
+}
+
+/// Forwards notifications until the connection closes.
+async fn forward_changes(
+    database_url: &str,
+    changes: &QueueChanges,
+) -> Result<(), tokio_postgres::Error> {
+    let (client, mut connection) =
+        tokio_postgres::connect(database_url, NoTls).await?;
+    // Notifications only arrive while the connection is being polled, which
+    // also has to happen for the LISTEN itself to complete.
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    let forward = async {
+        while let Some(message) = messages.next().await {
+            let AsyncMessage::Notification(notification) = message? else {
+                continue;
+            };
+            match notification.payload().parse() {
+                // Nobody listening is fine.
+                Ok(queue_id) => _ = changes.send(queue_id),
+                Err(e) => error!("Invalid queue change notification: {e}"),
+            }
+        }
+        Ok(())
+    };
+    let listen = async {
+        client
+            .batch_execute(&format!("LISTEN {CHANNEL}"))
+            .await?;
+        log!("Listening for queue changes");
+        Ok(())
+    };
+    futures::try_join!(listen, forward)?;
+    Ok(())
+}
@@ -1,13 +1,52 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    legacy_admin_tokens (queue_id) {
+        queue_id -> Uuid,
+        admin_token -> Text,
+    }
+}
+
+diesel::table! {
+    matches (id) {
+        id -> Uuid,
+        queue_id -> Uuid,
+        started_at -> Timestamptz,
+        ended_at -> Timestamptz,
+        #[max_length = 5]
+        winner -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    match_slots (match_id, side, slot_index) {
+        match_id -> Uuid,
+        #[max_length = 5]
+        side -> Varchar,
+        slot_index -> Int4,
+        player_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    players (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        display_name -> Varchar,
+        #[max_length = 255]
+        tag -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     queue_rows (id) {
         id -> Uuid,
         queue_id -> Uuid,
-        left_player_name -> Nullable<Text>,
-        right_player_name -> Nullable<Text>,
         queue_order -> Int4,
         created_at -> Timestamptz,
+        version -> Int4,
+        called_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -19,9 +58,111 @@ diesel::table! {
         #[max_length = 255]
         display_name -> Varchar,
         created_at -> Timestamptz,
+        admin_token_hash -> Nullable<Bytea>,
+        created_by -> Nullable<Uuid>,
+        version -> Int4,
+        #[max_length = 11]
+        mode -> Varchar,
+        max_streak -> Nullable<Int4>,
+        singles -> Bool,
+        team_size -> Int4,
+        no_show_timeout -> Nullable<Int4>,
+        no_show_move_back -> Nullable<Int4>,
+        max_rows -> Nullable<Int4>,
+        max_entries_per_player -> Nullable<Int4>,
+        #[max_length = 6]
+        status -> Varchar,
+        unique_players -> Bool,
+    }
+}
+
+diesel::table! {
+    rating_changes (match_id, player_id) {
+        match_id -> Uuid,
+        player_id -> Uuid,
+        rating_before -> Float8,
+        rating_after -> Float8,
+    }
+}
+
+diesel::table! {
+    ratings (queue_id, player_id) {
+        queue_id -> Uuid,
+        player_id -> Uuid,
+        rating -> Float8,
+        games -> Int4,
+        wins -> Int4,
+    }
+}
+
+diesel::table! {
+    row_slots (row_id, side, slot_index) {
+        row_id -> Uuid,
+        #[max_length = 5]
+        side -> Varchar,
+        slot_index -> Int4,
+        player_id -> Uuid,
+        checked_in -> Bool,
+    }
+}
+
+diesel::table! {
+    sessions (token_hash) {
+        token_hash -> Bytea,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    stations (id) {
+        id -> Uuid,
+        queue_id -> Uuid,
+        name -> Text,
+        created_at -> Timestamptz,
+        row_id -> Nullable<Uuid>,
+        started_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        username -> Varchar,
+        password_hash -> Text,
+        created_at -> Timestamptz,
     }
 }
 
+diesel::joinable!(legacy_admin_tokens -> queues (queue_id));
+diesel::joinable!(match_slots -> matches (match_id));
+diesel::joinable!(match_slots -> players (player_id));
+diesel::joinable!(matches -> queues (queue_id));
 diesel::joinable!(queue_rows -> queues (queue_id));
+diesel::joinable!(queues -> users (created_by));
+diesel::joinable!(rating_changes -> matches (match_id));
+diesel::joinable!(rating_changes -> players (player_id));
+diesel::joinable!(ratings -> players (player_id));
+diesel::joinable!(ratings -> queues (queue_id));
+diesel::joinable!(row_slots -> players (player_id));
+diesel::joinable!(row_slots -> queue_rows (row_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(stations -> queue_rows (row_id));
+diesel::joinable!(stations -> queues (queue_id));
 
-diesel::allow_tables_to_appear_in_same_query!(queue_rows, queues,);
+diesel::allow_tables_to_appear_in_same_query!(
+    legacy_admin_tokens,
+    match_slots,
+    matches,
+    players,
+    queue_rows,
+    queues,
+    rating_changes,
+    ratings,
+    row_slots,
+    sessions,
+    stations,
+    users,
+);
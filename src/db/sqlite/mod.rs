@@ -0,0 +1,1828 @@
+//! SQLite backend for running itq on a single machine without Postgres.
+//! Enabled with the `sqlite` feature and chosen by a `sqlite://` DATABASE_URL.
+//!
+//! Everything goes through one connection, taken in turn, which is plenty
+//! for a venue laptop and avoids SQLite's "database is locked" errors.
+
+mod models;
+mod schema;
+
+use self::models::Id;
+use crate::db::api::{self, ApiError};
+use crate::db::notify::QueueChanges;
+use crate::db::store::QueueStore;
+use crate::db::{self, Lineup};
+use crate::estimate;
+use crate::history::{count_games, GamesPlayed, PlayedMatch};
+use crate::queue::{
+    CreatedQueue, Player, QueueEntry, QueueInfo, QueueMode, QueueSettings,
+    QueueState, QueueStatus, Side, Slot, Station,
+};
+use crate::rating::{RatingPoint, Standing};
+use crate::user::User;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+use diesel::sql_types::Text;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use diesel_migrations::{
+    embed_migrations, EmbeddedMigrations, MigrationHarness,
+};
+use leptos::logging::error;
+use std::collections::HashMap;
+use tokio::sync::{broadcast, Mutex};
+use uuid::Uuid;
+
+type Conn = SyncConnectionWrapper<SqliteConnection>;
+
+/// Kept separate from the Postgres migrations, which use Postgres-only
+/// types and triggers.
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite");
+
+diesel::define_sql_function!(fn lower(x: Text) -> Text);
+diesel::define_sql_function!(fn sha256(x: Text) -> Binary);
+
+/// Stores everything in a SQLite database file.
+pub struct SqliteStore {
+    conn: Mutex<Conn>,
+    changes: QueueChanges,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if needed, and brings its
+    /// schema up to date.
+    pub fn open(
+        path: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = SqliteConnection::establish(path)?;
+        // Foreign keys, and so cascading deletes, are off by default
+        conn.batch_execute("PRAGMA foreign_keys = ON")?;
+        // The migration giving old queues admin tokens hashes them like
+        // `api::hash_token`, which SQLite can't do by itself.
+        sha256_utils::register_impl(&mut conn, |token: String| {
+            api::hash_token(&token)
+        })?;
+        conn.run_pending_migrations(MIGRATIONS)?;
+        let (changes, _) = broadcast::channel(64);
+        Ok(SqliteStore {
+            conn: Mutex::new(SyncConnectionWrapper::new(conn)),
+            changes,
+        })
+    }
+
+    /// Announces which queue changed whenever its rows do. SQLite has no
+    /// notifications, so the store sends these itself.
+    pub fn changes(&self) -> QueueChanges {
+        self.changes.clone()
+    }
+
+    fn notify(&self, queue_id: Uuid) {
+        // Nobody listening is fine.
+        let _ = self.changes.send(queue_id);
+    }
+}
+
+fn unique_violation(e: diesel::result::Error, taken: ApiError) -> ApiError {
+    match e {
+        DatabaseError(DatabaseErrorKind::UniqueViolation, _) => taken,
+        e => e.into(),
+    }
+}
+
+async fn find_row(
+    row_id: Uuid,
+    conn: &mut Conn,
+) -> Result<db::QueueRow, ApiError> {
+    use schema::queue_rows::dsl;
+    let record = dsl::queue_rows
+        .find(Id(row_id))
+        .select(models::RowRecord::as_select())
+        .first(conn)
+        .await?;
+    load_rows(vec![record], conn)
+        .await?
+        .pop()
+        .ok_or(ApiError::NotFound)
+}
+
+/// Loads the slots of stored rows, keeping the rows' order.
+async fn load_rows(
+    records: Vec<models::RowRecord>,
+    conn: &mut Conn,
+) -> Result<Vec<db::QueueRow>, ApiError> {
+    use schema::row_slots::dsl;
+    let row_ids: Vec<Id> = records
+        .iter()
+        .map(|r| r.id)
+        .collect();
+    let slots = dsl::row_slots
+        .filter(dsl::row_id.eq_any(row_ids))
+        .select(models::RowSlot::as_select())
+        .load(conn)
+        .await?;
+    Ok(db::QueueRow::assemble(
+        records
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        slots
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    ))
+}
+
+/// Loads the slots of stored matches, keeping the matches' order.
+async fn load_match_slots(
+    records: Vec<models::MatchRecord>,
+    conn: &mut Conn,
+) -> Result<Vec<db::Match>, ApiError> {
+    use schema::match_slots::dsl;
+    let match_ids: Vec<Id> = records
+        .iter()
+        .map(|m| m.id)
+        .collect();
+    let slots = dsl::match_slots
+        .filter(dsl::match_id.eq_any(match_ids))
+        .select(models::MatchSlot::as_select())
+        .load(conn)
+        .await?;
+    Ok(db::Match::assemble(
+        records
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        slots
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    ))
+}
+
+/// How many players make up each side of a queue's rows.
+async fn team_size(queue_id: Uuid, conn: &mut Conn) -> Result<i32, ApiError> {
+    use schema::queues::dsl;
+    Ok(dsl::queues
+        .find(Id(queue_id))
+        .select(dsl::team_size)
+        .first(conn)
+        .await?)
+}
+
+/// Loads a queue's rows and fails unless its settings let `player_id` join,
+/// as in [`api::check_can_join`].
+async fn check_can_join(
+    queue_id: Uuid,
+    player_id: Uuid,
+    new_row: bool,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::queue_rows;
+    let records = queue_rows::table
+        .filter(queue_rows::queue_id.eq(Id(queue_id)))
+        .select(models::RowRecord::as_select())
+        .load(conn)
+        .await?;
+    let rows = load_rows(records, conn).await?;
+    api::check_can_join(
+        &queue_settings(queue_id, conn).await?,
+        &rows,
+        player_id,
+        new_row,
+    )
+}
+
+/// Who may join a queue, and how much of it they may take up.
+async fn queue_settings(
+    queue_id: Uuid,
+    conn: &mut Conn,
+) -> Result<QueueSettings, ApiError> {
+    use schema::queues::dsl;
+    let queue = dsl::queues
+        .find(Id(queue_id))
+        .select(models::Queue::as_select())
+        .first(conn)
+        .await?;
+    Ok(QueueInfo::from(db::Queue::from(queue)).settings)
+}
+
+async fn load_state(
+    queue_id: Uuid,
+    conn: &mut Conn,
+) -> Result<QueueState, ApiError> {
+    use schema::{matches, queue_rows, queues};
+    let version = queues::table
+        .find(Id(queue_id))
+        .select(queues::version)
+        .first::<i32>(conn)
+        .await?;
+    let records = queue_rows::table
+        .filter(queue_rows::queue_id.eq(Id(queue_id)))
+        .order(queue_rows::queue_order.asc())
+        .select(models::RowRecord::as_select())
+        .load(conn)
+        .await?;
+    let rows = load_rows(records, conn).await?;
+    let mut entries = load_entries(rows, conn).await?;
+    let stations: Vec<Station> = load_stations(queue_id, conn)
+        .await?
+        .into_iter()
+        .map(Station::from)
+        .collect();
+    let recent = matches::table
+        .filter(matches::queue_id.eq(Id(queue_id)))
+        .order(matches::ended_at.desc())
+        .limit(estimate::RECENT_MATCHES)
+        .select((matches::started_at, matches::ended_at))
+        .load::<(DateTime<Utc>, DateTime<Utc>)>(conn)
+        .await?;
+    // Not `first()`, which diesel also has
+    let last_ended_at = recent
+        .iter()
+        .map(|(_, ended_at)| *ended_at)
+        .next();
+    estimate::estimate_starts(
+        &mut entries,
+        &stations,
+        last_ended_at,
+        estimate::median_duration(&recent),
+        Utc::now(),
+    );
+    Ok(QueueState {
+        version,
+        entries,
+        stations,
+    })
+}
+
+/// Loads a queue's stations, oldest first.
+async fn load_stations(
+    queue_id: Uuid,
+    conn: &mut Conn,
+) -> Result<Vec<db::Station>, ApiError> {
+    use schema::stations::dsl;
+    Ok(dsl::stations
+        .filter(dsl::queue_id.eq(Id(queue_id)))
+        .order((dsl::created_at.asc(), dsl::id.asc()))
+        .select(models::Station::as_select())
+        .load(conn)
+        .await?
+        .into_iter()
+        .map(db::Station::from)
+        .collect())
+}
+
+async fn find_station(
+    station_id: Uuid,
+    conn: &mut Conn,
+) -> Result<db::Station, ApiError> {
+    use schema::stations::dsl;
+    Ok(dsl::stations
+        .find(Id(station_id))
+        .select(models::Station::as_select())
+        .first(conn)
+        .await?
+        .into())
+}
+
+async fn write_station(
+    station: &db::Station,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::stations::dsl;
+    diesel::update(dsl::stations.find(Id(station.id)))
+        .set(models::Station::from(station.clone()))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Starts the first row waiting in a station's queue on it, uncalling it
+/// now that it's playing, or frees the station if nobody's waiting.
+async fn fill_station(
+    station: &mut db::Station,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::queue_rows::dsl;
+    let stations = load_stations(station.queue_id, conn).await?;
+    let next_id = dsl::queue_rows
+        .filter(dsl::queue_id.eq(Id(station.queue_id)))
+        .order(dsl::queue_order.asc())
+        .select(dsl::id)
+        .load::<Id>(conn)
+        .await?
+        .into_iter()
+        .map(|id| id.0)
+        .find(|&id| !api::on_station(id, &stations));
+    if let Some(row_id) = next_id {
+        let mut row = find_row(row_id, conn).await?;
+        if row.called_at.is_some() {
+            row.uncall();
+            write_row(&mut row, conn).await?;
+        }
+    }
+    station.row_id = next_id;
+    station.started_at = next_id.map(|_| Utc::now());
+    write_station(station, conn).await
+}
+
+/// Gets a queue's version, failing with [`ApiError::Conflict`] if
+/// `expected_version` is given and the queue has moved past it. Only one
+/// change runs at a time, so unlike Postgres nothing needs locking.
+async fn check_version(
+    queue_id: Uuid,
+    expected_version: Option<i32>,
+    conn: &mut Conn,
+) -> Result<i32, ApiError> {
+    use schema::queues::dsl;
+    let version = dsl::queues
+        .find(Id(queue_id))
+        .select(dsl::version)
+        .first::<i32>(conn)
+        .await?;
+    if expected_version.is_some_and(|expected| expected != version) {
+        return Err(ApiError::Conflict {
+            current: load_state(queue_id, conn).await?,
+        });
+    }
+    Ok(version)
+}
+
+/// Bumps a queue's version after checking it like [`check_version`].
+async fn bump_version(
+    queue_id: Uuid,
+    expected_version: Option<i32>,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::queues::dsl;
+    let version = check_version(queue_id, expected_version, conn).await?;
+    diesel::update(dsl::queues.find(Id(queue_id)))
+        .set(dsl::version.eq(version + 1))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Converts rows to entries, loading the players they reference.
+async fn load_entries(
+    db_rows: Vec<db::QueueRow>,
+    conn: &mut Conn,
+) -> Result<Vec<QueueEntry>, ApiError> {
+    let player_ids = db_rows
+        .iter()
+        .flat_map(|r| r.players.values().copied());
+    let players = load_players(player_ids, conn).await?;
+
+    Ok(db_rows
+        .into_iter()
+        .filter_map(|r| {
+            let slots = api::lineup_players(&r.players, &players);
+            (r, slots)
+                .try_into()
+                .inspect_err(|e| error!("{e}"))
+                .ok()
+        })
+        .collect())
+}
+
+/// Converts matches to played matches, loading their players.
+async fn load_matches(
+    records: Vec<models::MatchRecord>,
+    conn: &mut Conn,
+) -> Result<Vec<PlayedMatch>, ApiError> {
+    let db_matches = load_match_slots(records, conn).await?;
+    let player_ids = db_matches
+        .iter()
+        .flat_map(|m| m.players.values().copied());
+    let players = load_players(player_ids, conn).await?;
+
+    Ok(db_matches
+        .into_iter()
+        .map(|m| {
+            let slots = api::lineup_players(&m.players, &players);
+            (m, slots).into()
+        })
+        .collect())
+}
+
+/// Loads the players with the given ids.
+async fn load_players(
+    player_ids: impl Iterator<Item = Uuid>,
+    conn: &mut Conn,
+) -> Result<HashMap<Uuid, db::Player>, ApiError> {
+    use schema::players::dsl;
+
+    let player_ids: Vec<Id> = player_ids.map(Id).collect();
+    Ok(dsl::players
+        .filter(dsl::id.eq_any(player_ids))
+        .select(models::Player::as_select())
+        .load(conn)
+        .await?
+        .into_iter()
+        .map(|p| (p.id.0, p.into()))
+        .collect())
+}
+
+/// Saves a row and its slots, bumping its version, or deletes it and closes
+/// the gap if it's empty.
+async fn write_row(
+    row: &mut db::QueueRow,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::queue_rows::dsl;
+    if row.is_empty() {
+        diesel::delete(dsl::queue_rows.find(Id(row.id)))
+            .execute(conn)
+            .await?;
+        renumber_rows(row.queue_id, conn).await
+    } else {
+        row.version += 1;
+        diesel::update(dsl::queue_rows.find(Id(row.id)))
+            .set(models::RowRecord::from(row.record()))
+            .execute(conn)
+            .await?;
+        insert_slots(row, conn).await
+    }
+}
+
+/// Replaces the stored slots of a row with the ones it has now.
+async fn insert_slots(
+    row: &db::QueueRow,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::row_slots::dsl;
+    diesel::delete(dsl::row_slots.filter(dsl::row_id.eq(Id(row.id))))
+        .execute(conn)
+        .await?;
+    // SQLite can't insert several rows in one statement through diesel.
+    for slot in row.slots() {
+        diesel::insert_into(dsl::row_slots)
+            .values(models::RowSlot::from(slot))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Saves a match that just ended along with its players, and updates their
+/// ratings.
+async fn record_match(
+    played: &db::Match,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::{match_slots, matches};
+    diesel::insert_into(matches::table)
+        .values(models::MatchRecord::from(played.record()))
+        .execute(conn)
+        .await?;
+    for slot in played.slots() {
+        diesel::insert_into(match_slots::table)
+            .values(models::MatchSlot::from(slot))
+            .execute(conn)
+            .await?;
+    }
+    update_ratings(played, conn).await
+}
+
+/// Updates the ratings of everyone who played in a match that just ended,
+/// and records how each one moved.
+async fn update_ratings(
+    played: &db::Match,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::{rating_changes, ratings::dsl};
+    let player_ids: Vec<Id> = played
+        .players
+        .values()
+        .map(|&id| Id(id))
+        .collect();
+    let current: Vec<db::Rating> = dsl::ratings
+        .filter(dsl::queue_id.eq(Id(played.queue_id)))
+        .filter(dsl::player_id.eq_any(player_ids))
+        .select(models::Rating::as_select())
+        .load(conn)
+        .await?
+        .into_iter()
+        .map(db::Rating::from)
+        .collect();
+    for (rating, change) in played.rate(&current) {
+        let rating = models::Rating::from(rating);
+        diesel::insert_into(dsl::ratings)
+            .values(&rating)
+            .on_conflict((dsl::queue_id, dsl::player_id))
+            .do_update()
+            .set(&rating)
+            .execute(conn)
+            .await?;
+        diesel::insert_into(rating_changes::table)
+            .values(models::RatingChange::from(change))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Inserts a row with the given players after the last row of a queue.
+async fn insert_row_at_back(
+    queue_id: Uuid,
+    players: &Lineup,
+    conn: &mut Conn,
+) -> Result<Uuid, ApiError> {
+    use schema::queue_rows::dsl;
+    let max_order = dsl::queue_rows
+        .filter(dsl::queue_id.eq(Id(queue_id)))
+        .select(dsl::queue_order)
+        .order(dsl::queue_order.desc())
+        .first::<i32>(conn)
+        .await
+        .optional()?;
+    let row = db::QueueRow {
+        id: Uuid::new_v4(),
+        queue_id,
+        queue_order: max_order.map_or(0, |o| o + 1),
+        created_at: Utc::now(),
+        version: 0,
+        called_at: None,
+        players: players.clone(),
+        checked_in: Default::default(),
+    };
+    diesel::insert_into(dsl::queue_rows)
+        .values(models::RowRecord::from(row.record()))
+        .execute(conn)
+        .await?;
+    insert_slots(&row, conn).await?;
+    Ok(row.id)
+}
+
+/// Renumbers the rows of a queue so `queue_order` runs contiguously from 0.
+async fn renumber_rows(
+    queue_id: Uuid,
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::queue_rows::dsl;
+    let ids: Vec<Id> = dsl::queue_rows
+        .filter(dsl::queue_id.eq(Id(queue_id)))
+        .order(dsl::queue_order.asc())
+        .select(dsl::id)
+        .load(conn)
+        .await?;
+    write_order(queue_id, &ids, conn).await
+}
+
+/// Numbers a queue's rows in the order given.
+///
+/// SQLite checks the `(queue_id, queue_order)` unique constraint after every
+/// row, so the rows are first moved out of the way to negative orders. Only
+/// the rows that end up somewhere new get their version bumped.
+async fn write_order(
+    queue_id: Uuid,
+    ids: &[Id],
+    conn: &mut Conn,
+) -> Result<(), ApiError> {
+    use schema::queue_rows::dsl;
+    diesel::update(dsl::queue_rows.filter(dsl::queue_id.eq(Id(queue_id))))
+        .set(dsl::queue_order.eq((dsl::queue_order + 1) * -1))
+        .execute(conn)
+        .await?;
+    for (order, id) in ids.iter().enumerate() {
+        let order = order as i32;
+        diesel::update(
+            dsl::queue_rows
+                .find(*id)
+                .filter(dsl::queue_order.ne(-(order + 1))),
+        )
+        .set(dsl::version.eq(dsl::version + 1))
+        .execute(conn)
+        .await?;
+        diesel::update(dsl::queue_rows.find(*id))
+            .set(dsl::queue_order.eq(order))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl QueueStore for SqliteStore {
+    async fn get_all_queues(&self) -> Result<Vec<QueueInfo>, ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+        Ok(dsl::queues
+            .select(models::Queue::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(|q| db::Queue::from(q).into())
+            .collect())
+    }
+
+    async fn get_queue_info(
+        &self,
+        url_name: String,
+    ) -> Result<QueueInfo, ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+        let queue = dsl::queues
+            .filter(dsl::url_name.eq(url_name))
+            .select(models::Queue::as_select())
+            .first(conn)
+            .await?;
+        Ok(db::Queue::from(queue).into())
+    }
+
+    async fn get_queue_state(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<QueueState, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+        load_state(queue_id, conn).await
+    }
+
+    async fn get_user_queues(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<QueueInfo>, ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+        Ok(dsl::queues
+            .filter(dsl::created_by.eq(Id(user_id)))
+            .order(dsl::created_at.desc())
+            .select(models::Queue::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(|q| db::Queue::from(q).into())
+            .collect())
+    }
+
+    async fn add_queue(
+        &self,
+        display_name: String,
+        url_name: String,
+        created_by: Option<Uuid>,
+    ) -> Result<CreatedQueue, ApiError> {
+        use schema::queues;
+        let conn = &mut *self.conn.lock().await;
+
+        let admin_token = api::new_admin_token();
+        let queue = models::Queue {
+            id: Id(Uuid::new_v4()),
+            url_name,
+            display_name,
+            created_at: Utc::now(),
+            admin_token_hash: Some(api::hash_token(&admin_token)),
+            created_by: created_by.map(Id),
+            version: 0,
+            mode: QueueMode::default().to_string(),
+            max_streak: None,
+            singles: false,
+            team_size: 1,
+            no_show_timeout: None,
+            no_show_move_back: None,
+            max_rows: None,
+            max_entries_per_player: None,
+            status: QueueStatus::default().to_string(),
+            unique_players: false,
+        };
+        diesel::insert_into(queues::table)
+            .values(&queue)
+            .execute(conn)
+            .await
+            .map_err(|e| unique_violation(e, ApiError::UrlNameTaken))?;
+        Ok(CreatedQueue {
+            info: db::Queue::from(queue).into(),
+            admin_token,
+        })
+    }
+
+    async fn delete_queue(&self, queue_id: Uuid) -> Result<(), ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+        diesel::delete(dsl::queues.find(Id(queue_id)))
+            .execute(conn)
+            .await?;
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn set_queue_mode(
+        &self,
+        queue_id: Uuid,
+        mode: QueueMode,
+        max_streak: Option<i32>,
+        singles: bool,
+        team_size: i32,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let queue = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, expected_version, conn).await?;
+                    diesel::update(dsl::queues.find(Id(queue_id)))
+                        .set((
+                            dsl::mode.eq(mode.to_string()),
+                            dsl::max_streak.eq(max_streak),
+                            dsl::singles.eq(singles),
+                            dsl::team_size.eq(team_size),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    Ok(dsl::queues
+                        .find(Id(queue_id))
+                        .select(models::Queue::as_select())
+                        .first(conn)
+                        .await?)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(db::Queue::from(queue).into())
+    }
+
+    async fn set_no_show_rule(
+        &self,
+        queue_id: Uuid,
+        timeout: Option<i32>,
+        move_back: Option<i32>,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        use schema::{queue_rows, queues::dsl};
+        let conn = &mut *self.conn.lock().await;
+
+        let queue = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, expected_version, conn).await?;
+                    diesel::update(dsl::queues.find(Id(queue_id)))
+                        .set((
+                            dsl::no_show_timeout.eq(timeout),
+                            dsl::no_show_move_back.eq(move_back),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    let called = queue_rows::table
+                        .filter(queue_rows::queue_id.eq(Id(queue_id)))
+                        .filter(queue_rows::called_at.is_not_null());
+                    diesel::update(called)
+                        .set((
+                            queue_rows::called_at.eq(None::<DateTime<Utc>>),
+                            queue_rows::version.eq(queue_rows::version + 1),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    Ok(dsl::queues
+                        .find(Id(queue_id))
+                        .select(models::Queue::as_select())
+                        .first(conn)
+                        .await?)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(db::Queue::from(queue).into())
+    }
+
+    async fn set_queue_settings(
+        &self,
+        queue_id: Uuid,
+        settings: QueueSettings,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let queue = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, expected_version, conn).await?;
+                    diesel::update(dsl::queues.find(Id(queue_id)))
+                        .set((
+                            dsl::max_rows.eq(settings.max_rows),
+                            dsl::max_entries_per_player
+                                .eq(settings.max_entries_per_player),
+                            dsl::status.eq(settings.status.to_string()),
+                            dsl::unique_players.eq(settings.unique_players),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    Ok(dsl::queues
+                        .find(Id(queue_id))
+                        .select(models::Queue::as_select())
+                        .first(conn)
+                        .await?)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(db::Queue::from(queue).into())
+    }
+
+    async fn is_queue_admin(
+        &self,
+        queue_id: Uuid,
+        admin_token: Option<String>,
+        user_id: Option<Uuid>,
+    ) -> Result<bool, ApiError> {
+        use schema::queues::dsl;
+        let conn = &mut *self.conn.lock().await;
+        let (stored_hash, created_by): (Option<Vec<u8>>, Option<Id>) =
+            dsl::queues
+                .find(Id(queue_id))
+                .select((dsl::admin_token_hash, dsl::created_by))
+                .first(conn)
+                .await?;
+        Ok(api::grants_admin(
+            stored_hash.as_deref(),
+            created_by.map(|id| id.0),
+            admin_token,
+            user_id,
+        ))
+    }
+
+    async fn get_or_create_player(
+        &self,
+        display_name: String,
+        tag: Option<String>,
+    ) -> Result<Player, ApiError> {
+        use schema::players::{self, dsl};
+        let display_name = api::clean_player_name(&display_name)?;
+        let tag = api::clean_player_tag(tag.as_deref());
+        let conn = &mut *self.conn.lock().await;
+
+        let mut existing = dsl::players
+            .filter(lower(dsl::display_name).eq(lower(display_name)))
+            .select(models::Player::as_select())
+            .into_boxed();
+        existing = match tag {
+            Some(tag) => existing.filter(dsl::tag.eq(tag)),
+            None => existing.filter(dsl::tag.is_null()),
+        };
+        let existing = existing
+            .first(conn)
+            .await
+            .optional()?;
+        let player = match existing {
+            Some(player) => player,
+            None => {
+                let player = models::Player {
+                    id: Id(Uuid::new_v4()),
+                    display_name: display_name.to_string(),
+                    tag: tag.map(str::to_string),
+                    created_at: Utc::now(),
+                };
+                diesel::insert_into(players::table)
+                    .values(&player)
+                    .execute(conn)
+                    .await?;
+                player
+            }
+        };
+        Ok(db::Player::from(player).into())
+    }
+
+    async fn search_players(
+        &self,
+        query: String,
+    ) -> Result<Vec<Player>, ApiError> {
+        use schema::players::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        // Escape LIKE wildcards so they match literally. SQLite's LIKE
+        // already ignores case.
+        let pattern = format!(
+            "%{}%",
+            query
+                .trim()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        Ok(dsl::players
+            .filter(
+                dsl::display_name
+                    .like(pattern)
+                    .escape('\\'),
+            )
+            .order(dsl::display_name.asc())
+            .limit(10)
+            .select(models::Player::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(|p| db::Player::from(p).into())
+            .collect())
+    }
+
+    async fn get_row_queue_id(&self, row_id: Uuid) -> Result<Uuid, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+        Ok(find_row(row_id, conn)
+            .await?
+            .queue_id)
+    }
+
+    async fn add_row(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<Uuid, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+
+        let row_id = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, None, conn).await?;
+                    api::check_slot(slot, team_size(queue_id, conn).await?)?;
+                    check_can_join(queue_id, player_id, true, conn).await?;
+                    let players = Lineup::from([(slot, player_id)]);
+                    insert_row_at_back(queue_id, &players, conn).await
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(row_id)
+    }
+
+    async fn add_player_to_row(
+        &self,
+        row_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+
+        let row = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let mut row = find_row(row_id, conn).await?;
+                    api::check_slot(
+                        slot,
+                        team_size(row.queue_id, conn).await?,
+                    )?;
+                    check_can_join(row.queue_id, player_id, false, conn)
+                        .await?;
+                    if row.player(slot).is_some() {
+                        return Err(ApiError::Occupied {
+                            row_id,
+                            order: row.queue_order,
+                            slot,
+                        });
+                    }
+                    row.players.insert(slot, player_id);
+                    bump_version(row.queue_id, None, conn).await?;
+                    write_row(&mut row, conn).await?;
+                    Ok(row)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(row.queue_id);
+        load_entries(vec![row], conn)
+            .await?
+            .pop()
+            .ok_or(ApiError::NotFound)
+    }
+
+    async fn join_queue(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<QueueEntry, ApiError> {
+        use schema::queue_rows::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let row = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, None, conn).await?;
+                    let team_size = team_size(queue_id, conn).await?;
+                    let records = dsl::queue_rows
+                        .filter(dsl::queue_id.eq(Id(queue_id)))
+                        .order(dsl::queue_order.asc())
+                        .select(models::RowRecord::as_select())
+                        .load(conn)
+                        .await?;
+                    let waiting = load_rows(records, conn).await?;
+                    let settings = queue_settings(queue_id, conn).await?;
+                    let partner = api::find_partner(
+                        waiting.clone(),
+                        player_id,
+                        team_size,
+                    );
+                    api::check_can_join(
+                        &settings,
+                        &waiting,
+                        player_id,
+                        partner.is_none(),
+                    )?;
+                    match partner {
+                        Some((mut row, slot)) => {
+                            row.players.insert(slot, player_id);
+                            write_row(&mut row, conn).await?;
+                            Ok(row)
+                        }
+                        None => {
+                            let players =
+                                Lineup::from([(Side::Left.into(), player_id)]);
+                            let row_id =
+                                insert_row_at_back(queue_id, &players, conn)
+                                    .await?;
+                            find_row(row_id, conn).await
+                        }
+                    }
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        load_entries(vec![row], conn)
+            .await?
+            .pop()
+            .ok_or(ApiError::NotFound)
+    }
+
+    async fn remove_player_from_row(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+
+        let row = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let mut row = find_row(row_id, conn).await?;
+                    bump_version(row.queue_id, expected_version, conn).await?;
+                    row.players.remove(&slot);
+                    write_row(&mut row, conn).await?;
+                    Ok(row)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(row.queue_id);
+        if row.is_empty() {
+            return Ok(None);
+        }
+        Ok(load_entries(vec![row], conn)
+            .await?
+            .pop())
+    }
+
+    async fn delete_row(
+        &self,
+        row_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        use schema::queue_rows::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let queue_id = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let queue_id = find_row(row_id, conn)
+                        .await?
+                        .queue_id;
+                    bump_version(queue_id, expected_version, conn).await?;
+                    diesel::delete(dsl::queue_rows.find(Id(row_id)))
+                        .execute(conn)
+                        .await?;
+                    renumber_rows(queue_id, conn).await?;
+                    Ok(queue_id)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn move_row(
+        &self,
+        row_id: Uuid,
+        new_position: i32,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        use schema::queue_rows::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let queue_id = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let queue_id = find_row(row_id, conn)
+                        .await?
+                        .queue_id;
+                    bump_version(queue_id, expected_version, conn).await?;
+                    let mut ids: Vec<Id> = dsl::queue_rows
+                        .filter(dsl::queue_id.eq(Id(queue_id)))
+                        .order(dsl::queue_order.asc())
+                        .select(dsl::id)
+                        .load(conn)
+                        .await?;
+                    let old_position = ids
+                        .iter()
+                        .position(|id| *id == Id(row_id))
+                        .ok_or(ApiError::NotFound)?;
+                    let last = ids.len() as i32 - 1;
+                    if !(0..=last).contains(&new_position) {
+                        return Err(ApiError::InvalidOrder {
+                            expected: last,
+                            got: new_position,
+                        });
+                    }
+                    let moved = ids.remove(old_position);
+                    ids.insert(new_position as usize, moved);
+                    write_order(queue_id, &ids, conn).await?;
+                    Ok(queue_id)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn move_player(
+        &self,
+        from_row: Uuid,
+        from_slot: Slot,
+        to_row: Uuid,
+        to_slot: Slot,
+        swap: bool,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        if from_row == to_row && from_slot == to_slot {
+            return Ok(());
+        }
+        let conn = &mut *self.conn.lock().await;
+
+        let queue_id = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let mut rows = vec![find_row(from_row, conn).await?];
+                    if to_row != from_row {
+                        rows.push(find_row(to_row, conn).await?);
+                    }
+                    let to_index = rows.len() - 1;
+                    let queue_id = rows[0].queue_id;
+                    if rows[to_index].queue_id != queue_id {
+                        return Err(ApiError::DifferentQueues);
+                    }
+                    bump_version(queue_id, expected_version, conn).await?;
+                    api::check_slot(to_slot, team_size(queue_id, conn).await?)?;
+
+                    let Some(player) = rows[0].players.remove(&from_slot)
+                    else {
+                        return Err(ApiError::EmptySlot {
+                            row_id: from_row,
+                            slot: from_slot,
+                        });
+                    };
+                    let displaced = rows[to_index]
+                        .players
+                        .insert(to_slot, player);
+                    if displaced.is_some() && !swap {
+                        return Err(ApiError::Occupied {
+                            row_id: to_row,
+                            order: rows[to_index].queue_order,
+                            slot: to_slot,
+                        });
+                    }
+                    if let Some(displaced) = displaced {
+                        rows[0]
+                            .players
+                            .insert(from_slot, displaced);
+                    }
+
+                    // Write the target first so a row that loses its last
+                    // player is only deleted once the player has somewhere
+                    // to go.
+                    for row in rows.iter_mut().rev() {
+                        write_row(row, conn).await?;
+                    }
+                    Ok(queue_id)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn complete_front_row(
+        &self,
+        queue_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        use schema::{matches, queue_rows::dsl, queues};
+        let conn = &mut *self.conn.lock().await;
+
+        let front = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, expected_version, conn).await?;
+                    // The front row may be on a station, which would never
+                    // be freed.
+                    if !load_stations(queue_id, conn)
+                        .await?
+                        .is_empty()
+                    {
+                        return Err(ApiError::StationRequired { queue_id });
+                    }
+                    let queue: QueueInfo = db::Queue::from(
+                        queues::table
+                            .find(Id(queue_id))
+                            .select(models::Queue::as_select())
+                            .first(conn)
+                            .await?,
+                    )
+                    .into();
+                    let records = dsl::queue_rows
+                        .filter(dsl::queue_id.eq(Id(queue_id)))
+                        .order(dsl::queue_order.asc())
+                        .limit(2)
+                        .select(models::RowRecord::as_select())
+                        .load(conn)
+                        .await?;
+                    let mut front_rows = load_rows(records, conn)
+                        .await?
+                        .into_iter();
+                    let Some(front) = front_rows.next() else {
+                        return Ok(None);
+                    };
+                    let recent = matches::table
+                        .filter(matches::queue_id.eq(Id(queue_id)))
+                        .order(matches::ended_at.desc())
+                        .limit(api::recent_matches_needed(&queue))
+                        .select(models::MatchRecord::as_select())
+                        .load(conn)
+                        .await?;
+                    let recent = load_match_slots(recent, conn).await?;
+                    // Not `first()`, which diesel also has
+                    let last_ended_at = recent
+                        .iter()
+                        .map(|m| m.ended_at)
+                        .next();
+                    let played =
+                        db::Match::finished(&front, winner, last_ended_at)?;
+                    record_match(&played, conn).await?;
+                    let entry = load_entries(vec![front.clone()], conn)
+                        .await?
+                        .pop();
+
+                    let front_id = front.id;
+                    let rotation = api::rotate(
+                        &queue,
+                        front,
+                        front_rows.next(),
+                        winner,
+                        &recent,
+                    );
+                    match rotation.front {
+                        Some(mut row) => write_row(&mut row, conn).await?,
+                        None => {
+                            diesel::delete(dsl::queue_rows.find(Id(front_id)))
+                                .execute(conn)
+                                .await?;
+                            renumber_rows(queue_id, conn).await?;
+                        }
+                    }
+                    if let Some(mut row) = rotation.next {
+                        write_row(&mut row, conn).await?;
+                    }
+                    if let Some(players) = rotation.to_back {
+                        insert_row_at_back(queue_id, &players, conn).await?;
+                    }
+                    Ok(entry)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        if front.is_some() {
+            self.notify(queue_id);
+        }
+        Ok(front)
+    }
+
+    async fn check_in(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+
+        let entry = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let mut row = find_row(row_id, conn).await?;
+                    bump_version(row.queue_id, None, conn).await?;
+                    api::check_in_player(&mut row, slot)?;
+                    write_row(&mut row, conn).await?;
+                    load_entries(vec![row], conn)
+                        .await?
+                        .pop()
+                        .ok_or(ApiError::NotFound)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(entry.queue_id);
+        Ok(entry)
+    }
+
+    async fn check_no_shows(&self, now: DateTime<Utc>) -> Result<(), ApiError> {
+        use schema::{queue_rows::dsl, queues};
+        let conn = &mut *self.conn.lock().await;
+
+        let timed: Vec<QueueInfo> = queues::table
+            .filter(queues::no_show_timeout.is_not_null())
+            .select(models::Queue::as_select())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(|q| db::Queue::from(q).into())
+            .collect();
+        for queue in timed {
+            let changed = conn
+                .transaction::<_, ApiError, _>(|conn| {
+                    let queue = &queue;
+                    async move {
+                        let records = dsl::queue_rows
+                            .filter(dsl::queue_id.eq(Id(queue.id)))
+                            .order(dsl::queue_order.asc())
+                            .select(models::RowRecord::as_select())
+                            .load(conn)
+                            .await?;
+                        let rows = load_rows(records, conn).await?;
+                        let stations = load_stations(queue.id, conn).await?;
+                        let check = api::no_shows(queue, rows, &stations, now);
+                        if check.is_empty() {
+                            return Ok(false);
+                        }
+                        bump_version(queue.id, None, conn).await?;
+                        for mut row in check.changed {
+                            write_row(&mut row, conn).await?;
+                        }
+                        if let Some((row_id, _, position)) = check.move_back {
+                            let mut ids: Vec<Id> = dsl::queue_rows
+                                .filter(dsl::queue_id.eq(Id(queue.id)))
+                                .filter(dsl::id.ne(Id(row_id)))
+                                .order(dsl::queue_order.asc())
+                                .select(dsl::id)
+                                .load(conn)
+                                .await?;
+                            ids.insert(position as usize, Id(row_id));
+                            write_order(queue.id, &ids, conn).await?;
+                        }
+                        if let Some(row_id) = check.dropped {
+                            diesel::delete(dsl::queue_rows.find(Id(row_id)))
+                                .execute(conn)
+                                .await?;
+                            renumber_rows(queue.id, conn).await?;
+                        }
+                        Ok(true)
+                    }
+                    .scope_boxed()
+                })
+                .await;
+            match changed {
+                Ok(true) => self.notify(queue.id),
+                Ok(false) => {}
+                Err(e) => error!(
+                    "Error checking queue {} for no-shows: {e}",
+                    queue.id
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    async fn add_station(
+        &self,
+        queue_id: Uuid,
+        name: String,
+        expected_version: Option<i32>,
+    ) -> Result<Station, ApiError> {
+        use schema::stations::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let station = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    bump_version(queue_id, expected_version, conn).await?;
+                    let existing = load_stations(queue_id, conn).await?;
+                    let station = db::Station {
+                        id: Uuid::new_v4(),
+                        queue_id,
+                        name: api::station_name(&name, existing.len()),
+                        created_at: Utc::now(),
+                        row_id: None,
+                        started_at: None,
+                    };
+                    diesel::insert_into(dsl::stations)
+                        .values(models::Station::from(station.clone()))
+                        .execute(conn)
+                        .await?;
+                    Ok(station)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(station.into())
+    }
+
+    async fn delete_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        use schema::stations::dsl;
+        let conn = &mut *self.conn.lock().await;
+
+        let queue_id = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let queue_id = find_station(station_id, conn)
+                        .await?
+                        .queue_id;
+                    bump_version(queue_id, expected_version, conn).await?;
+                    diesel::delete(dsl::stations.find(Id(station_id)))
+                        .execute(conn)
+                        .await?;
+                    Ok(queue_id)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(())
+    }
+
+    async fn get_station_queue_id(
+        &self,
+        station_id: Uuid,
+    ) -> Result<Uuid, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+        Ok(find_station(station_id, conn)
+            .await?
+            .queue_id)
+    }
+
+    async fn assign_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        let conn = &mut *self.conn.lock().await;
+
+        let (queue_id, assigned, entry) = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let mut station = find_station(station_id, conn).await?;
+                    check_version(station.queue_id, expected_version, conn)
+                        .await?;
+                    let assigned = station.row_id.is_none();
+                    if assigned {
+                        fill_station(&mut station, conn).await?;
+                        bump_version(station.queue_id, None, conn).await?;
+                    }
+                    let entry = match station.row_id {
+                        Some(row_id) => {
+                            let row = find_row(row_id, conn).await?;
+                            load_entries(vec![row], conn)
+                                .await?
+                                .pop()
+                        }
+                        None => None,
+                    };
+                    Ok((station.queue_id, assigned, entry))
+                }
+                .scope_boxed()
+            })
+            .await?;
+        if assigned {
+            self.notify(queue_id);
+        }
+        Ok(entry)
+    }
+
+    async fn free_station(
+        &self,
+        station_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        use schema::{match_slots, matches, queue_rows::dsl, queues};
+        let conn = &mut *self.conn.lock().await;
+
+        let (queue_id, entry) = conn
+            .transaction::<_, ApiError, _>(|conn| {
+                async move {
+                    let mut station = find_station(station_id, conn).await?;
+                    let queue_id = station.queue_id;
+                    bump_version(queue_id, expected_version, conn).await?;
+                    let queue: QueueInfo = db::Queue::from(
+                        queues::table
+                            .find(Id(queue_id))
+                            .select(models::Queue::as_select())
+                            .first(conn)
+                            .await?,
+                    )
+                    .into();
+                    let records = dsl::queue_rows
+                        .filter(dsl::queue_id.eq(Id(queue_id)))
+                        .order(dsl::queue_order.asc())
+                        .select(models::RowRecord::as_select())
+                        .load(conn)
+                        .await?;
+                    let stations = load_stations(queue_id, conn).await?;
+                    let (playing, waiting): (Vec<_>, Vec<_>) =
+                        load_rows(records, conn)
+                            .await?
+                            .into_iter()
+                            .partition(|r| api::on_station(r.id, &stations));
+                    let Some(front) = playing
+                        .into_iter()
+                        .find(|r| station.row_id == Some(r.id))
+                    else {
+                        fill_station(&mut station, conn).await?;
+                        return Ok((queue_id, None));
+                    };
+                    // Other stations' matches don't break a streak, so only
+                    // count the ones these players were in.
+                    let player_ids: Vec<Id> = front
+                        .players
+                        .values()
+                        .map(|&id| Id(id))
+                        .collect();
+                    let recent = matches::table
+                        .filter(matches::queue_id.eq(Id(queue_id)))
+                        .filter(
+                            matches::id.eq_any(
+                                match_slots::table
+                                    .filter(
+                                        match_slots::player_id
+                                            .eq_any(player_ids),
+                                    )
+                                    .select(match_slots::match_id),
+                            ),
+                        )
+                        .order(matches::ended_at.desc())
+                        .limit(api::recent_matches_needed(&queue))
+                        .select(models::MatchRecord::as_select())
+                        .load(conn)
+                        .await?;
+                    let recent = load_match_slots(recent, conn).await?;
+                    let played = db::Match::finished(
+                        &front,
+                        winner,
+                        station.started_at,
+                    )?;
+                    record_match(&played, conn).await?;
+                    let entry = load_entries(vec![front.clone()], conn)
+                        .await?
+                        .pop();
+
+                    let front_id = front.id;
+                    let rotation = api::rotate(
+                        &queue,
+                        front,
+                        waiting.into_iter().next(),
+                        winner,
+                        &recent,
+                    );
+                    let stayed = match rotation.front {
+                        Some(mut row) => {
+                            write_row(&mut row, conn).await?;
+                            true
+                        }
+                        None => {
+                            // Frees the station too.
+                            diesel::delete(dsl::queue_rows.find(Id(front_id)))
+                                .execute(conn)
+                                .await?;
+                            renumber_rows(queue_id, conn).await?;
+                            false
+                        }
+                    };
+                    if let Some(mut row) = rotation.next {
+                        write_row(&mut row, conn).await?;
+                    }
+                    if let Some(players) = rotation.to_back {
+                        insert_row_at_back(queue_id, &players, conn).await?;
+                    }
+                    if stayed {
+                        station.started_at = Some(Utc::now());
+                        write_station(&station, conn).await?;
+                    } else {
+                        fill_station(&mut station, conn).await?;
+                    }
+                    Ok((queue_id, entry))
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.notify(queue_id);
+        Ok(entry)
+    }
+
+    async fn get_matches(
+        &self,
+        queue_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayedMatch>, ApiError> {
+        use schema::matches::dsl;
+        let conn = &mut *self.conn.lock().await;
+        let db_matches = dsl::matches
+            .filter(dsl::queue_id.eq(Id(queue_id)))
+            .order((dsl::ended_at.desc(), dsl::id.desc()))
+            .offset(offset)
+            .limit(limit)
+            .select(models::MatchRecord::as_select())
+            .load(conn)
+            .await?;
+        load_matches(db_matches, conn).await
+    }
+
+    async fn get_games_played(
+        &self,
+        queue_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<GamesPlayed>, ApiError> {
+        use schema::matches::dsl;
+        let conn = &mut *self.conn.lock().await;
+        let db_matches = dsl::matches
+            .filter(dsl::queue_id.eq(Id(queue_id)))
+            .filter(dsl::ended_at.ge(since))
+            .select(models::MatchRecord::as_select())
+            .load(conn)
+            .await?;
+        Ok(count_games(
+            &load_matches(db_matches, conn).await?,
+        ))
+    }
+
+    async fn get_leaderboard(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<Standing>, ApiError> {
+        use schema::{players, ratings::dsl};
+        let conn = &mut *self.conn.lock().await;
+        Ok(dsl::ratings
+            .inner_join(players::table)
+            .filter(dsl::queue_id.eq(Id(queue_id)))
+            .order((dsl::rating.desc(), players::display_name.asc()))
+            .select((
+                models::Rating::as_select(),
+                models::Player::as_select(),
+            ))
+            .load::<(models::Rating, models::Player)>(conn)
+            .await?
+            .into_iter()
+            .map(|(rating, player)| {
+                Standing::from((
+                    db::Rating::from(rating),
+                    db::Player::from(player),
+                ))
+            })
+            .collect())
+    }
+
+    async fn get_rating_history(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<RatingPoint>, ApiError> {
+        use schema::{matches, rating_changes::dsl};
+        let conn = &mut *self.conn.lock().await;
+        Ok(dsl::rating_changes
+            .inner_join(matches::table)
+            .filter(matches::queue_id.eq(Id(queue_id)))
+            .order((matches::ended_at.asc(), matches::id.asc()))
+            .select((
+                dsl::player_id,
+                matches::ended_at,
+                dsl::rating_after,
+            ))
+            .load::<(Id, DateTime<Utc>, f64)>(conn)
+            .await?
+            .into_iter()
+            .map(|(player_id, ended_at, rating)| RatingPoint {
+                player_id: player_id.0,
+                ended_at,
+                rating,
+            })
+            .collect())
+    }
+
+    async fn create_user(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError> {
+        use schema::users;
+        let username = api::validate_new_user(&username, &password)?;
+        let password_hash = api::hash_password(password).await?;
+
+        let conn = &mut *self.conn.lock().await;
+        let user = models::User {
+            id: Id(Uuid::new_v4()),
+            username,
+            password_hash,
+            created_at: Utc::now(),
+        };
+        diesel::insert_into(users::table)
+            .values(&user)
+            .execute(conn)
+            .await
+            .map_err(|e| unique_violation(e, ApiError::UsernameTaken))?;
+        Ok(db::User::from(user).into())
+    }
+
+    async fn verify_login(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError> {
+        use schema::users::dsl;
+        let user = {
+            let conn = &mut *self.conn.lock().await;
+            dsl::users
+                .filter(lower(dsl::username).eq(lower(username.trim())))
+                .select(models::User::as_select())
+                .first(conn)
+                .await
+                .optional()?
+                .ok_or(ApiError::InvalidCredentials)?
+        };
+        // Don't hold the connection while Argon2 runs
+        if !api::verify_password(password, user.password_hash.clone()).await? {
+            return Err(ApiError::InvalidCredentials);
+        }
+        Ok(db::User::from(user).into())
+    }
+
+    async fn create_session(&self, user_id: Uuid) -> Result<String, ApiError> {
+        use schema::sessions::{self, dsl};
+        let conn = &mut *self.conn.lock().await;
+
+        let now = Utc::now();
+        diesel::delete(dsl::sessions.filter(dsl::expires_at.le(now)))
+            .execute(conn)
+            .await?;
+        let token = api::new_session_token();
+        diesel::insert_into(sessions::table)
+            .values(&models::NewSession {
+                token_hash: api::hash_token(&token),
+                user_id: Id(user_id),
+                created_at: now,
+                expires_at: now + api::SESSION_LENGTH,
+            })
+            .execute(conn)
+            .await?;
+        Ok(token)
+    }
+
+    async fn get_session_user(
+        &self,
+        token: String,
+    ) -> Result<Option<User>, ApiError> {
+        use schema::{sessions, users};
+        let conn = &mut *self.conn.lock().await;
+
+        let user = sessions::table
+            .inner_join(users::table)
+            .filter(sessions::token_hash.eq(api::hash_token(&token)))
+            .filter(sessions::expires_at.gt(Utc::now()))
+            .select(models::User::as_select())
+            .first(conn)
+            .await
+            .optional()?;
+        Ok(user.map(|u| db::User::from(u).into()))
+    }
+
+    async fn delete_session(&self, token: String) -> Result<(), ApiError> {
+        use schema::sessions::dsl;
+        let conn = &mut *self.conn.lock().await;
+        diesel::delete(
+            dsl::sessions.filter(dsl::token_hash.eq(api::hash_token(&token))),
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+use super::schema::{
+    match_slots, matches, players, queue_rows, queues, rating_changes, ratings,
+    row_slots, sessions, stations, users,
+};
+use crate::db;
+use chrono::{DateTime, Utc};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use diesel::{AsExpression, FromSqlRow};
+use uuid::Uuid;
+
+/// A UUID stored as hyphenated TEXT, since SQLite has no UUID type.
+#[derive(AsExpression, FromSqlRow, Debug, Clone, Copy, PartialEq, Eq)]
+#[diesel(sql_type = Text)]
+pub struct Id(pub Uuid);
+
+impl ToSql<Text, Sqlite> for Id {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut Output<'b, '_, Sqlite>,
+    ) -> serialize::Result {
+        out.set_value(self.0.to_string());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for Id {
+    fn from_sql(
+        bytes: <Sqlite as Backend>::RawValue<'_>,
+    ) -> deserialize::Result<Self> {
+        let text = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(Id(text.parse()?))
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = queues)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct Queue {
+    pub id: Id,
+    pub url_name: String,
+    pub display_name: String,
+    pub created_at: DateTime<Utc>,
+    pub admin_token_hash: Option<Vec<u8>>,
+    pub created_by: Option<Id>,
+    pub version: i32,
+    pub mode: String,
+    pub max_streak: Option<i32>,
+    pub singles: bool,
+    pub team_size: i32,
+    pub no_show_timeout: Option<i32>,
+    pub no_show_move_back: Option<i32>,
+    pub max_rows: Option<i32>,
+    pub max_entries_per_player: Option<i32>,
+    pub status: String,
+    pub unique_players: bool,
+}
+
+impl From<Queue> for db::Queue {
+    fn from(q: Queue) -> Self {
+        db::Queue {
+            id: q.id.0,
+            url_name: q.url_name,
+            display_name: q.display_name,
+            created_at: q.created_at,
+            admin_token_hash: q.admin_token_hash,
+            created_by: q.created_by.map(|id| id.0),
+            version: q.version,
+            mode: q.mode,
+            max_streak: q.max_streak,
+            singles: q.singles,
+            team_size: q.team_size,
+            no_show_timeout: q.no_show_timeout,
+            no_show_move_back: q.no_show_move_back,
+            max_rows: q.max_rows,
+            max_entries_per_player: q.max_entries_per_player,
+            status: q.status,
+            unique_players: q.unique_players,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = queue_rows)]
+#[diesel(check_for_backend(Sqlite))]
+#[diesel(treat_none_as_null = true)]
+pub struct RowRecord {
+    pub id: Id,
+    pub queue_id: Id,
+    pub queue_order: i32,
+    pub created_at: DateTime<Utc>,
+    pub version: i32,
+    pub called_at: Option<DateTime<Utc>>,
+}
+
+impl From<RowRecord> for db::RowRecord {
+    fn from(r: RowRecord) -> Self {
+        db::RowRecord {
+            id: r.id.0,
+            queue_id: r.queue_id.0,
+            queue_order: r.queue_order,
+            created_at: r.created_at,
+            version: r.version,
+            called_at: r.called_at,
+        }
+    }
+}
+
+impl From<db::RowRecord> for RowRecord {
+    fn from(r: db::RowRecord) -> Self {
+        RowRecord {
+            id: Id(r.id),
+            queue_id: Id(r.queue_id),
+            queue_order: r.queue_order,
+            created_at: r.created_at,
+            version: r.version,
+            called_at: r.called_at,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = row_slots)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct RowSlot {
+    pub row_id: Id,
+    pub side: String,
+    pub slot_index: i32,
+    pub player_id: Id,
+    pub checked_in: bool,
+}
+
+impl From<RowSlot> for db::RowSlot {
+    fn from(s: RowSlot) -> Self {
+        db::RowSlot {
+            row_id: s.row_id.0,
+            side: s.side,
+            slot_index: s.slot_index,
+            player_id: s.player_id.0,
+            checked_in: s.checked_in,
+        }
+    }
+}
+
+impl From<db::RowSlot> for RowSlot {
+    fn from(s: db::RowSlot) -> Self {
+        RowSlot {
+            row_id: Id(s.row_id),
+            side: s.side,
+            slot_index: s.slot_index,
+            player_id: Id(s.player_id),
+            checked_in: s.checked_in,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = stations)]
+#[diesel(check_for_backend(Sqlite))]
+#[diesel(treat_none_as_null = true)]
+pub struct Station {
+    pub id: Id,
+    pub queue_id: Id,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub row_id: Option<Id>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+impl From<Station> for db::Station {
+    fn from(s: Station) -> Self {
+        db::Station {
+            id: s.id.0,
+            queue_id: s.queue_id.0,
+            name: s.name,
+            created_at: s.created_at,
+            row_id: s.row_id.map(|id| id.0),
+            started_at: s.started_at,
+        }
+    }
+}
+
+impl From<db::Station> for Station {
+    fn from(s: db::Station) -> Self {
+        Station {
+            id: Id(s.id),
+            queue_id: Id(s.queue_id),
+            name: s.name,
+            created_at: s.created_at,
+            row_id: s.row_id.map(Id),
+            started_at: s.started_at,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = matches)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct MatchRecord {
+    pub id: Id,
+    pub queue_id: Id,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub winner: Option<String>,
+}
+
+impl From<MatchRecord> for db::MatchRecord {
+    fn from(m: MatchRecord) -> Self {
+        db::MatchRecord {
+            id: m.id.0,
+            queue_id: m.queue_id.0,
+            started_at: m.started_at,
+            ended_at: m.ended_at,
+            winner: m.winner,
+        }
+    }
+}
+
+impl From<db::MatchRecord> for MatchRecord {
+    fn from(m: db::MatchRecord) -> Self {
+        MatchRecord {
+            id: Id(m.id),
+            queue_id: Id(m.queue_id),
+            started_at: m.started_at,
+            ended_at: m.ended_at,
+            winner: m.winner,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = match_slots)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct MatchSlot {
+    pub match_id: Id,
+    pub side: String,
+    pub slot_index: i32,
+    pub player_id: Id,
+}
+
+impl From<MatchSlot> for db::MatchSlot {
+    fn from(s: MatchSlot) -> Self {
+        db::MatchSlot {
+            match_id: s.match_id.0,
+            side: s.side,
+            slot_index: s.slot_index,
+            player_id: s.player_id.0,
+        }
+    }
+}
+
+impl From<db::MatchSlot> for MatchSlot {
+    fn from(s: db::MatchSlot) -> Self {
+        MatchSlot {
+            match_id: Id(s.match_id),
+            side: s.side,
+            slot_index: s.slot_index,
+            player_id: Id(s.player_id),
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = ratings)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct Rating {
+    pub queue_id: Id,
+    pub player_id: Id,
+    pub rating: f64,
+    pub games: i32,
+    pub wins: i32,
+}
+
+impl From<Rating> for db::Rating {
+    fn from(r: Rating) -> Self {
+        db::Rating {
+            queue_id: r.queue_id.0,
+            player_id: r.player_id.0,
+            rating: r.rating,
+            games: r.games,
+            wins: r.wins,
+        }
+    }
+}
+
+impl From<db::Rating> for Rating {
+    fn from(r: db::Rating) -> Self {
+        Rating {
+            queue_id: Id(r.queue_id),
+            player_id: Id(r.player_id),
+            rating: r.rating,
+            games: r.games,
+            wins: r.wins,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = rating_changes)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct RatingChange {
+    pub match_id: Id,
+    pub player_id: Id,
+    pub rating_before: f64,
+    pub rating_after: f64,
+}
+
+impl From<db::RatingChange> for RatingChange {
+    fn from(c: db::RatingChange) -> Self {
+        RatingChange {
+            match_id: Id(c.match_id),
+            player_id: Id(c.player_id),
+            rating_before: c.rating_before,
+            rating_after: c.rating_after,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = players)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct Player {
+    pub id: Id,
+    pub display_name: String,
+    pub tag: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Player> for db::Player {
+    fn from(p: Player) -> Self {
+        db::Player {
+            id: p.id.0,
+            display_name: p.display_name,
+            tag: p.tag,
+            created_at: p.created_at,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct User {
+    pub id: Id,
+    pub username: String,
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<User> for db::User {
+    fn from(u: User) -> Self {
+        db::User {
+            id: u.id.0,
+            username: u.username,
+            password_hash: u.password_hash,
+            created_at: u.created_at,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+#[diesel(check_for_backend(Sqlite))]
+pub struct NewSession {
+    pub token_hash: Vec<u8>,
+    pub user_id: Id,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,159 @@
+// Mirrors `crate::db::schema` with SQLite types. Ids are TEXT, see
+// `models::Id`.
+
+diesel::table! {
+    legacy_admin_tokens (queue_id) {
+        queue_id -> Text,
+        admin_token -> Text,
+    }
+}
+
+diesel::table! {
+    matches (id) {
+        id -> Text,
+        queue_id -> Text,
+        started_at -> TimestamptzSqlite,
+        ended_at -> TimestamptzSqlite,
+        winner -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    match_slots (match_id, side, slot_index) {
+        match_id -> Text,
+        side -> Text,
+        slot_index -> Integer,
+        player_id -> Text,
+    }
+}
+
+diesel::table! {
+    players (id) {
+        id -> Text,
+        display_name -> Text,
+        tag -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    queue_rows (id) {
+        id -> Text,
+        queue_id -> Text,
+        queue_order -> Integer,
+        created_at -> TimestamptzSqlite,
+        version -> Integer,
+        called_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    queues (id) {
+        id -> Text,
+        url_name -> Text,
+        display_name -> Text,
+        created_at -> TimestamptzSqlite,
+        admin_token_hash -> Nullable<Binary>,
+        created_by -> Nullable<Text>,
+        version -> Integer,
+        mode -> Text,
+        max_streak -> Nullable<Integer>,
+        singles -> Bool,
+        team_size -> Integer,
+        no_show_timeout -> Nullable<Integer>,
+        no_show_move_back -> Nullable<Integer>,
+        max_rows -> Nullable<Integer>,
+        max_entries_per_player -> Nullable<Integer>,
+        status -> Text,
+        unique_players -> Bool,
+    }
+}
+
+diesel::table! {
+    rating_changes (match_id, player_id) {
+        match_id -> Text,
+        player_id -> Text,
+        rating_before -> Double,
+        rating_after -> Double,
+    }
+}
+
+diesel::table! {
+    ratings (queue_id, player_id) {
+        queue_id -> Text,
+        player_id -> Text,
+        rating -> Double,
+        games -> Integer,
+        wins -> Integer,
+    }
+}
+
+diesel::table! {
+    row_slots (row_id, side, slot_index) {
+        row_id -> Text,
+        side -> Text,
+        slot_index -> Integer,
+        player_id -> Text,
+        checked_in -> Bool,
+    }
+}
+
+diesel::table! {
+    sessions (token_hash) {
+        token_hash -> Binary,
+        user_id -> Text,
+        created_at -> TimestamptzSqlite,
+        expires_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    stations (id) {
+        id -> Text,
+        queue_id -> Text,
+        name -> Text,
+        created_at -> TimestamptzSqlite,
+        row_id -> Nullable<Text>,
+        started_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Text,
+        username -> Text,
+        password_hash -> Text,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::joinable!(legacy_admin_tokens -> queues (queue_id));
+diesel::joinable!(match_slots -> matches (match_id));
+diesel::joinable!(match_slots -> players (player_id));
+diesel::joinable!(matches -> queues (queue_id));
+diesel::joinable!(queue_rows -> queues (queue_id));
+diesel::joinable!(queues -> users (created_by));
+diesel::joinable!(rating_changes -> matches (match_id));
+diesel::joinable!(rating_changes -> players (player_id));
+diesel::joinable!(ratings -> players (player_id));
+diesel::joinable!(ratings -> queues (queue_id));
+diesel::joinable!(row_slots -> players (player_id));
+diesel::joinable!(row_slots -> queue_rows (row_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(stations -> queue_rows (row_id));
+diesel::joinable!(stations -> queues (queue_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    legacy_admin_tokens,
+    match_slots,
+    matches,
+    players,
+    queue_rows,
+    queues,
+    rating_changes,
+    ratings,
+    row_slots,
+    sessions,
+    stations,
+    users,
+);
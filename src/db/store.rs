@@ -0,0 +1,577 @@
+use crate::db::api::{self, ApiError};
+use crate::db::DbPool;
+use crate::history::{GamesPlayed, PlayedMatch};
+use crate::queue::{
+    CreatedQueue, Player, QueueEntry, QueueInfo, QueueMode, QueueSettings,
+    QueueState, Side, Slot, Station,
+};
+use crate::rating::{RatingPoint, Standing};
+use crate::user::User;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// The store the app runs on, provided as context to server functions and
+/// as state to the axum handlers.
+pub type Store = Arc<dyn QueueStore>;
+
+/// Everything the app stores. The functions in [`api`] implement it on
+/// Postgres, and [`MemoryStore`](crate::db::memory::MemoryStore) keeps
+/// everything in memory.
+///
+/// See the matching functions in [`api`] for what each method does. Every
+/// change bumps the version of the queue it touches, and the ones taking an
+/// `expected_version` fail with [`ApiError::Conflict`] if the queue has moved
+/// past it.
+#[async_trait]
+pub trait QueueStore: Send + Sync {
+    async fn get_all_queues(&self) -> Result<Vec<QueueInfo>, ApiError>;
+    async fn get_queue_info(
+        &self,
+        url_name: String,
+    ) -> Result<QueueInfo, ApiError>;
+    async fn get_queue_state(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<QueueState, ApiError>;
+    async fn get_user_queues(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<QueueInfo>, ApiError>;
+    async fn add_queue(
+        &self,
+        display_name: String,
+        url_name: String,
+        created_by: Option<Uuid>,
+    ) -> Result<CreatedQueue, ApiError>;
+    async fn delete_queue(&self, queue_id: Uuid) -> Result<(), ApiError>;
+    async fn set_queue_mode(
+        &self,
+        queue_id: Uuid,
+        mode: QueueMode,
+        max_streak: Option<i32>,
+        singles: bool,
+        team_size: i32,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError>;
+    async fn set_no_show_rule(
+        &self,
+        queue_id: Uuid,
+        timeout: Option<i32>,
+        move_back: Option<i32>,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError>;
+    async fn set_queue_settings(
+        &self,
+        queue_id: Uuid,
+        settings: QueueSettings,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError>;
+    async fn is_queue_admin(
+        &self,
+        queue_id: Uuid,
+        admin_token: Option<String>,
+        user_id: Option<Uuid>,
+    ) -> Result<bool, ApiError>;
+
+    async fn get_or_create_player(
+        &self,
+        display_name: String,
+        tag: Option<String>,
+    ) -> Result<Player, ApiError>;
+    async fn search_players(
+        &self,
+        query: String,
+    ) -> Result<Vec<Player>, ApiError>;
+
+    async fn get_row_queue_id(&self, row_id: Uuid) -> Result<Uuid, ApiError>;
+    async fn add_row(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<Uuid, ApiError>;
+    async fn add_player_to_row(
+        &self,
+        row_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError>;
+    async fn join_queue(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<QueueEntry, ApiError>;
+    async fn remove_player_from_row(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError>;
+    async fn delete_row(
+        &self,
+        row_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError>;
+    async fn move_row(
+        &self,
+        row_id: Uuid,
+        new_position: i32,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError>;
+    async fn move_player(
+        &self,
+        from_row: Uuid,
+        from_slot: Slot,
+        to_row: Uuid,
+        to_slot: Slot,
+        swap: bool,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError>;
+    async fn complete_front_row(
+        &self,
+        queue_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError>;
+    async fn check_in(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError>;
+    async fn check_no_shows(&self, now: DateTime<Utc>) -> Result<(), ApiError>;
+
+    async fn add_station(
+        &self,
+        queue_id: Uuid,
+        name: String,
+        expected_version: Option<i32>,
+    ) -> Result<Station, ApiError>;
+    async fn delete_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError>;
+    async fn get_station_queue_id(
+        &self,
+        station_id: Uuid,
+    ) -> Result<Uuid, ApiError>;
+    async fn assign_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError>;
+    async fn free_station(
+        &self,
+        station_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError>;
+
+    async fn get_matches(
+        &self,
+        queue_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayedMatch>, ApiError>;
+    async fn get_games_played(
+        &self,
+        queue_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<GamesPlayed>, ApiError>;
+    async fn get_leaderboard(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<Standing>, ApiError>;
+    async fn get_rating_history(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<RatingPoint>, ApiError>;
+
+    async fn create_user(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError>;
+    async fn verify_login(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError>;
+    async fn create_session(&self, user_id: Uuid) -> Result<String, ApiError>;
+    async fn get_session_user(
+        &self,
+        token: String,
+    ) -> Result<Option<User>, ApiError>;
+    async fn delete_session(&self, token: String) -> Result<(), ApiError>;
+}
+
+/// Stores everything in Postgres.
+pub struct PgStore {
+    pool: DbPool,
+}
+
+impl PgStore {
+    pub fn new(pool: DbPool) -> Self {
+        PgStore { pool }
+    }
+}
+
+#[async_trait]
+impl QueueStore for PgStore {
+    async fn get_all_queues(&self) -> Result<Vec<QueueInfo>, ApiError> {
+        api::get_all_queues(self.pool.clone()).await
+    }
+
+    async fn get_queue_info(
+        &self,
+        url_name: String,
+    ) -> Result<QueueInfo, ApiError> {
+        api::get_queue_info(url_name, self.pool.clone()).await
+    }
+
+    async fn get_queue_state(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<QueueState, ApiError> {
+        api::get_queue_state(queue_id, self.pool.clone()).await
+    }
+
+    async fn get_user_queues(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<QueueInfo>, ApiError> {
+        api::get_user_queues(user_id, self.pool.clone()).await
+    }
+
+    async fn add_queue(
+        &self,
+        display_name: String,
+        url_name: String,
+        created_by: Option<Uuid>,
+    ) -> Result<CreatedQueue, ApiError> {
+        api::add_queue(
+            display_name,
+            url_name,
+            created_by,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn delete_queue(&self, queue_id: Uuid) -> Result<(), ApiError> {
+        api::delete_queue(queue_id, self.pool.clone()).await
+    }
+
+    async fn set_queue_mode(
+        &self,
+        queue_id: Uuid,
+        mode: QueueMode,
+        max_streak: Option<i32>,
+        singles: bool,
+        team_size: i32,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        api::set_queue_mode(
+            queue_id,
+            mode,
+            max_streak,
+            singles,
+            team_size,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn set_no_show_rule(
+        &self,
+        queue_id: Uuid,
+        timeout: Option<i32>,
+        move_back: Option<i32>,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        api::set_no_show_rule(
+            queue_id,
+            timeout,
+            move_back,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn set_queue_settings(
+        &self,
+        queue_id: Uuid,
+        settings: QueueSettings,
+        expected_version: Option<i32>,
+    ) -> Result<QueueInfo, ApiError> {
+        api::set_queue_settings(
+            queue_id,
+            settings,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn is_queue_admin(
+        &self,
+        queue_id: Uuid,
+        admin_token: Option<String>,
+        user_id: Option<Uuid>,
+    ) -> Result<bool, ApiError> {
+        api::is_queue_admin(queue_id, admin_token, user_id, self.pool.clone())
+            .await
+    }
+
+    async fn get_or_create_player(
+        &self,
+        display_name: String,
+        tag: Option<String>,
+    ) -> Result<Player, ApiError> {
+        api::get_or_create_player(display_name, tag, self.pool.clone()).await
+    }
+
+    async fn search_players(
+        &self,
+        query: String,
+    ) -> Result<Vec<Player>, ApiError> {
+        api::search_players(query, self.pool.clone()).await
+    }
+
+    async fn get_row_queue_id(&self, row_id: Uuid) -> Result<Uuid, ApiError> {
+        api::get_row_queue_id(row_id, self.pool.clone()).await
+    }
+
+    async fn add_row(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<Uuid, ApiError> {
+        api::add_row(queue_id, player_id, slot, self.pool.clone()).await
+    }
+
+    async fn add_player_to_row(
+        &self,
+        row_id: Uuid,
+        player_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError> {
+        api::add_player_to_row(row_id, player_id, slot, self.pool.clone()).await
+    }
+
+    async fn join_queue(
+        &self,
+        queue_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<QueueEntry, ApiError> {
+        api::join_queue(queue_id, player_id, self.pool.clone()).await
+    }
+
+    async fn remove_player_from_row(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        api::remove_player_from_row(
+            row_id,
+            slot,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn delete_row(
+        &self,
+        row_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        api::delete_row(row_id, expected_version, self.pool.clone()).await
+    }
+
+    async fn move_row(
+        &self,
+        row_id: Uuid,
+        new_position: i32,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        api::move_row(
+            row_id,
+            new_position,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn move_player(
+        &self,
+        from_row: Uuid,
+        from_slot: Slot,
+        to_row: Uuid,
+        to_slot: Slot,
+        swap: bool,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        api::move_player(
+            from_row,
+            from_slot,
+            to_row,
+            to_slot,
+            swap,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn complete_front_row(
+        &self,
+        queue_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        api::complete_front_row(
+            queue_id,
+            winner,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn check_in(
+        &self,
+        row_id: Uuid,
+        slot: Slot,
+    ) -> Result<QueueEntry, ApiError> {
+        api::check_in(row_id, slot, self.pool.clone()).await
+    }
+
+    async fn check_no_shows(&self, now: DateTime<Utc>) -> Result<(), ApiError> {
+        api::check_no_shows(now, self.pool.clone()).await
+    }
+
+    async fn add_station(
+        &self,
+        queue_id: Uuid,
+        name: String,
+        expected_version: Option<i32>,
+    ) -> Result<Station, ApiError> {
+        api::add_station(
+            queue_id,
+            name,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn delete_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<(), ApiError> {
+        api::delete_station(station_id, expected_version, self.pool.clone())
+            .await
+    }
+
+    async fn get_station_queue_id(
+        &self,
+        station_id: Uuid,
+    ) -> Result<Uuid, ApiError> {
+        api::get_station_queue_id(station_id, self.pool.clone()).await
+    }
+
+    async fn assign_station(
+        &self,
+        station_id: Uuid,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        api::assign_station(station_id, expected_version, self.pool.clone())
+            .await
+    }
+
+    async fn free_station(
+        &self,
+        station_id: Uuid,
+        winner: Option<Side>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<QueueEntry>, ApiError> {
+        api::free_station(
+            station_id,
+            winner,
+            expected_version,
+            self.pool.clone(),
+        )
+        .await
+    }
+
+    async fn get_matches(
+        &self,
+        queue_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<PlayedMatch>, ApiError> {
+        api::get_matches(queue_id, offset, limit, self.pool.clone()).await
+    }
+
+    async fn get_games_played(
+        &self,
+        queue_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<GamesPlayed>, ApiError> {
+        api::get_games_played(queue_id, since, self.pool.clone()).await
+    }
+
+    async fn get_leaderboard(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<Standing>, ApiError> {
+        api::get_leaderboard(queue_id, self.pool.clone()).await
+    }
+
+    async fn get_rating_history(
+        &self,
+        queue_id: Uuid,
+    ) -> Result<Vec<RatingPoint>, ApiError> {
+        api::get_rating_history(queue_id, self.pool.clone()).await
+    }
+
+    async fn create_user(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError> {
+        api::create_user(username, password, self.pool.clone()).await
+    }
+
+    async fn verify_login(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<User, ApiError> {
+        api::verify_login(username, password, self.pool.clone()).await
+    }
+
+    async fn create_session(&self, user_id: Uuid) -> Result<String, ApiError> {
+        api::create_session(user_id, self.pool.clone()).await
+    }
+
+    async fn get_session_user(
+        &self,
+        token: String,
+    ) -> Result<Option<User>, ApiError> {
+        api::get_session_user(token, self.pool.clone()).await
+    }
+
+    async fn delete_session(&self, token: String) -> Result<(), ApiError> {
+        api::delete_session(token, self.pool.clone()).await
+    }
+}
@@ -0,0 +1,79 @@
+//! Estimates when waiting rows will start playing, from how long a queue's
+//! recent matches took.
+
+use crate::queue::{QueueEntry, Station};
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// How many of a queue's latest matches the median match length is taken
+/// over.
+pub const RECENT_MATCHES: i64 = 10;
+
+/// The median length of `matches`, each given as when it started and ended,
+/// or `None` if there are none. Not the mean, so one match that ran on over
+/// a break doesn't throw off every estimate.
+pub fn median_duration(
+    matches: &[(DateTime<Utc>, DateTime<Utc>)],
+) -> Option<TimeDelta> {
+    let mut durations: Vec<TimeDelta> = matches
+        .iter()
+        .map(|(started_at, ended_at)| *ended_at - *started_at)
+        .collect();
+    durations.sort();
+    let middle = durations.len() / 2;
+    match durations.len() {
+        0 => None,
+        len if len % 2 == 0 => {
+            Some((durations[middle - 1] + durations[middle]) / 2)
+        }
+        _ => Some(durations[middle]),
+    }
+}
+
+/// Fills in when each waiting entry, front first, should start playing.
+///
+/// Each station frees up `median` after its match started, or now if it's
+/// free or its match is running over. A queue without stations plays its
+/// front row, which started when the last match ended. Waiting rows then
+/// take whichever station frees up first, in order. Rows already playing
+/// get no estimate, and neither does anyone without a `median` to go on.
+pub fn estimate_starts(
+    entries: &mut [QueueEntry],
+    stations: &[Station],
+    last_ended_at: Option<DateTime<Utc>>,
+    median: Option<TimeDelta>,
+    now: DateTime<Utc>,
+) {
+    let Some(median) = median else {
+        return;
+    };
+    let mut free_at: Vec<DateTime<Utc>> = if stations.is_empty() {
+        vec![(last_ended_at.unwrap_or(now) + median).max(now)]
+    } else {
+        stations
+            .iter()
+            .map(|s| match (s.row_id, s.started_at) {
+                (Some(_), Some(started_at)) => (started_at + median).max(now),
+                _ => now,
+            })
+            .collect()
+    };
+    let playing = |index: usize, entry: &QueueEntry| {
+        if stations.is_empty() {
+            index == 0
+        } else {
+            stations
+                .iter()
+                .any(|s| s.row_id == Some(entry.id))
+        }
+    };
+    for (index, entry) in entries.iter_mut().enumerate() {
+        if playing(index, entry) {
+            continue;
+        }
+        let Some(next) = free_at.iter_mut().min() else {
+            return;
+        };
+        entry.estimated_start = Some(*next);
+        *next += median;
+    }
+}
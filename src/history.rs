@@ -0,0 +1,102 @@
+#[cfg(feature = "ssr")]
+use crate::db;
+#[cfg(feature = "ssr")]
+use crate::queue::Slot;
+use crate::queue::{Player, Side};
+use chrono::{DateTime, TimeDelta, Utc};
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Matches that ended this recently count as tonight's.
+pub const TONIGHT: TimeDelta = TimeDelta::hours(12);
+
+/// A match that has been played, kept after its row leaves the queue.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlayedMatch {
+    pub id: Uuid,
+    pub queue_id: Uuid,
+    /// The players on each side, in slot order.
+    pub left: Vec<Player>,
+    pub right: Vec<Player>,
+    /// When the row could start playing: when it reached the front of the
+    /// queue or a station, or was called to play.
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub winner: Option<Side>,
+}
+
+impl PlayedMatch {
+    /// The players on the given side of the match.
+    pub fn team(&self, side: Side) -> &[Player] {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+}
+
+/// How many matches a player has finished.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GamesPlayed {
+    pub player: Player,
+    pub games: u32,
+}
+
+/// Counts the matches each player took part in, most games first.
+pub fn count_games(matches: &[PlayedMatch]) -> Vec<GamesPlayed> {
+    let mut counts: HashMap<Uuid, GamesPlayed> = HashMap::new();
+    for player in matches
+        .iter()
+        .flat_map(|m| m.left.iter().chain(&m.right))
+    {
+        counts
+            .entry(player.id)
+            .or_insert_with(|| GamesPlayed {
+                player: player.clone(),
+                games: 0,
+            })
+            .games += 1;
+    }
+    let mut counts: Vec<GamesPlayed> = counts.into_values().collect();
+    counts.sort_by(|a, b| {
+        b.games
+            .cmp(&a.games)
+            .then_with(|| {
+                a.player
+                    .display_name
+                    .to_lowercase()
+                    .cmp(
+                        &b.player
+                            .display_name
+                            .to_lowercase(),
+                    )
+            })
+    });
+    counts
+}
+
+// Matches only hold player ids, so the players are loaded alongside them.
+#[cfg(feature = "ssr")]
+impl From<(db::Match, Vec<(Slot, db::Player)>)> for PlayedMatch {
+    fn from((db_match, players): (db::Match, Vec<(Slot, db::Player)>)) -> Self {
+        let (left, right): (Vec<_>, Vec<_>) = players
+            .into_iter()
+            .partition(|(slot, _)| slot.side == Side::Left);
+        let team = |players: Vec<(Slot, db::Player)>| {
+            players
+                .into_iter()
+                .map(|(_, player)| Player::from(player))
+                .collect()
+        };
+        PlayedMatch {
+            winner: db_match.winner,
+            id: db_match.id,
+            queue_id: db_match.queue_id,
+            left: team(left),
+            right: team(right),
+            started_at: db_match.started_at,
+            ended_at: db_match.ended_at,
+        }
+    }
+}
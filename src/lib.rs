@@ -1,8 +1,24 @@
+#[cfg(feature = "ssr")]
+pub mod admin;
 pub mod app;
 #[cfg(feature = "ssr")]
+pub mod auth;
+#[cfg(feature = "ssr")]
+pub mod cookies;
+#[cfg(feature = "ssr")]
 pub mod db;
+pub mod estimate;
+pub mod history;
+#[cfg(feature = "ssr")]
+pub mod live;
+#[cfg(feature = "ssr")]
+pub mod no_show;
 pub mod pages;
 pub mod queue;
+pub mod rating;
+#[cfg(feature = "ssr")]
+pub mod rest;
+pub mod user;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
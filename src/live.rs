@@ -0,0 +1,63 @@
+use crate::db::api::ApiError;
+use crate::db::{notify::QueueChanges, Store};
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{stream, Stream, StreamExt};
+use leptos::logging::error;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Streams a queue's [`QueueState`](crate::queue::QueueState) as `state`
+/// events, once on connect and again whenever its rows change.
+pub async fn queue_events(
+    Path(url_name): Path<String>,
+    store: Store,
+    changes: QueueChanges,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let queue_id = match store
+        .get_queue_info(url_name)
+        .await
+    {
+        Ok(queue) => queue.id,
+        Err(ApiError::NotFound) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Error subscribing to queue: {e}");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Subscribe before the first load so no change can slip in between.
+    let changed =
+        stream::unfold(changes.subscribe(), move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(id) if id == queue_id => return Some(((), rx)),
+                    Ok(_) => continue,
+                    // Some changes were missed, so reload to be safe.
+                    Err(RecvError::Lagged(_)) => return Some(((), rx)),
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+    let events = stream::once(async {})
+        .chain(changed)
+        .filter_map(move |()| {
+            let store = store.clone();
+            async move {
+                let state = store
+                    .get_queue_state(queue_id)
+                    .await
+                    .inspect_err(|e| error!("Error loading queue state: {e}"))
+                    .ok()?;
+                Event::default()
+                    .event("state")
+                    .json_data(state)
+                    .inspect_err(|e| error!("Error serializing state: {e}"))
+                    .ok()
+                    .map(Ok)
+            }
+        });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -1,10 +1,10 @@
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
-    use axum::Router;
+    use axum::{routing::get, Router};
     use dotenvy;
     use itq::app::*;
-    use itq::db;
+    use itq::{auth, db, live, no_show, rest};
     use leptos::logging::log;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
@@ -15,21 +15,31 @@ async fn main() {
     let leptos_options = conf.leptos_options;
     dotenvy::dotenv().expect(".env should be loadable");
 
-    // Create database pool
+    // Connect to the database named by DATABASE_URL
     let database_url =
         std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = db::create_pool(&database_url).await;
+    let (store, queue_changes) = db::open_store(database_url).await;
+    no_show::spawn_no_show_checks(store.clone());
 
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(App);
 
     let app = Router::new()
+        .nest("/api/v1", rest::router(store.clone()))
+        .merge(rest::docs_router())
+        .route(
+            "/queue/{url_name}/events",
+            get({
+                let store = store.clone();
+                move |path| live::queue_events(path, store, queue_changes)
+            }),
+        )
         .leptos_routes_with_context(
             &leptos_options,
             routes,
             {
-                let pool = pool.clone();
-                move || provide_context(pool.clone())
+                let store = store.clone();
+                move || provide_context(store.clone())
             },
             {
                 let leptos_options = leptos_options.clone();
@@ -37,6 +47,10 @@ async fn main() {
             },
         )
         .fallback(leptos_axum::file_and_error_handler(shell))
+        .layer(axum::middleware::from_fn_with_state(
+            store.clone(),
+            auth::session_layer,
+        ))
         .with_state(leptos_options);
 
     // run our app with hyper
@@ -0,0 +1,26 @@
+use crate::db::Store;
+use leptos::logging::error;
+use std::time::Duration;
+
+/// How often queues are checked for front rows to call and no-shows.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Starts checking every queue with a no-show timeout in the background,
+/// calling their front rows and moving back or dropping the ones that don't
+/// check in in time.
+pub fn spawn_no_show_checks(store: Store) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        interval
+            .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = store
+                .check_no_shows(chrono::Utc::now())
+                .await
+            {
+                error!("Error checking for no-shows: {e}");
+            }
+        }
+    });
+}
@@ -0,0 +1,177 @@
+use crate::user::User;
+use leptos::prelude::*;
+use leptos_router::hooks::use_navigate;
+
+/// Login state shared by the whole app.
+#[derive(Copy, Clone)]
+pub struct Session {
+    pub login: ServerAction<Login>,
+    pub register: ServerAction<Register>,
+    pub logout: ServerAction<Logout>,
+    /// The logged in user, reloaded whenever someone logs in or out.
+    pub user: Resource<Option<User>>,
+}
+
+impl Session {
+    /// Changes whenever someone logs in, registers or logs out, for
+    /// resources that depend on who is logged in.
+    pub fn changes(&self) -> (usize, usize, usize) {
+        (
+            self.login.version().get(),
+            self.register.version().get(),
+            self.logout.version().get(),
+        )
+    }
+}
+
+/// Provides the [`Session`] context for the rest of the app.
+pub fn provide_session() {
+    let login = ServerAction::<Login>::new();
+    let register = ServerAction::<Register>::new();
+    let logout = ServerAction::<Logout>::new();
+    let user = Resource::new(
+        move || {
+            (
+                login.version().get(),
+                register.version().get(),
+                logout.version().get(),
+            )
+        },
+        |_| async {
+            get_current_user()
+                .await
+                .ok()
+                .flatten()
+        },
+    );
+    provide_context(Session {
+        login,
+        register,
+        logout,
+        user,
+    });
+}
+
+#[component]
+pub fn AccountNav() -> impl IntoView {
+    let session = expect_context::<Session>();
+
+    view! {
+        <nav class="account-nav">
+            <a href="/">"itq"</a>
+            <Transition fallback=|| ()>
+                {move || {
+                    session
+                        .user
+                        .get()
+                        .map(|user| match user {
+                            Some(user) => {
+                                view! {
+                                    <span>"Logged in as "{user.username}</span>
+                                    <ActionForm action=session.logout>
+                                        <button type="submit">"Log out"</button>
+                                    </ActionForm>
+                                }
+                                    .into_any()
+                            }
+                            None => view! { <a href="/login">"Log in"</a> }.into_any(),
+                        })
+                }}
+            </Transition>
+        </nav>
+    }
+}
+
+#[component]
+pub fn LoginPage() -> impl IntoView {
+    let session = expect_context::<Session>();
+    let navigate = use_navigate();
+
+    // There's nothing to do here once logged in.
+    Effect::new(move |_| {
+        if let Some(Some(_)) = session.user.get() {
+            navigate("/", Default::default());
+        }
+    });
+
+    view! {
+        <h1>"Log In"</h1>
+        <ActionForm action=session.login>
+            <label>
+                "Username" <input type="text" name="username" autocomplete="username" />
+            </label>
+            <label>
+                "Password"
+                <input type="password" name="password" autocomplete="current-password" />
+            </label>
+            <input type="submit" value="Log In" />
+        </ActionForm>
+        {failure(session.login.value())}
+        <h2>"Create an Account"</h2>
+        <ActionForm action=session.register>
+            <label>
+                "Username" <input type="text" name="username" autocomplete="username" />
+            </label>
+            <label>
+                "Password"
+                <input type="password" name="password" autocomplete="new-password" />
+            </label>
+            <input type="submit" value="Create Account" />
+        </ActionForm>
+        {failure(session.register.value())}
+    }
+}
+
+/// Shows why the last attempt to log in or register failed.
+fn failure(
+    value: MappedSignal<Option<Result<User, ServerFnError>>>,
+) -> impl IntoView {
+    move || match value.get() {
+        Some(Err(e)) => Some(view! { <p class="error">{e.to_string()}</p> }),
+        _ => None,
+    }
+}
+
+#[server]
+pub async fn get_current_user() -> Result<Option<User>, ServerFnError> {
+    use crate::auth::current_user;
+    current_user().await
+}
+
+#[server]
+pub async fn login(
+    username: String,
+    password: String,
+) -> Result<User, ServerFnError> {
+    use crate::auth::start_session;
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let user = store
+        .verify_login(username, password)
+        .await?;
+    start_session(&user).await?;
+    Ok(user)
+}
+
+#[server]
+pub async fn register(
+    username: String,
+    password: String,
+) -> Result<User, ServerFnError> {
+    use crate::auth::start_session;
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let user = store
+        .create_user(username, password)
+        .await?;
+    start_session(&user).await?;
+    Ok(user)
+}
+
+#[server]
+pub async fn logout() -> Result<(), ServerFnError> {
+    use crate::auth::end_session;
+    end_session().await
+}
@@ -1,4 +1,4 @@
-use crate::queue::QueueInfo;
+use crate::queue::CreatedQueue;
 use leptos::prelude::*;
 
 #[component]
@@ -25,14 +25,25 @@ pub fn AddQueuePage() -> impl IntoView {
                     .get()
                     .map(|result| {
                         match result {
-                            Ok(queue_info) => {
+                            Ok(CreatedQueue { info, admin_token }) => {
+                                // Visiting this link on another device grants
+                                // admin access there too.
+                                let admin_link = format!(
+                                    "/queue/{}?admin={}",
+                                    info.url_name,
+                                    admin_token,
+                                );
                                 view! {
                                     <p>
                                         "Queue added: "
                                         <a href=format!(
                                             "/queue/{}",
-                                            queue_info.url_name,
-                                        )>{queue_info.display_name}</a>
+                                            info.url_name,
+                                        )>{info.display_name}</a>
+                                    </p>
+                                    <p>
+                                        "Keep this admin link private. Anyone who has it can manage the queue: "
+                                        <a href=admin_link.clone()>{admin_link.clone()}</a>
                                     </p>
                                 }
                                     .into_any()
@@ -52,8 +63,19 @@ pub fn AddQueuePage() -> impl IntoView {
 pub async fn add_queue(
     display_name: String,
     url_name: String,
-) -> Result<QueueInfo, ServerFnError> {
-    use crate::db::{api::add_queue, DbPool};
-    let pool = use_context::<DbPool>().expect("there to be a `pool` provided.");
-    Ok(add_queue(display_name, url_name, pool).await?)
+) -> Result<CreatedQueue, ServerFnError> {
+    use crate::admin::set_admin_cookie;
+    use crate::auth::current_user;
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let created_by = current_user()
+        .await?
+        .map(|user| user.id);
+    let created = store
+        .add_queue(display_name, url_name, created_by)
+        .await?;
+    // The creator can manage the queue from this browser right away.
+    set_admin_cookie(created.info.id, &created.admin_token);
+    Ok(created)
 }
@@ -0,0 +1,212 @@
+use crate::history::{GamesPlayed, PlayedMatch};
+use crate::queue::{QueueInfo, Side};
+use leptos::prelude::*;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use leptos_router::hooks::{use_params_map, use_query_map};
+
+/// One page of a queue's finished matches, newest first, along with how many
+/// games everyone has played tonight.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchHistory {
+    queue: QueueInfo,
+    matches: Vec<PlayedMatch>,
+    /// Whether there are older matches on the next page.
+    has_more: bool,
+    games_tonight: Vec<GamesPlayed>,
+}
+
+#[component]
+pub fn HistoryPage() -> impl IntoView {
+    let params = use_params_map();
+    let query = use_query_map();
+    let url_queue_name = move || {
+        params
+            .read()
+            .get("url_name")
+            .expect("there to be a `url_name` guaranteed by the router")
+    };
+    let page = move || {
+        query
+            .read()
+            .get("page")
+            .and_then(|page| page.parse::<u32>().ok())
+            .unwrap_or(1)
+            .max(1)
+    };
+    let history = Resource::new(
+        move || (url_queue_name(), page()),
+        |(url_name, page)| get_match_history(url_name, page),
+    );
+
+    view! {
+        <div class="history-page">
+            <Suspense fallback=move || {
+                view! { <p>"Loading history..."</p> }
+            }>
+                {move || {
+                    history
+                        .get()
+                        .map(|history| match history {
+                            Ok(history) => {
+                                view! { <History history page=page() /> }
+                                    .into_any()
+                            }
+                            Err(e) => {
+                                view! {
+                                    <p>"Error loading history: "{e.to_string()}</p>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn History(history: MatchHistory, page: u32) -> impl IntoView {
+    let queue_href = format!("/queue/{}", history.queue.url_name);
+    let page_href = |page: u32| format!("{queue_href}/history?page={page}");
+    let (newer_href, older_href) = (page_href(page - 1), page_href(page + 1));
+    view! {
+        <h1>{history.queue.display_name}" history"</h1>
+        <a href=queue_href.clone()>"Back to the queue"</a>
+        <h2>"Games tonight"</h2>
+        <Show
+            when={
+                let empty = history.games_tonight.is_empty();
+                move || !empty
+            }
+            fallback=|| view! { <p>"Nobody has played tonight yet."</p> }
+        >
+            <table>
+                <thead>
+                    <tr>
+                        <th>"Player"</th>
+                        <th>"Games"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {history
+                        .games_tonight
+                        .clone()
+                        .into_iter()
+                        .map(|played| {
+                            view! {
+                                <tr>
+                                    <td>{played.player.display_name}</td>
+                                    <td>{played.games}</td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()}
+                </tbody>
+            </table>
+        </Show>
+        <h2>"Matches"</h2>
+        <Show
+            when={
+                let empty = history.matches.is_empty();
+                move || !empty
+            }
+            fallback=|| view! { <p>"No matches played yet."</p> }
+        >
+            <table>
+                <thead>
+                    <tr>
+                        <th>"Finished"</th>
+                        <th>"Length"</th>
+                        <th>"Left"</th>
+                        <th>"Right"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {history
+                        .matches
+                        .clone()
+                        .into_iter()
+                        .map(|played| view! { <MatchRow played /> })
+                        .collect_view()}
+                </tbody>
+            </table>
+        </Show>
+        <nav class="pages">
+            <Show when=move || { page > 1 }>
+                <a href=newer_href.clone()>"Newer"</a>
+            </Show>
+            <Show when=move || history.has_more>
+                <a href=older_href.clone()>"Older"</a>
+            </Show>
+        </nav>
+    }
+}
+
+#[component]
+fn MatchRow(played: PlayedMatch) -> impl IntoView {
+    let minutes = (played.ended_at - played.started_at).num_minutes();
+    let player_cell = |side: Side| {
+        let name = played
+            .team(side)
+            .iter()
+            .map(|p| p.display_name.as_str())
+            .collect::<Vec<_>>()
+            .join(" & ");
+        let won = played.winner == Some(side);
+        view! {
+            <td class:winner=won>
+                {name}
+                {won.then_some(" (won)")}
+            </td>
+        }
+    };
+    view! {
+        <tr>
+            <td>
+                <time datetime=played.ended_at.to_rfc3339()>
+                    {played.ended_at.format("%b %-d, %H:%M UTC").to_string()}
+                </time>
+            </td>
+            <td>{minutes}" min"</td>
+            {player_cell(Side::Left)}
+            {player_cell(Side::Right)}
+        </tr>
+    }
+}
+
+#[server]
+/// Gets a page of a queue's finished matches, counting from 1
+async fn get_match_history(
+    url_name: String,
+    page: u32,
+) -> Result<MatchHistory, ServerFnError> {
+    use crate::db::Store;
+    use crate::history::TONIGHT;
+    /// How many matches are listed per page.
+    const MATCHES_PER_PAGE: u32 = 20;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    let per_page = i64::from(MATCHES_PER_PAGE);
+    // Ask for one more than fits to find out if there's another page.
+    let mut matches = store
+        .get_matches(
+            queue.id,
+            i64::from(page.max(1) - 1) * per_page,
+            per_page + 1,
+        )
+        .await?;
+    let has_more = matches.len() > MATCHES_PER_PAGE as usize;
+    matches.truncate(MATCHES_PER_PAGE as usize);
+    let games_tonight = store
+        .get_games_played(queue.id, chrono::Utc::now() - TONIGHT)
+        .await?;
+    Ok(MatchHistory {
+        queue,
+        matches,
+        has_more,
+        games_tonight,
+    })
+}
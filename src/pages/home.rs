@@ -1,13 +1,53 @@
 use leptos::prelude::*;
 
+use crate::pages::account::Session;
 use crate::queue::QueueInfo;
 
 #[component]
 pub fn HomePage() -> impl IntoView {
     let queue_infos = OnceResource::new(load_queues());
+    let session = expect_context::<Session>();
+    let my_queues = Resource::new(
+        move || session.changes(),
+        |_| async {
+            load_my_queues()
+                .await
+                .ok()
+                .flatten()
+        },
+    );
 
     view! {
         <h1>Welcome to itq!</h1>
+        <Transition fallback=|| ()>
+            {move || {
+                my_queues
+                    .get()
+                    .flatten()
+                    .map(|queues| {
+                        view! {
+                            <h2>"My queues"</h2>
+                            <Show
+                                when={
+                                    let empty = queues.is_empty();
+                                    move || !empty
+                                }
+                                fallback=|| view! { <p>"You haven't created any queues yet."</p> }
+                            >
+                                <For
+                                    each={
+                                        let queues = queues.clone();
+                                        move || queues.clone()
+                                    }
+                                    key=|q| q.id
+                                    children=move |q| view! { <QueueDisplay queue_info=q /> }
+                                />
+                            </Show>
+                        }
+                    })
+            }}
+        </Transition>
+        <h2>"All queues"</h2>
         <Suspense fallback=move || {
             view! { <p>Loading queues...</p> }
         }>
@@ -49,9 +89,27 @@ pub fn QueueDisplay(queue_info: QueueInfo) -> impl IntoView {
     }
 }
 
+#[server]
+/// Loads the logged in user's queues, or `None` if nobody is logged in
+pub async fn load_my_queues() -> Result<Option<Vec<QueueInfo>>, ServerFnError> {
+    use crate::auth::current_user;
+    use crate::db::Store;
+    let Some(user) = current_user().await? else {
+        return Ok(None);
+    };
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(Some(
+        store
+            .get_user_queues(user.id)
+            .await?,
+    ))
+}
+
 #[server]
 pub async fn load_queues() -> Result<Vec<QueueInfo>, ServerFnError> {
-    use crate::db::{api::get_all_queues, DbPool};
-    let pool = use_context::<DbPool>().expect("there to be a `pool` provided.");
-    Ok(get_all_queues(pool).await?)
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store.get_all_queues().await?)
 }
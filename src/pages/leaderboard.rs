@@ -0,0 +1,172 @@
+use crate::queue::QueueInfo;
+use crate::rating::{RatingPoint, Standing, INITIAL_RATING};
+use leptos::prelude::*;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use leptos_router::hooks::use_params_map;
+use uuid::Uuid;
+
+/// Everyone rated in a queue, highest rating first, along with every rating
+/// change so their progress can be charted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Leaderboard {
+    queue: QueueInfo,
+    standings: Vec<Standing>,
+    history: Vec<RatingPoint>,
+}
+
+#[component]
+pub fn LeaderboardPage() -> impl IntoView {
+    let params = use_params_map();
+    let url_queue_name = move || {
+        params
+            .read()
+            .get("url_name")
+            .expect("there to be a `url_name` guaranteed by the router")
+    };
+    let leaderboard = Resource::new(url_queue_name, get_leaderboard);
+
+    view! {
+        <div class="leaderboard-page">
+            <Suspense fallback=move || {
+                view! { <p>"Loading leaderboard..."</p> }
+            }>
+                {move || {
+                    leaderboard
+                        .get()
+                        .map(|leaderboard| match leaderboard {
+                            Ok(leaderboard) => {
+                                view! { <Standings leaderboard /> }.into_any()
+                            }
+                            Err(e) => {
+                                view! {
+                                    <p>"Error loading leaderboard: "{e.to_string()}</p>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn Standings(leaderboard: Leaderboard) -> impl IntoView {
+    let queue_href = format!("/queue/{}", leaderboard.queue.url_name);
+    let history = leaderboard.history;
+    view! {
+        <h1>{leaderboard.queue.display_name}" leaderboard"</h1>
+        <a href=queue_href>"Back to the queue"</a>
+        <Show
+            when={
+                let empty = leaderboard.standings.is_empty();
+                move || !empty
+            }
+            fallback=|| {
+                view! { <p>"Nobody is rated yet. Ratings start once a match has a winner."</p> }
+            }
+        >
+            <table>
+                <thead>
+                    <tr>
+                        <th>"#"</th>
+                        <th>"Player"</th>
+                        <th>"Rating"</th>
+                        <th>"Wins"</th>
+                        <th>"Games"</th>
+                        <th>"Progress"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {leaderboard
+                        .standings
+                        .clone()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(rank, standing)| {
+                            let points = progress_points(&history, standing.player.id);
+                            view! {
+                                <tr>
+                                    <td>{rank + 1}</td>
+                                    <td>{standing.player.display_name}</td>
+                                    <td>{format!("{:.0}", standing.rating)}</td>
+                                    <td>{standing.wins}</td>
+                                    <td>{standing.games}</td>
+                                    <td>
+                                        <svg
+                                            class="sparkline"
+                                            viewBox="0 0 100 24"
+                                            preserveAspectRatio="none"
+                                        >
+                                            <polyline points=points />
+                                        </svg>
+                                    </td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()}
+                </tbody>
+            </table>
+        </Show>
+    }
+}
+
+/// Plots a player's rating over their matches as SVG polyline points in a
+/// 100 by 24 box, starting from the rating everyone starts with.
+fn progress_points(history: &[RatingPoint], player_id: Uuid) -> String {
+    let ratings: Vec<f64> = std::iter::once(INITIAL_RATING)
+        .chain(
+            history
+                .iter()
+                .filter(|point| point.player_id == player_id)
+                .map(|point| point.rating),
+        )
+        .collect();
+    let low = ratings
+        .iter()
+        .copied()
+        .fold(f64::INFINITY, f64::min);
+    let high = ratings
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let step = 100.0 / (ratings.len() - 1).max(1) as f64;
+    ratings
+        .iter()
+        .enumerate()
+        .map(|(i, rating)| {
+            // A flat line sits in the middle.
+            let y = if high > low {
+                22.0 - (rating - low) / (high - low) * 20.0
+            } else {
+                12.0
+            };
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[server]
+/// Gets the leaderboard of the queue with the given url_name
+async fn get_leaderboard(
+    url_name: String,
+) -> Result<Leaderboard, ServerFnError> {
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    let standings = store
+        .get_leaderboard(queue.id)
+        .await?;
+    let history = store
+        .get_rating_history(queue.id)
+        .await?;
+    Ok(Leaderboard {
+        queue,
+        standings,
+        history,
+    })
+}
@@ -1,7 +1,13 @@
+pub mod account;
 pub mod add;
+pub mod history;
 pub mod home;
+pub mod leaderboard;
 pub mod queue;
 
+pub use account::{provide_session, AccountNav, LoginPage};
 pub use add::AddQueuePage;
+pub use history::HistoryPage;
 pub use home::HomePage;
+pub use leaderboard::LeaderboardPage;
 pub use queue::QueuePage;
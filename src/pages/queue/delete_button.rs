@@ -1,5 +1,7 @@
 #[cfg(feature = "ssr")]
-use crate::db::{api, DbPool};
+use crate::admin::require_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
 use crate::queue::QueueInfo;
 use leptos::prelude::*;
 use leptos_router::hooks::use_navigate;
@@ -47,6 +49,8 @@ pub fn DeleteButton() -> impl IntoView {
 
 #[server]
 pub async fn delete_queue(id: Uuid) -> Result<(), ServerFnError> {
-    let pool = use_context::<DbPool>().expect("there to be a `pool` provided.");
-    Ok(api::delete_queue(id, pool).await?)
+    require_admin(id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store.delete_queue(id).await?)
 }
@@ -9,6 +9,12 @@ pub fn QueueHeader() -> impl IntoView {
         <div class="queue-header">
             <h1>{queue_info.display_name}</h1>
             <p>"id: "{queue_info.id.to_string()}</p>
+            <a href=format!("/queue/{}/history", queue_info.url_name)>
+                "Match history"
+            </a>
+            <a href=format!("/queue/{}/leaderboard", queue_info.url_name)>
+                "Leaderboard"
+            </a>
         // TODO: add # of players/rows to queue info
         // <p>"players: "{ move || queue_data }</p>
         </div>
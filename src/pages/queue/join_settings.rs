@@ -0,0 +1,128 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::parse_limit;
+use crate::queue::{QueueInfo, QueueSettings, QueueStatus};
+use leptos::prelude::*;
+use uuid::Uuid;
+
+#[component]
+pub fn JoinSettings() -> impl IntoView {
+    let queue_info = use_context::<QueueInfo>()
+        .expect("there to be a `queue_info` provided.");
+    let settings = expect_context::<RwSignal<QueueSettings>>();
+    let set_queue_settings = ServerAction::<SetQueueSettings>::new();
+    let value = set_queue_settings.value();
+
+    Effect::new(move |_| {
+        if let Some(Ok(info)) = value.get() {
+            settings.set(info.settings);
+        }
+    });
+
+    let option = move |value: QueueStatus, label: &'static str| {
+        view! {
+            <option
+                value=value.to_string()
+                selected=queue_info.settings.status == value
+            >
+                {label}
+            </option>
+        }
+    };
+
+    view! {
+        <ActionForm action=set_queue_settings>
+            <input
+                type="hidden"
+                name="queue_id"
+                value=queue_info.id.to_string()
+            />
+            <label>
+                "The queue is "
+                <select name="status">
+                    {option(QueueStatus::Open, "open to everyone")}
+                    {option(QueueStatus::Paused, "paused for now")}
+                    {option(QueueStatus::Closed, "closed")}
+                </select>
+            </label>
+            <label>
+                ", holds at most "
+                <input
+                    type="number"
+                    name="max_rows"
+                    min="1"
+                    placeholder="any number of"
+                    value=queue_info.settings.max_rows.map(|max| max.to_string())
+                />
+                " rows"
+            </label>
+            <label>
+                ", and each player can be in "
+                <input
+                    type="number"
+                    name="max_entries_per_player"
+                    min="1"
+                    placeholder="any number of"
+                    value=queue_info
+                        .settings
+                        .max_entries_per_player
+                        .map(|max| max.to_string())
+                />
+                " of them at once"
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    name="unique_players"
+                    value="true"
+                    checked=queue_info.settings.unique_players
+                />
+                " Players already waiting can't join again"
+            </label>
+            <button type="submit">"Save"</button>
+        </ActionForm>
+        {move || {
+            value
+                .get()
+                .map(|result| match result {
+                    Ok(_) => view! { <p>"Saved"</p> }.into_any(),
+                    Err(e) => {
+                        view! { <p>"Error saving settings: " {e.to_string()}</p> }
+                            .into_any()
+                    }
+                })
+        }}
+    }
+}
+
+#[server]
+/// Changes who may join the queue and how much of it they may take up. A
+/// blank limit means no limit, and `unique_players` is only sent when its box
+/// is ticked.
+pub async fn set_queue_settings(
+    queue_id: Uuid,
+    status: QueueStatus,
+    max_rows: String,
+    max_entries_per_player: String,
+    unique_players: Option<String>,
+) -> Result<QueueInfo, ServerFnError> {
+    require_admin(queue_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let settings = QueueSettings {
+        max_rows: parse_limit("The row limit", &max_rows)?,
+        max_entries_per_player: parse_limit(
+            "The limit per player",
+            &max_entries_per_player,
+        )?,
+        status,
+        unique_players: unique_players.is_some(),
+    };
+    // Settings don't track the queue's version, so the last save wins.
+    Ok(store
+        .set_queue_settings(queue_id, settings, None)
+        .await?)
+}
@@ -1,15 +1,40 @@
 mod delete_button;
 mod header;
+mod join_settings;
+mod mode_settings;
+mod no_show_settings;
 mod rows;
 
 use crate::queue::QueueInfo;
 use delete_button::DeleteButton;
 use header::QueueHeader;
+use join_settings::JoinSettings;
 use leptos::context::provide_context;
 use leptos::prelude::*;
-use leptos_router::hooks::use_params_map;
+use leptos_router::hooks::{use_navigate, use_params_map, use_query_map};
+use leptos_router::NavigateOptions;
+use mode_settings::ModeSettings;
+use no_show_settings::NoShowSettings;
 use rows::Rows;
 
+/// Whether the current visitor may manage the queue. Admin controls are only
+/// shown to admins.
+#[derive(Copy, Clone)]
+pub struct IsAdmin(pub Signal<bool>);
+
+/// Whether players join the queue alone and get paired up, in which case
+/// rows don't offer a side to join.
+#[derive(Copy, Clone)]
+pub struct Singles(pub RwSignal<bool>);
+
+/// How many slots each side of a row has.
+#[derive(Copy, Clone)]
+pub struct TeamSize(pub RwSignal<i32>);
+
+/// Seconds a called row has to check in, if rows are called at all.
+#[derive(Copy, Clone)]
+pub struct NoShowTimeout(pub RwSignal<Option<i32>>);
+
 #[component]
 pub fn QueuePage() -> impl IntoView {
     let params = use_params_map();
@@ -23,6 +48,41 @@ pub fn QueuePage() -> impl IntoView {
         get_queue(name).await.ok()
     });
 
+    let claim_admin = ServerAction::<ClaimAdmin>::new();
+    let claimed = claim_admin.value();
+    let is_admin_rsc = Resource::new(url_queue_name, |name| async {
+        check_admin(name)
+            .await
+            .unwrap_or(false)
+    });
+    let is_admin = Signal::derive(move || {
+        matches!(claimed.get(), Some(Ok(true)))
+            || is_admin_rsc.get().unwrap_or(false)
+    });
+    provide_context(IsAdmin(is_admin));
+
+    // Admin links carry the token in the query string. Trade it for a cookie
+    // and drop it from the address bar.
+    let query = use_query_map();
+    let navigate = use_navigate();
+    Effect::new(move |_| {
+        let Some(admin_token) = query.read().get("admin") else {
+            return;
+        };
+        let url_name = url_queue_name();
+        claim_admin.dispatch(ClaimAdmin {
+            url_name: url_name.clone(),
+            admin_token,
+        });
+        navigate(
+            &format!("/queue/{url_name}"),
+            NavigateOptions {
+                replace: true,
+                ..Default::default()
+            },
+        );
+    });
+
     view! {
         <div class="queue-page">
             <p>"Now Viewing: "{url_queue_name}</p>
@@ -36,12 +96,24 @@ pub fn QueuePage() -> impl IntoView {
                         .map_or(
                             view! { <h1>"Error: No Queue Found"</h1> }.into_any(),
                             move |queue_info| {
+                                provide_context(RwSignal::new(queue_info.mode));
+                                provide_context(Singles(RwSignal::new(queue_info.singles)));
+                                provide_context(TeamSize(RwSignal::new(queue_info.team_size)));
+                                provide_context(
+                                    NoShowTimeout(RwSignal::new(queue_info.no_show_timeout)),
+                                );
+                                provide_context(RwSignal::new(queue_info.settings));
                                 provide_context(queue_info);
                                 // Provide context for deeply nested components
                                 view! {
                                     <QueueHeader />
                                     <Rows />
-                                    <DeleteButton />
+                                    <Show when=move || is_admin.get()>
+                                        <ModeSettings />
+                                        <NoShowSettings />
+                                        <JoinSettings />
+                                        <DeleteButton />
+                                    </Show>
                                 }
                                     .into_any()
                             },
@@ -55,7 +127,63 @@ pub fn QueuePage() -> impl IntoView {
 #[server]
 /// Gets a queue from the database from the queue's unique url_name
 async fn get_queue(url_name: String) -> Result<QueueInfo, ServerFnError> {
-    use crate::db::{api::get_queue_info, DbPool};
-    let pool = use_context::<DbPool>().expect("there to be a `pool` provided.");
-    Ok(get_queue_info(url_name, pool).await?)
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store
+        .get_queue_info(url_name)
+        .await?)
+}
+
+#[server]
+/// Checks whether the visitor may manage the queue with the given url_name
+async fn check_admin(url_name: String) -> Result<bool, ServerFnError> {
+    use crate::admin::is_admin;
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    is_admin(queue.id).await
+}
+
+#[server]
+/// Grants admin access to this browser if the token from an admin link is
+/// valid
+async fn claim_admin(
+    url_name: String,
+    admin_token: String,
+) -> Result<bool, ServerFnError> {
+    use crate::admin::set_admin_cookie;
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    let is_admin = store
+        .is_queue_admin(queue.id, Some(admin_token.clone()), None)
+        .await?;
+    if is_admin {
+        set_admin_cookie(queue.id, &admin_token);
+    }
+    Ok(is_admin)
+}
+
+/// Reads an optional limit from a settings form. Blank means no limit, and
+/// anything else has to be a positive whole number, so a typo can't quietly
+/// lift a limit.
+#[cfg(feature = "ssr")]
+fn parse_limit(name: &str, value: &str) -> Result<Option<i32>, ServerFnError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<i32>() {
+        Ok(limit) if limit > 0 => Ok(Some(limit)),
+        _ => Err(ServerFnError::new(format!(
+            "{name} must be a whole number above 0, or blank, not \"{value}\""
+        ))),
+    }
 }
@@ -0,0 +1,137 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::parse_limit;
+use crate::pages::queue::{Singles, TeamSize};
+use crate::queue::{QueueInfo, QueueMode, MAX_TEAM_SIZE};
+use leptos::prelude::*;
+use uuid::Uuid;
+
+#[component]
+pub fn ModeSettings() -> impl IntoView {
+    let queue_info = use_context::<QueueInfo>()
+        .expect("there to be a `queue_info` provided.");
+    let mode = expect_context::<RwSignal<QueueMode>>();
+    let Singles(singles) = expect_context::<Singles>();
+    let TeamSize(team_size) = expect_context::<TeamSize>();
+    let set_queue_mode = ServerAction::<SetQueueMode>::new();
+    let value = set_queue_mode.value();
+
+    Effect::new(move |_| {
+        if let Some(Ok(info)) = value.get() {
+            mode.set(info.mode);
+            singles.set(info.singles);
+            team_size.set(info.team_size);
+        }
+    });
+
+    let option = move |value: QueueMode, label: &'static str| {
+        view! {
+            <option value=value.to_string() selected=queue_info.mode == value>
+                {label}
+            </option>
+        }
+    };
+
+    view! {
+        <ActionForm action=set_queue_mode>
+            <input
+                type="hidden"
+                name="queue_id"
+                value=queue_info.id.to_string()
+            />
+            <label>
+                "When a match ends "
+                <select name="mode">
+                    {option(QueueMode::Pairs, "both players leave")}
+                    {option(QueueMode::WinnerStays, "the winner stays on")}
+                    {option(QueueMode::LoserStays, "the loser stays on")}
+                </select>
+            </label>
+            <label>
+                " for at most "
+                <input
+                    type="number"
+                    name="max_streak"
+                    min="1"
+                    placeholder="any number of"
+                    value=queue_info.max_streak.map(|max| max.to_string())
+                />
+                " matches in a row"
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    name="singles"
+                    value="true"
+                    checked=queue_info.singles
+                />
+                " Players join alone and get paired up"
+            </label>
+            <label>
+                <input
+                    type="number"
+                    name="team_size"
+                    min="1"
+                    max=MAX_TEAM_SIZE
+                    value=queue_info.team_size
+                />
+                " players on each side"
+            </label>
+            <button type="submit">"Save"</button>
+        </ActionForm>
+        {move || {
+            value
+                .get()
+                .map(|result| match result {
+                    Ok(_) => view! { <p>"Saved"</p> }.into_any(),
+                    Err(e) => {
+                        view! { <p>"Error saving mode: " {e.to_string()}</p> }
+                            .into_any()
+                    }
+                })
+        }}
+    }
+}
+
+#[server]
+/// Changes how the queue moves along when a match ends. A blank `max_streak`
+/// means no limit, and `singles` is only sent when its box is ticked.
+/// `team_size` has to be a whole number from 1 to [`MAX_TEAM_SIZE`], since it
+/// changes the shape of every row.
+pub async fn set_queue_mode(
+    queue_id: Uuid,
+    mode: QueueMode,
+    max_streak: String,
+    singles: Option<String>,
+    team_size: String,
+) -> Result<QueueInfo, ServerFnError> {
+    require_admin(queue_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let max_streak = parse_limit("The streak limit", &max_streak)?;
+    let team_size = team_size.trim();
+    let team_size = match team_size.parse::<i32>() {
+        Ok(size) if (1..=MAX_TEAM_SIZE).contains(&size) => size,
+        _ => {
+            return Err(ServerFnError::new(format!(
+                "The team size must be a whole number from 1 to \
+                 {MAX_TEAM_SIZE}, not \"{team_size}\""
+            )))
+        }
+    };
+    Ok(store
+        .set_queue_mode(
+            queue_id,
+            mode,
+            max_streak,
+            singles.is_some(),
+            team_size,
+            // Like the other settings forms, this one doesn't track the
+            // queue's version.
+            None,
+        )
+        .await?)
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::parse_limit;
+use crate::pages::queue::NoShowTimeout;
+use crate::queue::QueueInfo;
+use leptos::prelude::*;
+use uuid::Uuid;
+
+#[component]
+pub fn NoShowSettings() -> impl IntoView {
+    let queue_info = use_context::<QueueInfo>()
+        .expect("there to be a `queue_info` provided.");
+    let NoShowTimeout(timeout) = expect_context::<NoShowTimeout>();
+    let set_no_show_rule = ServerAction::<SetNoShowRule>::new();
+    let value = set_no_show_rule.value();
+
+    Effect::new(move |_| {
+        if let Some(Ok(info)) = value.get() {
+            timeout.set(info.no_show_timeout);
+        }
+    });
+
+    view! {
+        <ActionForm action=set_no_show_rule>
+            <input
+                type="hidden"
+                name="queue_id"
+                value=queue_info.id.to_string()
+            />
+            <label>
+                "Players at the front have "
+                <input
+                    type="number"
+                    name="timeout"
+                    min="1"
+                    placeholder="unlimited"
+                    value=queue_info.no_show_timeout.map(|secs| secs.to_string())
+                />
+                " seconds to check in"
+            </label>
+            <label>
+                ", or their row moves back "
+                <input
+                    type="number"
+                    name="move_back"
+                    min="1"
+                    placeholder="out of the queue"
+                    value=queue_info.no_show_move_back.map(|places| places.to_string())
+                />
+                " places"
+            </label>
+            <button type="submit">"Save"</button>
+        </ActionForm>
+        {move || {
+            value
+                .get()
+                .map(|result| match result {
+                    Ok(_) => view! { <p>"Saved"</p> }.into_any(),
+                    Err(e) => {
+                        view! { <p>"Error saving check-in rule: " {e.to_string()}</p> }
+                            .into_any()
+                    }
+                })
+        }}
+    }
+}
+
+#[server]
+/// Changes how long players at the front have to check in, and what happens
+/// to their row if they don't. A blank `timeout` turns check-ins off, and a
+/// blank `move_back` drops the row.
+pub async fn set_no_show_rule(
+    queue_id: Uuid,
+    timeout: String,
+    move_back: String,
+) -> Result<QueueInfo, ServerFnError> {
+    require_admin(queue_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    let timeout = parse_limit("The check-in time", &timeout)?;
+    let move_back = parse_limit("The places to move back", &move_back)?;
+    // The form is filled in from the queue's settings, not its rows, so it
+    // has no version to send and the last save wins.
+    Ok(store
+        .set_no_show_rule(queue_id, timeout, move_back, None)
+        .await?)
+}
@@ -1,11 +1,48 @@
+use crate::pages::queue::rows::versions::QueueVersion;
 use crate::pages::queue::rows::{
-    AddModalState, EntryStore, LocalQueueEntry, LocalUuidState,
+    get_queue_state, lineup, AddModalState, EntryStore, LocalQueueEntry,
+    LocalUuidState,
 };
-use crate::queue::{QueueInfo, Side};
+use crate::queue::{Player, QueueInfo, QueueState, Side, SlotPlayer};
 use leptos::logging::{error, log};
 use leptos::prelude::*;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+/// A player the server has added to a row.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AddedPlayer {
+    /// The id the row had locally when the player was submitted.
+    local_id: LocalUuidState,
+    row_id: Uuid,
+    /// Every filled slot of the row after the add, including any that were
+    /// filled by others in the meantime.
+    players: Vec<SlotPlayer>,
+}
+
+/// What came of submitting a player.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AddOutcome {
+    Added(AddedPlayer),
+    /// The queue turns away players who are already waiting, and this one is
+    /// in the row at `order`. Nothing was added, so the client shows the
+    /// `current` state instead of its optimistic one.
+    AlreadyQueued {
+        local_id: LocalUuidState,
+        order: i32,
+        current: QueueState,
+    },
+    /// The queue wouldn't take the player, e.g. because it's full or closed,
+    /// for the given `reason`. Like [`AddOutcome::AlreadyQueued`], nothing
+    /// was added.
+    Refused {
+        local_id: LocalUuidState,
+        reason: String,
+        current: QueueState,
+    },
+}
+
 #[component]
 pub fn AddPlayerModal(
     modal_state: ReadSignal<AddModalState>,
@@ -15,6 +52,24 @@ pub fn AddPlayerModal(
         .expect("there to be a `queue_info` provided.");
     let entry_store_signal = use_context::<RwSignal<EntryStore>>()
         .expect("there to be a `entry_store` provided.");
+    let queue_version = expect_context::<QueueVersion>();
+    // Why the last player wasn't added, and the modal to show them again
+    // with the reason.
+    let refusal = RwSignal::new(None::<String>);
+    let submitted_state = StoredValue::new(AddModalState::Closed);
+    // Players submitted that the server hasn't answered for yet, by the id
+    // their row had locally.
+    let unanswered = StoredValue::new(Vec::<LocalUuidState>::new());
+    // Takes back an optimistic add the server refused.
+    let roll_back =
+        move |local_id: LocalUuidState, current: QueueState, reason: String| {
+            unanswered.update_value(|ids| ids.retain(|id| *id != local_id));
+            entry_store_signal
+                .update(|es| es.retain(|e| e.id.read_untracked() != local_id));
+            queue_version.sync(current);
+            refusal.set(Some(reason));
+            set_modal_state.set(submitted_state.get_value());
+        };
 
     let is_visible = move || modal_state.get() != AddModalState::Closed;
 
@@ -23,19 +78,98 @@ pub fn AddPlayerModal(
 
     Effect::new(move |_| {
         let server_fn_result = add_player_value.get();
-        // If a new row was created, update the optimistically rendered row.
-        if let Some(Ok(Some((old_id, new_id)))) = server_fn_result {
+        if let Some(Ok(AddOutcome::AlreadyQueued {
+            local_id,
+            order,
+            current,
+        })) = server_fn_result
+        {
+            let reason = format!("You're already in row {}", order + 1);
+            roll_back(local_id, current, reason);
+            return;
+        }
+        if let Some(Ok(AddOutcome::Refused {
+            local_id,
+            reason,
+            current,
+        })) = server_fn_result
+        {
+            roll_back(local_id, current, reason);
+            return;
+        }
+        // The request itself failed, so there's no telling which player it
+        // was for. Take back every unanswered add and show the server's
+        // state, which has any that did go through.
+        if let Some(Err(e)) = server_fn_result {
+            error!("Error adding player: {}", e);
+            let local_ids = unanswered
+                .try_update_value(std::mem::take)
+                .unwrap_or_default();
+            entry_store_signal.update(|es| {
+                es.retain(|entry| {
+                    !local_ids.contains(&entry.id.read_untracked())
+                })
+            });
+            refusal.set(Some(e.to_string()));
+            let queue_id = queue_info.id;
+            leptos::task::spawn_local(async move {
+                match get_queue_state(queue_id).await {
+                    Ok(current) => queue_version.sync(current),
+                    Err(e) => error!("Error getting queue entries: {}", e),
+                }
+            });
+            return;
+        }
+        // Update the optimistically rendered row with the server's ids.
+        if let Some(Ok(AddOutcome::Added(added))) = server_fn_result {
+            unanswered
+                .update_value(|ids| ids.retain(|id| *id != added.local_id));
+            let resolved_id = LocalUuidState::Resolved(added.row_id);
+            // A live update may have delivered the new row already, or the
+            // player was paired into a row that's already shown.
+            if added.local_id != resolved_id {
+                let shown = entry_store_signal
+                    .read_untracked()
+                    .iter()
+                    .find(|e| e.id.read_untracked() == resolved_id)
+                    .copied();
+                if let Some(e) = shown {
+                    e.players
+                        .set(lineup(&added.players));
+                    entry_store_signal.update(|es| {
+                        es.retain(|e| e.id.read_untracked() != added.local_id)
+                    });
+                    return;
+                }
+            }
             match entry_store_signal
                 .read_untracked()
                 .iter()
-                .find(|e| {
-                    e.id.read_untracked() == LocalUuidState::Pending(old_id)
-                }) {
+                .find(|e| e.id.read_untracked() == added.local_id)
+            {
                 Some(e) => {
-                    e.id.set(LocalUuidState::Resolved(new_id))
+                    e.id.set(resolved_id);
+                    e.players
+                        .set(lineup(&added.players));
                 }
-                None => log!("Couldn't find pending entry to update."),
+                None => log!("Couldn't find optimistic entry to update."),
+            }
+        }
+    });
+
+    // Suggest existing players so the same person isn't entered twice.
+    let (player_query, set_player_query) = signal(String::new());
+    let player_tag = RwSignal::new(String::new());
+    let player_suggestions = LocalResource::new(move || {
+        let query = player_query.get();
+        async move {
+            if query.trim().is_empty() {
+                return Vec::new();
             }
+            search_players(query)
+                .await
+                .inspect_err(|e| error!("Error searching players: {}", e))
+                .unwrap_or_default()
         }
     });
 
@@ -44,89 +178,162 @@ pub fn AddPlayerModal(
             <div class="modal-content">
                 <h1>"Add Player"</h1>
                 {move || {
-                    if let AddModalState::Open { row_id, side, order } = modal_state
+                    refusal
                         .get()
-                    {
-                        // Generate pre-filled ActionForm inputs
-                        // I should be using LocalUuidState but I was lazy and
-                        // couldn't figure it out
-                        let (resolved_id, pending_id) = match row_id {
-                            Some(id) => (Some(id.to_string()), None),
-                            None => (None, Some(uuid::Uuid::new_v4().to_string())),
-                        };
-
-                        view! {
-                            <ActionForm
-                                action=add_player
-                                on:submit=move |ev| {
-                                    set_modal_state.set(AddModalState::Closed);
-                                    // Optimistically update the changed row
-                                    let input = AddPlayer::from_event(&ev)
-                                        .expect("submission to be well-formed");
-                                    let local_id = local_uuid_helper(
-                                            input.resolved_id,
-                                            input.pending_id,
-                                        )
-                                        .expect("row id hack to be well-formed");
-                                    match local_id {
-                                        LocalUuidState::Resolved(_) => {
-                                            let entry = entry_store_signal
-                                                .read()
-                                                .get(order)
-                                                .expect("entry to exist")
-                                                .to_owned();
-                                            let slot = match side {
-                                                Side::Left => entry.left,
-                                                Side::Right => entry.right,
-                                            };
-                                            if slot.get().is_none() {
-                                                slot.set(Some(input.player))
-                                            } else {
-                                                error!(
-                                                    "ON_SUBMIT: Attempted to add player to occupied slot!"
-                                                )
-                                            }
-                                        }
-                                        LocalUuidState::Pending(_) => {
-                                            let (left, right) = match side {
-                                                Side::Left => {
-                                                    (RwSignal::new(Some(input.player)), RwSignal::new(None))
-                                                }
-                                                Side::Right => {
-                                                    (RwSignal::new(None), RwSignal::new(Some(input.player)))
-                                                }
-                                            };
-                                            let new_entry = LocalQueueEntry {
-                                                id: RwSignal::new(local_id),
-                                                left,
-                                                right,
-                                            };
-                                            entry_store_signal
-                                                .update(|es| {
-                                                    es.push(new_entry);
-                                                });
+                        .map(|reason| {
+                            view! {
+                                <p class="modal-error" role="alert">
+                                    {reason}
+                                </p>
+                            }
+                        })
+                }}
+                {move || {
+                    let (row_id, slot, order) = match modal_state.get() {
+                        AddModalState::Open { row_id, slot, order } => {
+                            (row_id, Some(slot), order)
+                        }
+                        AddModalState::Join { order } => (None, None, order),
+                        AddModalState::Closed => return ().into_any(),
+                    };
+                    // Generate pre-filled ActionForm inputs
+                    // I should be using LocalUuidState but I was lazy and
+                    // couldn't figure it out
+                    let (resolved_id, pending_id) = match row_id {
+                        Some(id) => (Some(id.to_string()), None),
+                        None => (None, Some(uuid::Uuid::new_v4().to_string())),
+                    };
+
+                    view! {
+                        <ActionForm
+                            action=add_player
+                            on:submit=move |ev| {
+                                submitted_state.set_value(modal_state.get_untracked());
+                                refusal.set(None);
+                                set_modal_state.set(AddModalState::Closed);
+                                // Optimistically update the changed row
+                                let input = AddPlayer::from_event(&ev)
+                                    .expect("submission to be well-formed");
+                                let local_id = local_uuid_helper(
+                                        input.resolved_id,
+                                        input.pending_id,
+                                    )
+                                    .expect("row id hack to be well-formed");
+                                unanswered
+                                    .update_value(|ids| ids.push(local_id.clone()));
+                                // Stand-in until the server resolves the player
+                                let player = Player {
+                                    id: Uuid::new_v4(),
+                                    display_name: input.player.trim().to_string(),
+                                    tag: input
+                                        .tag
+                                        .map(|tag| tag.trim().to_string())
+                                        .filter(|tag| !tag.is_empty()),
+                                };
+                                set_player_query.set(String::new());
+                                player_tag.set(String::new());
+                                match local_id {
+                                    LocalUuidState::Resolved(_) => {
+                                        let entry = entry_store_signal
+                                            .read()
+                                            .get(order)
+                                            .expect("entry to exist")
+                                            .to_owned();
+                                        let slot = slot.unwrap_or(Side::Left.into());
+                                        if entry.player_untracked(slot).is_none() {
+                                            entry.set_player(slot, Some(player));
+                                        } else {
+                                            error!(
+                                                "ON_SUBMIT: Attempted to add player to occupied slot!"
+                                            )
                                         }
                                     }
+                                    // Show a new row at the back even when
+                                    // joining alone, until the server says
+                                    // which row the player was paired into.
+                                    LocalUuidState::Pending(_) => {
+                                        let slot = slot.unwrap_or(Side::Left.into());
+                                        let new_entry = LocalQueueEntry::pending(
+                                            local_id,
+                                            BTreeMap::from([(slot, player)]),
+                                        );
+                                        entry_store_signal
+                                            .update(|es| {
+                                                es.push(new_entry);
+                                            });
+                                    }
                                 }
-                            >
+                            }
+                        >
+                            <input
+                                type="hidden"
+                                name="queue_id"
+                                value=queue_info.id.to_string()
+                            />
+                            {slot
+                                .map(|slot| {
+                                    view! {
+                                        <input type="hidden" name="side" value=slot.side.to_string() />
+                                        <input type="hidden" name="slot_index" value=slot.index />
+                                    }
+                                })}
+                            <input type="hidden" name="resolved_id" value=resolved_id />
+                            <input type="hidden" name="pending_id" value=pending_id />
+                            <label>
+                                "Player Name"
                                 <input
-                                    type="hidden"
-                                    name="queue_id"
-                                    value=queue_info.id.to_string()
+                                    type="text"
+                                    name="player"
+                                    list="player-suggestions"
+                                    autocomplete="off"
+                                    prop:value=player_query
+                                    on:input=move |ev| {
+                                        let value = event_target_value(&ev);
+                                        // A picked suggestion fills in the
+                                        // player's tag, so it's them that's
+                                        // added and not someone else with
+                                        // the same name.
+                                        let picked = player_suggestions
+                                            .get_untracked()
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .find(|player| suggestion(player) == value);
+                                        match picked {
+                                            Some(player) => {
+                                                player_tag.set(player.tag.unwrap_or_default());
+                                                set_player_query.set(player.display_name);
+                                            }
+                                            None => set_player_query.set(value),
+                                        }
+                                    }
                                 />
-                                <input type="hidden" name="side" value=side.to_string() />
-                                <input type="hidden" name="resolved_id" value=resolved_id />
-                                <input type="hidden" name="pending_id" value=pending_id />
-                                <label>
-                                    "Player Name" <input type="text" name="player" />
-                                </label>
-                                <input type="submit" value="Add Player" />
-                            </ActionForm>
-                        }
-                            .into_any()
-                    } else {
-                        ().into_any()
+                            </label>
+                            <label>
+                                "Tag (optional)"
+                                <input
+                                    type="text"
+                                    name="tag"
+                                    autocomplete="off"
+                                    prop:value=player_tag
+                                    on:input=move |ev| player_tag.set(event_target_value(&ev))
+                                />
+                            </label>
+                            <datalist id="player-suggestions">
+                                {move || {
+                                    player_suggestions
+                                        .get()
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(|player| {
+                                            view! { <option value=suggestion(&player)></option> }
+                                        })
+                                        .collect_view()
+                                }}
+                            </datalist>
+                            <input type="submit" value="Add Player" />
+                        </ActionForm>
                     }
+                        .into_any()
                 }}
             </div>
         </div>
@@ -138,11 +345,15 @@ pub async fn add_player(
     queue_id: Uuid,
     resolved_id: Option<Uuid>,
     pending_id: Option<Uuid>,
-    side: Side,
+    side: Option<Side>,
+    slot_index: Option<i32>,
     player: String,
-) -> Result<Option<(Uuid, Uuid)>, ServerFnError> {
-    use crate::db::{api::add_player_to_row, api::add_row, DbPool};
-    let pool = use_context::<DbPool>().expect("there to be a pool provided.");
+    tag: Option<String>,
+) -> Result<AddOutcome, ServerFnError> {
+    use crate::db::Store;
+    use crate::queue::Slot;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
 
     // To monitor unresolved state
     // use tokio::time::sleep;
@@ -150,27 +361,126 @@ pub async fn add_player(
     // sleep(Duration::from_millis(2000)).await;
 
     // TODO: pass row_id as LocalUuidState instead of this bs lmao
-    let row_id = local_uuid_helper(resolved_id, pending_id)?;
+    let local_id = local_uuid_helper(resolved_id, pending_id)?;
+    let player = match store
+        .get_or_create_player(player, tag)
+        .await
+    {
+        Ok(player) => player,
+        Err(e) => return refused(&store, queue_id, local_id, e).await,
+    };
 
-    match row_id {
-        // If we add a new player to an existing row, return none
-        LocalUuidState::Resolved(row_id) => {
+    let slot = side.map(|side| Slot::new(side, slot_index.unwrap_or(0)));
+    let added = match (local_id.clone(), slot) {
+        (LocalUuidState::Resolved(row_id), Some(slot)) => {
             log!("SERVER_FN: adding player to row {}", row_id);
-            add_player_to_row(row_id, player, side, pool)
+            store
+                .add_player_to_row(row_id, player.id, slot)
                 .await
-                .inspect_err(|e| error!("Error adding player: {}", e))?;
-            Ok(None)
+                .map(|entry| (entry.id, entry.players))
+        }
+        (LocalUuidState::Resolved(_), None) => {
+            return Err(ServerFnError::ServerError(
+                "A side is needed to join a row".to_string(),
+            ));
         }
-        // If we add a player to a new row, return the old and new IDs so we can
+        // Without a side the player joins alone and gets paired up, which
+        // may put them in a row the client already shows
+        (LocalUuidState::Pending(_), None) => {
+            log!("SERVER_FN: pairing player up");
+            store
+                .join_queue(queue_id, player.id)
+                .await
+                .map(|entry| (entry.id, entry.players))
+        }
+        // If we add a player to a new row, the client needs the new ID to
         // update the optimistically rendered row
-        LocalUuidState::Pending(temp_id) => {
+        (LocalUuidState::Pending(_), Some(slot)) => {
             log!("SERVER_FN: adding player to new row");
-            let new_id = add_row(queue_id, player, side, pool)
+            store
+                .add_row(queue_id, player.id, slot)
                 .await
-                .inspect_err(|e| error!("Error adding player: {}", e))?;
-            log!("SERVER_FN: Returning new row ID {}", new_id);
-            Ok(Some((temp_id, new_id)))
+                .inspect(|new_id| {
+                    log!("SERVER_FN: Returning new row ID {}", new_id)
+                })
+                .map(|new_id| {
+                    let players = vec![SlotPlayer {
+                        slot,
+                        player,
+                        checked_in: false,
+                    }];
+                    (new_id, players)
+                })
         }
+    };
+    match added {
+        Ok((row_id, players)) => Ok(AddOutcome::Added(AddedPlayer {
+            local_id,
+            row_id,
+            players,
+        })),
+        Err(e) => refused(&store, queue_id, local_id, e).await,
+    }
+}
+
+/// Tells the client why a player wasn't added, along with the queue as it
+/// is, so it can take back the row or slot it showed them in.
+#[cfg(feature = "ssr")]
+async fn refused(
+    store: &crate::db::Store,
+    queue_id: Uuid,
+    local_id: LocalUuidState,
+    e: crate::db::api::ApiError,
+) -> Result<AddOutcome, ServerFnError> {
+    use crate::db::api::ApiError;
+    let current = store
+        .get_queue_state(queue_id)
+        .await?;
+    Ok(match e {
+        // Not an error for the client, which tells the player where they are.
+        ApiError::AlreadyQueued { order, .. } => AddOutcome::AlreadyQueued {
+            local_id,
+            order,
+            current,
+        },
+        // Don't show database details to visitors.
+        ApiError::PoolError(_)
+        | ApiError::DieselError(_)
+        | ApiError::PasswordHashError(_) => {
+            error!("Error adding player: {}", e);
+            AddOutcome::Refused {
+                local_id,
+                reason: "Couldn't add the player, try again".to_string(),
+                current,
+            }
+        }
+        e => AddOutcome::Refused {
+            local_id,
+            reason: format!("Couldn't add the player: {e}"),
+            current,
+        },
+    })
+}
+
+#[server]
+pub async fn search_players(
+    query: String,
+) -> Result<Vec<Player>, ServerFnError> {
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store
+        .search_players(query)
+        .await
+        .inspect_err(|e| error!("Error searching players: {}", e))?)
+}
+
+/// How a suggested player is shown, with their tag to tell them apart from
+/// others with the same name.
+fn suggestion(player: &Player) -> String {
+    match &player.tag {
+        Some(tag) => format!("{} ({tag})", player.display_name),
+        None => player.display_name.clone(),
     }
 }
 
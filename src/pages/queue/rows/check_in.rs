@@ -0,0 +1,181 @@
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+use crate::pages::queue::rows::{EntryStore, LocalQueueEntry, LocalUuidState};
+use crate::pages::queue::NoShowTimeout;
+use crate::queue::{Player, QueueEntry, Side, Slot};
+use chrono::{DateTime, Utc};
+use leptos::logging::error;
+use leptos::prelude::*;
+use uuid::Uuid;
+
+/// The current time, which ticks every second in the browser so countdowns
+/// keep moving.
+#[derive(Copy, Clone)]
+pub struct Clock(RwSignal<DateTime<Utc>>);
+
+impl Clock {
+    pub fn start() -> Self {
+        let now = RwSignal::new(Utc::now());
+        #[cfg(feature = "hydrate")]
+        {
+            use std::time::Duration;
+            match set_interval_with_handle(
+                move || now.set(Utc::now()),
+                Duration::from_secs(1),
+            ) {
+                Ok(handle) => on_cleanup(move || handle.clear()),
+                Err(e) => error!("Error starting the clock: {:?}", e),
+            }
+        }
+        Clock(now)
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.0.get()
+    }
+}
+
+/// How long a called row has left to check in, shown next to its order.
+#[component]
+pub fn CallCountdown(entry: LocalQueueEntry) -> impl IntoView {
+    let Clock(now) = expect_context::<Clock>();
+    let NoShowTimeout(timeout) = expect_context::<NoShowTimeout>();
+    let everyone_checked_in = move || {
+        entry.players.with(|players| {
+            entry
+                .checked_in
+                .with(|checked_in| {
+                    players
+                        .values()
+                        .all(|p| checked_in.contains(&p.id))
+                })
+        })
+    };
+    let countdown = move || {
+        let called_at = entry.called_at.get()?;
+        if everyone_checked_in() {
+            return Some("Ready".to_string());
+        }
+        let deadline =
+            called_at + chrono::TimeDelta::seconds(timeout.get()?.into());
+        let left = (deadline - now.get()).num_seconds();
+        Some(if left > 0 {
+            format!("{}:{:02}", left / 60, left % 60)
+        } else {
+            "Time's up".to_string()
+        })
+    };
+
+    view! {
+        <Show when=move || countdown().is_some()>
+            <div
+                class="call-countdown"
+                class:ready=everyone_checked_in
+                title="Time left to check in"
+            >
+                {countdown}
+            </div>
+        </Show>
+    }
+}
+
+/// Lets a player in a called row say they're here, and shows once they have.
+/// Anyone can check a player in, since it's usually the player themselves.
+#[component]
+pub fn CheckInButton(
+    id: Signal<Option<LocalUuidState>>,
+    player_data: Signal<Option<Player>>,
+    player_slot: Slot,
+    is_inactive: Signal<bool>,
+) -> impl IntoView {
+    let entry_store_signal = expect_context::<RwSignal<EntryStore>>();
+    let check_in = ServerAction::<CheckIn>::new();
+    let value = check_in.value();
+
+    let entry = Memo::new(move |_| {
+        let id = id.get()?;
+        entry_store_signal.with(|es| {
+            es.iter()
+                .find(|e| e.id.get() == id)
+                .copied()
+        })
+    });
+    let is_called = move || {
+        entry
+            .get()
+            .is_some_and(|e| e.called_at.get().is_some())
+    };
+    let is_checked_in = move || {
+        let (Some(entry), Some(player)) = (entry.get(), player_data.get())
+        else {
+            return false;
+        };
+        entry
+            .checked_in
+            .with(|checked_in| checked_in.contains(&player.id))
+    };
+
+    Effect::new(move |_| {
+        let Some(Err(e)) = value.get() else { return };
+        error!("Error checking in: {}", e);
+        // Take back the optimistic check-in.
+        if let (Some(entry), Some(player)) =
+            (entry.get_untracked(), player_data.get_untracked())
+        {
+            entry
+                .checked_in
+                .update(|checked_in| {
+                    checked_in.remove(&player.id);
+                });
+        }
+    });
+
+    let row_id = move || match id.get() {
+        Some(LocalUuidState::Resolved(uuid)) => Some(uuid.to_string()),
+        _ => None,
+    };
+
+    view! {
+        <Show when=is_called>
+            <Show
+                when=move || !is_checked_in()
+                fallback=|| view! { <span class="checked-in">"Checked in"</span> }
+            >
+                <ActionForm
+                    action=check_in
+                    on:submit=move |_| {
+                        if let (Some(entry), Some(player)) =
+                            (entry.get_untracked(), player_data.get_untracked())
+                        {
+                            entry.checked_in.update(|checked_in| {
+                                checked_in.insert(player.id);
+                            });
+                        }
+                    }
+                >
+                    <input type="hidden" name="row_id" value=row_id />
+                    <input type="hidden" name="side" value=player_slot.side.to_string() />
+                    <input type="hidden" name="slot_index" value=player_slot.index />
+                    <button type="submit" class="check-in-button" disabled=is_inactive>
+                        "Check in"
+                    </button>
+                </ActionForm>
+            </Show>
+        </Show>
+    }
+}
+
+#[server]
+/// Checks in the player in one slot of a row that's been called.
+pub async fn check_in(
+    row_id: Uuid,
+    side: Side,
+    slot_index: i32,
+) -> Result<QueueEntry, ServerFnError> {
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store
+        .check_in(row_id, Slot::new(side, slot_index))
+        .await
+        .inspect_err(|e| error!("Error checking in: {}", e))?)
+}
@@ -0,0 +1,138 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::rows::versions::versioned;
+use crate::pages::queue::rows::versions::{QueueVersion, Versioned};
+use crate::pages::queue::rows::{EntryStore, LocalQueueEntry, LocalUuidState};
+use crate::queue::{QueueEntry, QueueInfo, QueueMode, Side};
+use leptos::logging::error;
+use leptos::prelude::*;
+use uuid::Uuid;
+
+#[component]
+pub fn FinishMatchButton() -> impl IntoView {
+    let queue_info = use_context::<QueueInfo>()
+        .expect("there to be a `queue_info` provided.");
+    let entry_store_signal = use_context::<RwSignal<EntryStore>>()
+        .expect("there to be a `entry_store` provided.");
+    let queue_version = expect_context::<QueueVersion>();
+    let mode = expect_context::<RwSignal<QueueMode>>();
+    let complete_front_row = ServerAction::<CompleteFrontRow>::new();
+    let value = complete_front_row.value();
+    // Holds the optimistically removed entry until the server responds.
+    let completed = StoredValue::new(None::<LocalQueueEntry>);
+
+    Effect::new(move |_| {
+        let Some(result) = value.get() else { return };
+        let entry = completed
+            .try_update_value(Option::take)
+            .flatten();
+        match result {
+            Ok(Versioned::UpToDate(_)) => (),
+            // Someone else finished the match or changed the queue first.
+            Ok(Versioned::Conflict(current)) => queue_version.refresh(current),
+            Err(e) => {
+                error!("Error completing match: {}", e);
+                // Put the row back where it was.
+                if let Some(entry) = entry {
+                    entry_store_signal.update(|es| es.insert(0, entry));
+                }
+            }
+        }
+    });
+
+    // Winners can only be picked once both sides have someone.
+    let front_teams = move || {
+        entry_store_signal.with(|es| {
+            let front = es.first()?;
+            Some((
+                front.team_names(Side::Left)?,
+                front.team_names(Side::Right)?,
+            ))
+        })
+    };
+
+    // Only allow finishing a match the server knows about.
+    let can_finish = move || {
+        entry_store_signal.with(|es| {
+            es.first().is_some_and(|e| {
+                matches!(e.id.get(), LocalUuidState::Resolved(_))
+            })
+        })
+    };
+
+    view! {
+        <ActionForm
+            action=complete_front_row
+            on:submit=move |_| {
+                // Someone may stay on in the other modes, so leave the row
+                // until the server says what happened.
+                if mode.get_untracked() != QueueMode::Pairs {
+                    return;
+                }
+                entry_store_signal
+                    .update(|es| {
+                        if !es.is_empty() {
+                            completed.set_value(Some(es.remove(0)));
+                        }
+                    });
+            }
+        >
+            <input
+                type="hidden"
+                name="queue_id"
+                value=queue_info.id.to_string()
+            />
+            <input
+                type="hidden"
+                name="expected_version"
+                value=move || queue_version.get()
+            />
+            {move || {
+                front_teams()
+                    .map(|(left, right)| {
+                        view! {
+                            <button
+                                type="submit"
+                                name="winner"
+                                value="Left"
+                                disabled=move || !can_finish()
+                            >
+                                {left}" won"
+                            </button>
+                            <button
+                                type="submit"
+                                name="winner"
+                                value="Right"
+                                disabled=move || !can_finish()
+                            >
+                                {right}" won"
+                            </button>
+                        }
+                    })
+            }}
+            <button type="submit" disabled=move || !can_finish()>
+                "Match Finished"
+            </button>
+        </ActionForm>
+    }
+}
+
+#[server]
+pub async fn complete_front_row(
+    queue_id: Uuid,
+    expected_version: i32,
+    winner: Option<Side>,
+) -> Result<Versioned<Option<QueueEntry>>, ServerFnError> {
+    require_admin(queue_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .complete_front_row(queue_id, winner, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error completing match: {}", e))?)
+}
@@ -0,0 +1,105 @@
+use crate::pages::queue::rows::versions::QueueVersion;
+use crate::pages::queue::rows::{
+    checked_in, lineup, EntryStore, LocalQueueEntry, LocalUuidState,
+};
+use crate::queue::QueueEntry;
+use leptos::prelude::*;
+
+/// Keeps the entry store in sync with changes made elsewhere, like on another
+/// tablet at the venue.
+#[cfg(feature = "hydrate")]
+pub fn subscribe_to_queue(url_name: &str, queue_version: QueueVersion) {
+    use crate::queue::QueueState;
+    use leptos::logging::error;
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{EventSource, MessageEvent};
+
+    let source = match EventSource::new(&format!("/queue/{url_name}/events")) {
+        Ok(source) => source,
+        Err(e) => {
+            error!("Error subscribing to queue: {:?}", e);
+            return;
+        }
+    };
+    let on_state =
+        Closure::<dyn FnMut(MessageEvent)>::new(move |ev: MessageEvent| {
+            let Some(data) = ev.data().as_string() else {
+                return;
+            };
+            match serde_json::from_str::<QueueState>(&data) {
+                Ok(state) => queue_version.sync(state),
+                Err(e) => error!("Error reading queue update: {}", e),
+            }
+        });
+    if let Err(e) = source.add_event_listener_with_callback(
+        "state",
+        on_state.as_ref().unchecked_ref(),
+    ) {
+        error!("Error subscribing to queue: {:?}", e);
+    }
+
+    // The callback has to live as long as the subscription.
+    let subscription = StoredValue::new_local((source, on_state));
+    on_cleanup(move || subscription.with_value(|(source, _)| source.close()));
+}
+
+#[cfg(not(feature = "hydrate"))]
+pub fn subscribe_to_queue(_url_name: &str, _queue_version: QueueVersion) {}
+
+/// Patches the entry store to match the server, reusing the signals of
+/// entries that already exist so only what changed is re-rendered.
+pub fn merge_entries(
+    entry_store_signal: RwSignal<EntryStore>,
+    server_entries: Vec<QueueEntry>,
+) {
+    let local = entry_store_signal.get_untracked();
+    let mut merged: EntryStore = server_entries
+        .into_iter()
+        .map(|server_entry| {
+            let id = LocalUuidState::Resolved(server_entry.id);
+            let Some(entry) = local
+                .iter()
+                .find(|e| e.id.get_untracked() == id)
+            else {
+                return LocalQueueEntry::from(server_entry);
+            };
+            let players = lineup(&server_entry.players);
+            if entry
+                .players
+                .with_untracked(|p| *p != players)
+            {
+                entry.players.set(players);
+            }
+            if entry.called_at.get_untracked() != server_entry.called_at {
+                entry
+                    .called_at
+                    .set(server_entry.called_at);
+            }
+            let checked_in = checked_in(&server_entry.players);
+            if entry
+                .checked_in
+                .with_untracked(|c| *c != checked_in)
+            {
+                entry.checked_in.set(checked_in);
+            }
+            if entry
+                .estimated_start
+                .get_untracked()
+                != server_entry.estimated_start
+            {
+                entry
+                    .estimated_start
+                    .set(server_entry.estimated_start);
+            }
+            *entry
+        })
+        .collect();
+    // Rows the server doesn't know about yet stay at the back.
+    merged.extend(local.iter().filter(|e| {
+        matches!(e.id.get_untracked(), LocalUuidState::Pending(_))
+    }));
+
+    if merged != local {
+        entry_store_signal.set(merged);
+    }
+}
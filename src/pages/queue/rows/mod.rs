@@ -1,10 +1,35 @@
 mod add_player_modal;
+mod check_in;
+mod finish_match_button;
+mod live_updates;
+mod move_player;
+mod remove_buttons;
+mod reorder;
+mod start_estimate;
+mod stations;
+mod versions;
 
-use crate::queue::{QueueEntry, QueueInfo, Side};
+use crate::pages::queue::{IsAdmin, Singles, TeamSize};
+use crate::queue::{
+    Player, QueueEntry, QueueInfo, QueueSettings, QueueState, QueueStatus,
+    Side, Slot, SlotPlayer, Station,
+};
 use add_player_modal::AddPlayerModal;
+use check_in::{CallCountdown, CheckInButton, Clock};
+use chrono::{DateTime, Utc};
+use finish_match_button::FinishMatchButton;
+use leptos::ev::DragEvent;
 use leptos::server_fn::serde::{Deserialize, Serialize};
 use leptos::{logging::error, prelude::*};
+use live_updates::subscribe_to_queue;
+use move_player::{PlayerMove, PlayerMover};
+use remove_buttons::{DeleteRowButton, RemovePlayerButton, Remover};
+use reorder::{start_drag, DragHandle, DragState, RowMover};
+use start_estimate::StartEstimate;
+use stations::NowPlaying;
+use std::collections::{BTreeMap, BTreeSet};
 use uuid::Uuid;
+use versions::{QueueVersion, RefreshedNotice};
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub enum LocalUuidState {
@@ -14,18 +39,99 @@ pub enum LocalUuidState {
     Pending(Uuid),
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct LocalQueueEntry {
     id: RwSignal<LocalUuidState>,
-    left: RwSignal<Option<String>>,
-    right: RwSignal<Option<String>>,
+    players: RwSignal<BTreeMap<Slot, Player>>,
+    /// When the row was called to play, if it has been.
+    called_at: RwSignal<Option<DateTime<Utc>>>,
+    /// The ids of the players who checked in since the row was called.
+    checked_in: RwSignal<BTreeSet<Uuid>>,
+    /// When the server expects the row to start playing, if it's waiting.
+    estimated_start: RwSignal<Option<DateTime<Utc>>>,
+}
+
+/// The players of a row from the server, by slot.
+fn lineup(players: &[SlotPlayer]) -> BTreeMap<Slot, Player> {
+    players
+        .iter()
+        .map(|p| (p.slot, p.player.clone()))
+        .collect()
+}
+
+/// The ids of the players of a row from the server who checked in.
+fn checked_in(players: &[SlotPlayer]) -> BTreeSet<Uuid> {
+    players
+        .iter()
+        .filter(|p| p.checked_in)
+        .map(|p| p.player.id)
+        .collect()
+}
+
+impl From<QueueEntry> for LocalQueueEntry {
+    fn from(entry: QueueEntry) -> Self {
+        LocalQueueEntry {
+            id: RwSignal::new(LocalUuidState::Resolved(entry.id)),
+            players: RwSignal::new(lineup(&entry.players)),
+            called_at: RwSignal::new(entry.called_at),
+            checked_in: RwSignal::new(checked_in(&entry.players)),
+            estimated_start: RwSignal::new(entry.estimated_start),
+        }
+    }
+}
+
+impl LocalQueueEntry {
+    /// A row that's only been added on this device so far.
+    fn pending(id: LocalUuidState, players: BTreeMap<Slot, Player>) -> Self {
+        LocalQueueEntry {
+            id: RwSignal::new(id),
+            players: RwSignal::new(players),
+            called_at: RwSignal::new(None),
+            checked_in: RwSignal::new(BTreeSet::new()),
+            estimated_start: RwSignal::new(None),
+        }
+    }
+
+    /// The player in the given slot of this entry, without tracking it.
+    fn player_untracked(&self, slot: Slot) -> Option<Player> {
+        self.players
+            .with_untracked(|players| players.get(&slot).cloned())
+    }
+
+    /// Puts a player in the given slot of this entry, or empties it if
+    /// `player` is `None`. Returns whoever was in the slot before.
+    fn set_player(&self, slot: Slot, player: Option<Player>) -> Option<Player> {
+        self.players
+            .try_update(|players| match player {
+                Some(player) => players.insert(slot, player),
+                None => players.remove(&slot),
+            })
+            .flatten()
+    }
+
+    /// The names of everyone on one side of this entry, or `None` if the
+    /// side is empty.
+    fn team_names(&self, side: Side) -> Option<String> {
+        self.players.with(|players| {
+            let names: Vec<&str> = players
+                .iter()
+                .filter(|(slot, _)| slot.side == side)
+                .map(|(_, player)| player.display_name.as_str())
+                .collect();
+            (!names.is_empty()).then(|| names.join(" & "))
+        })
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum AddModalState {
     Open {
         row_id: Option<Uuid>,
-        side: Side,
+        slot: Slot,
+        order: usize,
+    },
+    /// Joining a singles queue, where the server picks the row and side.
+    Join {
         order: usize,
     },
     Closed,
@@ -36,33 +142,20 @@ pub type EntryStore = Vec<LocalQueueEntry>;
 pub fn Rows() -> impl IntoView {
     let queue_info = use_context::<QueueInfo>()
         .expect("there to be a `queue_info` provided.");
+    let IsAdmin(is_admin) = expect_context::<IsAdmin>();
+    let settings = expect_context::<RwSignal<QueueSettings>>();
     let (modal_state, set_modal_state) = signal(AddModalState::Closed);
     provide_context(modal_state);
     provide_context(set_modal_state);
 
-    // Load and unpack entries from server on page load.
-    let entry_store_rsc: Resource<Result<Vec<LocalQueueEntry>, ServerFnError>> =
+    let queue_id = queue_info.id;
+    // Load entries from server on page load.
+    let entry_store_rsc: Resource<Result<QueueState, ServerFnError>> =
         Resource::new(
             || (), // No dependencies, only run on page load
             move |_| async move {
-                get_queue_entries(queue_info.id)
+                get_queue_state(queue_id)
                     .await
-                    .map(|server_entries| {
-                        server_entries
-                            .into_iter()
-                            .map(|entry| {
-                                let (left, right) =
-                                    entry.players.players_tuple();
-                                LocalQueueEntry {
-                                    id: RwSignal::new(
-                                        LocalUuidState::Resolved(entry.id),
-                                    ),
-                                    left: RwSignal::new(left),
-                                    right: RwSignal::new(right),
-                                }
-                            })
-                            .collect()
-                    })
                     .inspect_err(|e| {
                         error!("Error getting queue entries: {}", e);
                     })
@@ -70,10 +163,23 @@ pub fn Rows() -> impl IntoView {
         );
 
     let entry_store_signal = RwSignal::new(Vec::new());
+    let stations = RwSignal::new(Vec::<Station>::new());
+    let queue_version = QueueVersion::new(entry_store_signal, stations);
     provide_context(entry_store_signal);
+    provide_context(stations);
+    provide_context(queue_version);
+    provide_context(RwSignal::new(DragState::Idle));
+    provide_context(Clock::start());
+    provide_context(RowMover::new(entry_store_signal, queue_version));
+    provide_context(PlayerMover::new(
+        entry_store_signal,
+        queue_version,
+    ));
+    provide_context(Remover::new(entry_store_signal, queue_version));
+    subscribe_to_queue(&queue_info.url_name, queue_version);
     // Update entry store signal when entries load
     Effect::new(move |_| match entry_store_rsc.get() {
-        Some(Ok(entries)) => entry_store_signal.set(entries),
+        Some(Ok(state)) => queue_version.sync(state),
         Some(Err(e)) => error!("Error loading rows: {}", e),
         _ => (),
     });
@@ -100,14 +206,34 @@ pub fn Rows() -> impl IntoView {
                         .into_any();
                 }
                 view! {
+                    <RefreshedNotice />
+                    <NowPlaying />
+                    // Matches on stations are finished from their panels.
+                    <Show when=move || {
+                        is_admin.get() && stations.with(Vec::is_empty)
+                    }>
+                        <FinishMatchButton />
+                    </Show>
                     <For
-                        each=move || {
-                            entry_store_signal.get().into_iter().enumerate()
+                        each=move || entry_store_signal.get()
+                        key=|entry| entry.id.get()
+                        children=move |entry| {
+                            // Rows are keyed by id, so derive the order to keep
+                            // labels correct when rows ahead are removed.
+                            let order = Signal::derive(move || {
+                                entry_store_signal
+                                    .with(|es| es.iter().position(|e| *e == entry))
+                                    .unwrap_or_default()
+                            });
+                            view! { <Row entry order /> }
                         }
-                        key=|(_, entry)| entry.id.get()
-                        children=move |(order, entry)| view! { <Row entry order /> }
                     />
-                    <EmptyRow order=entry_store_signal.with(|es| es.len()) />
+                    <Show
+                        when=move || settings.with(|s| s.status == QueueStatus::Open)
+                        fallback=move || view! { <ClosedNotice /> }
+                    >
+                        <EmptyRow order=entry_store_signal.with(|es| es.len()) />
+                    </Show>
                 }
                     .into_any()
             }}
@@ -120,7 +246,7 @@ pub fn Rows() -> impl IntoView {
 }
 
 #[component]
-pub fn Row(entry: LocalQueueEntry, order: usize) -> impl IntoView {
+pub fn Row(entry: LocalQueueEntry, order: Signal<usize>) -> impl IntoView {
     // "Deactivate" the row if there is no UUID on the frontend
     let is_inactive = Signal::derive(move || {
         matches!(entry.id.get(), LocalUuidState::Pending(_))
@@ -128,43 +254,117 @@ pub fn Row(entry: LocalQueueEntry, order: usize) -> impl IntoView {
     // Signal that gets the entry id and wraps in Some
     let id = Signal::derive(move || Some(entry.id.get()));
 
-    // TODO: Drag & Drop Reordering (😬)
+    let drag_state = expect_context::<RwSignal<DragState>>();
+    let row_mover = expect_context::<RowMover>();
+    let IsAdmin(is_admin) = expect_context::<IsAdmin>();
+    let stations = expect_context::<RwSignal<Vec<Station>>>();
+    let is_dragging = move || drag_state.get() == DragState::Row(entry);
+    // The station the row is playing on, if any.
+    let station_name = move || {
+        let LocalUuidState::Resolved(row_id) = entry.id.get() else {
+            return None;
+        };
+        stations.with(|stations| {
+            stations
+                .iter()
+                .find(|s| s.row_id == Some(row_id))
+                .map(|s| s.name.clone())
+        })
+    };
 
     view! {
-        <div class="rowContainer" class:inactive=is_inactive>
-            <div class="orderLabel">{order + 1}</div>
-            <PlayerToken
-                player_data=entry.left.into()
-                side=Side::Left
-                id=id
-                order
-                is_inactive=is_inactive
-            />
-            <PlayerToken
-                player_data=entry.right.into()
-                side=Side::Right
-                id=id
-                order
-                is_inactive=is_inactive
-            />
+        <div
+            class="rowContainer"
+            class:inactive=is_inactive
+            class:dragging=is_dragging
+            class:playing=move || station_name().is_some()
+            on:dragover=move |ev: DragEvent| {
+                // Accept drops only while a row is being dragged.
+                if matches!(drag_state.get_untracked(), DragState::Row(_)) {
+                    ev.prevent_default();
+                }
+            }
+            on:drop=move |ev: DragEvent| {
+                if let DragState::Row(dragged) = drag_state.get_untracked() {
+                    ev.prevent_default();
+                    drag_state.set(DragState::Idle);
+                    row_mover.move_to(dragged, order.get_untracked());
+                }
+            }
+        >
+            <Show
+                when=move || is_admin.get()
+                fallback=|| view! { <div class="drag-handle inactive"></div> }
+            >
+                <DragHandle entry order is_inactive />
+            </Show>
+            <div class="orderLabel">{move || order.get() + 1}</div>
+            {move || {
+                station_name().map(|name| view! { <div class="station-label">{name}</div> })
+            }}
+            <CallCountdown entry />
+            <StartEstimate entry />
+            <Team entry=Some(entry) side=Side::Left id order is_inactive />
+            <Team entry=Some(entry) side=Side::Right id order is_inactive />
+            <Show when=move || is_admin.get()>
+                <DeleteRowButton entry is_inactive />
+            </Show>
         </div>
     }
 }
 
+/// Shown instead of the empty row while the queue isn't taking players.
+#[component]
+fn ClosedNotice() -> impl IntoView {
+    let settings = expect_context::<RwSignal<QueueSettings>>();
+    view! {
+        <p class="closed-notice">
+            {move || match settings.get().status {
+                QueueStatus::Paused => "Joining is paused for now.",
+                _ => "This queue is closed.",
+            }}
+        </p>
+    }
+}
+
 #[component]
 pub fn EmptyRow(order: usize) -> impl IntoView {
+    let set_modal_state = expect_context::<WriteSignal<AddModalState>>();
+    let Singles(singles) = expect_context::<Singles>();
+    view! {
+        <Show
+            when=move || singles.get()
+            fallback=move || view! { <PairsEmptyRow order /> }
+        >
+            <div class="rowContainer">
+                <div class="drag-handle inactive"></div>
+                <div class="orderLabel">"-"</div>
+                <div class="player-token empty">
+                    <button on:click=move |_| {
+                        set_modal_state.set(AddModalState::Join { order })
+                    }>"Join"</button>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+/// The empty row at the back of a queue where players pick their side.
+#[component]
+fn PairsEmptyRow(order: usize) -> impl IntoView {
     view! {
         <div class="rowContainer">
+            <div class="drag-handle inactive"></div>
             <div class="orderLabel">"-"</div>
-            <PlayerToken
-                player_data=Signal::derive(move || None)
+            <Team
+                entry=None
                 side=Side::Left
                 id=Signal::derive(move || None)
                 order
                 is_inactive=Signal::derive(move || false)
             />
-            <PlayerToken
-                player_data=Signal::derive(move || None)
+            <Team
+                entry=None
                 side=Side::Right
                 id=Signal::derive(move || None)
                 order
@@ -174,35 +374,180 @@ pub fn EmptyRow(order: usize) -> impl IntoView {
     }
 }
 
-// TODO: When players are their own "type", we could implement IntoView on the
-// Player struct? Or have different methods like token_view() or info_view()
-// etc. Might not really make sense unless it's used in many places.
+/// One side of a row, with a token for each of its slots.
 #[component]
-pub fn PlayerToken(
-    player_data: Signal<Option<String>>,
-    order: usize,
+fn Team(
+    entry: Option<LocalQueueEntry>,
     side: Side,
     id: Signal<Option<LocalUuidState>>,
+    #[prop(into)] order: Signal<usize>,
     is_inactive: Signal<bool>,
 ) -> impl IntoView {
-    // TODO: Add remaining missing functionality
-    // * Delete Player
-    // * Drag and Drop Swap??
+    let TeamSize(team_size) = expect_context::<TeamSize>();
+    // Players keep their slots when the team size shrinks, so show those
+    // slots too until they're emptied.
+    let slot_count = Memo::new(move |_| {
+        let filled = entry.map_or(0, |entry| {
+            entry.players.with(|players| {
+                players
+                    .keys()
+                    .filter(|slot| slot.side == side)
+                    .map(|slot| slot.index + 1)
+                    .max()
+                    .unwrap_or(0)
+            })
+        });
+        team_size.get().max(filled)
+    });
 
+    view! {
+        <div class="team">
+            {move || {
+                (0..slot_count.get())
+                    .map(|index| {
+                        let slot = Slot::new(side, index);
+                        let player_data = Memo::new(move |_| {
+                            entry
+                                .and_then(|entry| {
+                                    entry.players.with(|players| players.get(&slot).cloned())
+                                })
+                        });
+                        view! {
+                            <PlayerToken
+                                player_data=player_data.into()
+                                player_slot=slot
+                                id
+                                order
+                                is_inactive
+                            />
+                        }
+                    })
+                    .collect_view()
+            }}
+        </div>
+    }
+}
+
+#[component]
+pub fn PlayerToken(
+    player_data: Signal<Option<Player>>,
+    #[prop(into)] order: Signal<usize>,
+    player_slot: Slot,
+    id: Signal<Option<LocalUuidState>>,
+    is_inactive: Signal<bool>,
+) -> impl IntoView {
     let set_modal_state = expect_context::<WriteSignal<AddModalState>>();
+    let entry_store_signal = expect_context::<RwSignal<EntryStore>>();
+    let drag_state = expect_context::<RwSignal<DragState>>();
+    let player_mover = expect_context::<PlayerMover>();
+    let IsAdmin(is_admin) = expect_context::<IsAdmin>();
+    let Singles(singles) = expect_context::<Singles>();
+    let settings = expect_context::<RwSignal<QueueSettings>>();
+    // Only admins can move players around, one move at a time.
+    let is_draggable = move || {
+        is_admin.get() && !is_inactive.get() && !player_mover.is_pending()
+    };
+
+    // The entry this token belongs to, if the server knows about it.
+    let entry = move || {
+        let id @ LocalUuidState::Resolved(_) = id.get_untracked()? else {
+            return None;
+        };
+        entry_store_signal.with_untracked(|es| {
+            es.iter()
+                .find(|e| e.id.get_untracked() == id)
+                .copied()
+        })
+    };
+    let on_dragstart = move |ev: DragEvent| {
+        if let Some(entry) = entry() {
+            start_drag(&ev);
+            drag_state.set(DragState::Player {
+                entry,
+                slot: player_slot,
+            });
+        }
+    };
+    let on_dragover = move |ev: DragEvent| {
+        // Accept drops only while a player is being dragged.
+        if matches!(
+            drag_state.get_untracked(),
+            DragState::Player { .. }
+        ) && entry().is_some()
+        {
+            ev.prevent_default();
+        }
+    };
+    let on_drop = move |ev: DragEvent| {
+        let (
+            DragState::Player {
+                entry: from,
+                slot: from_slot,
+            },
+            Some(to),
+        ) = (drag_state.get_untracked(), entry())
+        else {
+            return;
+        };
+        ev.prevent_default();
+        ev.stop_propagation();
+        drag_state.set(DragState::Idle);
+        player_mover.drop_player(PlayerMove {
+            from,
+            from_slot,
+            to,
+            to_slot: player_slot,
+        });
+    };
 
     view! {
         <Show
             when=move || { player_data.get().is_none() }
             fallback=move || {
                 view! {
-                    <div class="player-token" class:inactive=is_inactive>
-                        <p>{player_data.get().unwrap()}</p>
+                    <div
+                        class="player-token"
+                        class:inactive=is_inactive
+                        draggable=move || if is_draggable() { "true" } else { "false" }
+                        on:dragstart=on_dragstart
+                        on:dragend=move |_| drag_state.set(DragState::Idle)
+                        on:dragover=on_dragover
+                        on:drop=on_drop
+                    >
+                        {player_data
+                            .get()
+                            .map(|player| {
+                                view! {
+                                    <p>{player.display_name}</p>
+                                    {player
+                                        .tag
+                                        .map(|tag| view! { <span class="player-tag">{tag}</span> })}
+                                }
+                            })}
+                        <CheckInButton id player_data player_slot is_inactive />
+                        <Show when=move || is_admin.get()>
+                            <RemovePlayerButton id player_slot is_inactive />
+                        </Show>
                     </div>
                 }
             }
         >
-            <div class="player-token empty" class:inactive=is_inactive>
+            <div
+                class="player-token empty"
+                class:inactive=is_inactive
+                on:dragover=on_dragover
+                on:drop=on_drop
+            >
+                // Players can't pick a partner in singles queues, the next
+                // one to join takes the slot.
+                <Show
+                    when=move || !singles.get()
+                    fallback=|| view! { <p class="waiting">"Waiting for a partner"</p> }
+                >
+                <Show
+                    when=move || settings.with(|s| s.status == QueueStatus::Open)
+                    fallback=move || view! { <ClosedNotice /> }
+                >
                 <button disabled=is_inactive on:click=move |_| {
                     let row_id = match id.get() {
                         None | Some(LocalUuidState::Pending(_)) => None,
@@ -211,8 +556,8 @@ pub fn PlayerToken(
                     set_modal_state
                         .set(AddModalState::Open {
                             row_id,
-                            side,
-                            order,
+                            slot: player_slot,
+                            order: order.get(),
                         });
                 }>
                     <svg
@@ -230,18 +575,22 @@ pub fn PlayerToken(
                         />
                     </svg>
                 </button>
+                </Show>
+                </Show>
             </div>
         </Show>
     }
 }
 
 #[server]
-pub async fn get_queue_entries(
+pub async fn get_queue_state(
     queue_id: Uuid,
-) -> Result<Vec<QueueEntry>, ServerFnError> {
-    use crate::db::{api::get_queue_entries, DbPool};
-    let pool = use_context::<DbPool>().expect("there to be a `pool` provided.");
-    Ok(get_queue_entries(queue_id, pool)
+) -> Result<QueueState, ServerFnError> {
+    use crate::db::Store;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(store
+        .get_queue_state(queue_id)
         .await
         .inspect_err(|e| error!("Error getting queue entries: {}", e))?)
 }
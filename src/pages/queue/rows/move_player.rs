@@ -0,0 +1,231 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_row_admin;
+#[cfg(feature = "ssr")]
+use crate::db::{api::ApiError, Store};
+use crate::pages::queue::rows::versions::{QueueVersion, Versioned};
+use crate::pages::queue::rows::{EntryStore, LocalQueueEntry, LocalUuidState};
+use crate::queue::{Player, Slot};
+use leptos::logging::error;
+use leptos::prelude::*;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MoveOutcome {
+    Moved,
+    /// The target slot was filled before the move reached the server, so
+    /// nothing was moved.
+    Occupied,
+}
+
+/// A player move between two slots, possibly in the same row.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlayerMove {
+    pub from: LocalQueueEntry,
+    pub from_slot: Slot,
+    pub to: LocalQueueEntry,
+    pub to_slot: Slot,
+}
+
+/// Entry store state from before an optimistic move.
+#[derive(Clone)]
+struct Snapshot {
+    entries: EntryStore,
+    players: Vec<(LocalQueueEntry, BTreeMap<Slot, Player>)>,
+}
+
+impl Snapshot {
+    fn take(player_move: PlayerMove, entries: EntryStore) -> Self {
+        let players = [player_move.from, player_move.to]
+            .into_iter()
+            .map(|e| (e, e.players.get_untracked()))
+            .collect();
+        Snapshot { entries, players }
+    }
+
+    fn restore(self, entry_store_signal: RwSignal<EntryStore>) {
+        for (entry, players) in self.players {
+            entry.players.set(players);
+        }
+        entry_store_signal.set(self.entries);
+    }
+}
+
+/// Moves players between slots optimistically and syncs the move to the
+/// server. If the target slot turns out to be occupied, the move is rolled
+/// back and the user is offered a swap instead. Only one move is in flight
+/// at a time, so each rollback restores the state its own move replaced.
+#[derive(Copy, Clone)]
+pub struct PlayerMover {
+    move_player: ServerAction<MovePlayer>,
+    pending: StoredValue<Option<(PlayerMove, Snapshot)>>,
+    entry_store_signal: RwSignal<EntryStore>,
+    queue_version: QueueVersion,
+}
+
+impl PlayerMover {
+    pub fn new(
+        entry_store_signal: RwSignal<EntryStore>,
+        queue_version: QueueVersion,
+    ) -> Self {
+        let move_player = ServerAction::<MovePlayer>::new();
+        let value = move_player.value();
+        let pending = StoredValue::new(None::<(PlayerMove, Snapshot)>);
+        let mover = PlayerMover {
+            move_player,
+            pending,
+            entry_store_signal,
+            queue_version,
+        };
+
+        Effect::new(move |_| {
+            let Some(result) = value.get() else { return };
+            let Some((player_move, snapshot)) = pending
+                .try_update_value(Option::take)
+                .flatten()
+            else {
+                return;
+            };
+            match result {
+                Ok(Versioned::UpToDate(MoveOutcome::Moved)) => (),
+                Ok(Versioned::UpToDate(MoveOutcome::Occupied)) => {
+                    snapshot.restore(entry_store_signal);
+                    let offer = "Someone just joined that slot. Swap with \
+                                 them instead?";
+                    if confirm(offer) {
+                        mover.start_move(player_move, true);
+                    }
+                }
+                Ok(Versioned::Conflict(current)) => {
+                    queue_version.refresh(current)
+                }
+                Err(e) => {
+                    error!("Error moving player: {}", e);
+                    snapshot.restore(entry_store_signal);
+                }
+            }
+        });
+
+        mover
+    }
+
+    /// Whether a move is waiting on the server.
+    pub fn is_pending(&self) -> bool {
+        self.move_player.pending().get()
+    }
+
+    /// Moves a player, asking before swapping with whoever is in the target
+    /// slot.
+    pub fn drop_player(&self, player_move: PlayerMove) {
+        let PlayerMove { to, to_slot, .. } = player_move;
+        let swap = match to.player_untracked(to_slot) {
+            Some(player) => {
+                if !confirm(&format!("Swap places with {player}?")) {
+                    return;
+                }
+                true
+            }
+            None => false,
+        };
+        self.start_move(player_move, swap);
+    }
+
+    fn start_move(&self, player_move: PlayerMove, swap: bool) {
+        let PlayerMove {
+            from,
+            from_slot,
+            to,
+            to_slot,
+        } = player_move;
+        if from == to && from_slot == to_slot {
+            return;
+        }
+        if self
+            .move_player
+            .pending()
+            .get_untracked()
+        {
+            return;
+        }
+        // Rows the server doesn't know about yet can't take part in a move.
+        let (
+            LocalUuidState::Resolved(from_row),
+            LocalUuidState::Resolved(to_row),
+        ) = (from.id.get_untracked(), to.id.get_untracked())
+        else {
+            return;
+        };
+
+        let snapshot = Snapshot::take(
+            player_move,
+            self.entry_store_signal
+                .get_untracked(),
+        );
+        let moving = from.set_player(from_slot, None);
+        let displaced = to.set_player(to_slot, moving);
+        from.set_player(from_slot, displaced);
+        if from
+            .players
+            .with_untracked(BTreeMap::is_empty)
+        {
+            self.entry_store_signal
+                .update(|es| es.retain(|e| *e != from));
+        }
+
+        self.pending
+            .set_value(Some((player_move, snapshot)));
+        self.move_player
+            .dispatch(MovePlayer {
+                from_row,
+                from_slot,
+                to_row,
+                to_slot,
+                swap,
+                expected_version: self.queue_version.get_untracked(),
+            });
+    }
+}
+
+fn confirm(message: &str) -> bool {
+    window()
+        .confirm_with_message(message)
+        .unwrap_or(false)
+}
+
+#[server]
+pub async fn move_player(
+    from_row: Uuid,
+    from_slot: Slot,
+    to_row: Uuid,
+    to_slot: Slot,
+    swap: bool,
+    expected_version: i32,
+) -> Result<Versioned<MoveOutcome>, ServerFnError> {
+    require_row_admin(from_row).await?;
+    require_row_admin(to_row).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    match store
+        .move_player(
+            from_row,
+            from_slot,
+            to_row,
+            to_slot,
+            swap,
+            Some(expected_version),
+        )
+        .await
+    {
+        Ok(()) => Ok(Versioned::UpToDate(MoveOutcome::Moved)),
+        // Not an error for the client, which offers a swap instead.
+        Err(ApiError::Occupied { .. }) => {
+            Ok(Versioned::UpToDate(MoveOutcome::Occupied))
+        }
+        Err(ApiError::Conflict { current }) => Ok(Versioned::Conflict(current)),
+        Err(e) => {
+            error!("Error moving player: {}", e);
+            Err(e.into())
+        }
+    }
+}
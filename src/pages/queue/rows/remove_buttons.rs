@@ -0,0 +1,423 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_row_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::rows::versions::versioned;
+use crate::pages::queue::rows::versions::{QueueVersion, Versioned};
+use crate::pages::queue::rows::{EntryStore, LocalQueueEntry, LocalUuidState};
+use crate::queue::{Player, QueueEntry, Side, Slot};
+use leptos::logging::error;
+use leptos::prelude::*;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// What an optimistic removal changed, so it can be undone if the server
+/// rejects it.
+#[derive(Clone)]
+struct Removal {
+    index: usize,
+    entry: LocalQueueEntry,
+    cleared: Option<(Slot, Player)>,
+    row_deleted: bool,
+}
+
+impl Removal {
+    fn undo(self, entry_store_signal: RwSignal<EntryStore>) {
+        if let Some((slot, player)) = self.cleared {
+            self.entry
+                .set_player(slot, Some(player));
+        }
+        if self.row_deleted {
+            entry_store_signal
+                .update(|es| es.insert(self.index.min(es.len()), self.entry));
+        }
+    }
+}
+
+/// Rolls back the stored removal if the server function failed, or shows
+/// the queue as it is now if it had changed.
+fn settle_removal<T>(
+    result: Option<Result<Versioned<T>, ServerFnError>>,
+    removal: StoredValue<Option<Removal>>,
+    entry_store_signal: RwSignal<EntryStore>,
+    queue_version: QueueVersion,
+) {
+    let Some(result) = result else { return };
+    let removal = removal
+        .try_update_value(Option::take)
+        .flatten();
+    match result {
+        Ok(Versioned::UpToDate(_)) => (),
+        Ok(Versioned::Conflict(current)) => queue_version.refresh(current),
+        Err(e) => {
+            error!("Error removing from queue: {}", e);
+            if let Some(removal) = removal {
+                removal.undo(entry_store_signal);
+            }
+        }
+    }
+}
+
+/// Removes players and rows optimistically and syncs the removal to the
+/// server, putting them back if the server rejects it. Lives as long as the
+/// rows list, since the optimistic update unmounts the button that started
+/// it. Only one removal is in flight at a time, so each rollback undoes its
+/// own removal.
+#[derive(Copy, Clone)]
+pub struct Remover {
+    remove_player: ServerAction<RemovePlayer>,
+    delete_row: ServerAction<DeleteRow>,
+    player_removal: StoredValue<Option<Removal>>,
+    row_removal: StoredValue<Option<Removal>>,
+    entry_store_signal: RwSignal<EntryStore>,
+    queue_version: QueueVersion,
+}
+
+impl Remover {
+    pub fn new(
+        entry_store_signal: RwSignal<EntryStore>,
+        queue_version: QueueVersion,
+    ) -> Self {
+        let remove_player = ServerAction::<RemovePlayer>::new();
+        let delete_row = ServerAction::<DeleteRow>::new();
+        let player_removal = StoredValue::new(None::<Removal>);
+        let row_removal = StoredValue::new(None::<Removal>);
+        let remover = Remover {
+            remove_player,
+            delete_row,
+            player_removal,
+            row_removal,
+            entry_store_signal,
+            queue_version,
+        };
+
+        let removed = remove_player.value();
+        Effect::new(move |_| {
+            settle_removal(
+                removed.get(),
+                player_removal,
+                entry_store_signal,
+                queue_version,
+            )
+        });
+        let deleted = delete_row.value();
+        Effect::new(move |_| {
+            settle_removal(
+                deleted.get(),
+                row_removal,
+                entry_store_signal,
+                queue_version,
+            )
+        });
+
+        remover
+    }
+
+    /// Whether a removal is waiting on the server.
+    pub fn is_pending(&self) -> bool {
+        self.remove_player.pending().get() || self.delete_row.pending().get()
+    }
+
+    fn is_pending_untracked(&self) -> bool {
+        self.remove_player
+            .pending()
+            .get_untracked()
+            || self
+                .delete_row
+                .pending()
+                .get_untracked()
+    }
+
+    /// Clears a slot, dropping the row if it would be left empty.
+    pub fn remove_player(&self, id: LocalUuidState, slot: Slot) {
+        // Rows the server doesn't know about yet can't be changed.
+        let LocalUuidState::Resolved(row_id) = id else {
+            return;
+        };
+        if self.is_pending_untracked() {
+            return;
+        }
+        let Some(entry) = self
+            .entry_store_signal
+            .read_untracked()
+            .iter()
+            .find(|e| e.id.get_untracked() == id)
+            .copied()
+        else {
+            return;
+        };
+        let Some(removal) = self.take_player(entry, slot) else {
+            return;
+        };
+        self.player_removal
+            .set_value(Some(removal));
+        self.remove_player
+            .dispatch(RemovePlayer {
+                row_id,
+                side: slot.side,
+                slot_index: slot.index,
+                expected_version: self.queue_version.get_untracked(),
+            });
+    }
+
+    /// Deletes a whole row.
+    pub fn delete_row(&self, entry: LocalQueueEntry) {
+        let LocalUuidState::Resolved(row_id) = entry.id.get_untracked() else {
+            return;
+        };
+        if self.is_pending_untracked() {
+            return;
+        }
+        let Some(removal) = self.take_row(entry) else {
+            return;
+        };
+        self.row_removal
+            .set_value(Some(removal));
+        self.delete_row
+            .dispatch(DeleteRow {
+                row_id,
+                expected_version: self.queue_version.get_untracked(),
+            });
+    }
+
+    fn take_player(
+        &self,
+        entry: LocalQueueEntry,
+        slot: Slot,
+    ) -> Option<Removal> {
+        let index = self
+            .entry_store_signal
+            .read_untracked()
+            .iter()
+            .position(|e| *e == entry)?;
+        let cleared = entry
+            .set_player(slot, None)
+            .map(|player| (slot, player));
+        let row_deleted = entry
+            .players
+            .with_untracked(BTreeMap::is_empty);
+        if row_deleted {
+            self.entry_store_signal
+                .update(|es| {
+                    es.remove(index);
+                });
+        }
+        Some(Removal {
+            index,
+            entry,
+            cleared,
+            row_deleted,
+        })
+    }
+
+    fn take_row(&self, entry: LocalQueueEntry) -> Option<Removal> {
+        let index = self
+            .entry_store_signal
+            .read_untracked()
+            .iter()
+            .position(|e| *e == entry)?;
+        self.entry_store_signal
+            .update(|es| {
+                es.remove(index);
+            });
+        Some(Removal {
+            index,
+            entry,
+            cleared: None,
+            row_deleted: true,
+        })
+    }
+}
+
+#[component]
+pub fn RemovePlayerButton(
+    id: Signal<Option<LocalUuidState>>,
+    player_slot: Slot,
+    is_inactive: Signal<bool>,
+) -> impl IntoView {
+    let remover = expect_context::<Remover>();
+    let remove = move |_| {
+        if let Some(id) = id.get_untracked() {
+            remover.remove_player(id, player_slot);
+        }
+    };
+
+    view! {
+        <button
+            type="button"
+            class="remove-button"
+            title="Remove player"
+            disabled=move || is_inactive.get() || remover.is_pending()
+            on:click=remove
+        >
+            "×"
+        </button>
+    }
+}
+
+#[component]
+pub fn DeleteRowButton(
+    entry: LocalQueueEntry,
+    is_inactive: Signal<bool>,
+) -> impl IntoView {
+    let remover = expect_context::<Remover>();
+
+    view! {
+        <button
+            type="button"
+            class="remove-button"
+            title="Delete row"
+            disabled=move || is_inactive.get() || remover.is_pending()
+            on:click=move |_| remover.delete_row(entry)
+        >
+            "×"
+        </button>
+    }
+}
+
+#[server]
+pub async fn remove_player(
+    row_id: Uuid,
+    side: Side,
+    slot_index: i32,
+    expected_version: i32,
+) -> Result<Versioned<Option<QueueEntry>>, ServerFnError> {
+    require_row_admin(row_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .remove_player_from_row(
+                row_id,
+                Slot::new(side, slot_index),
+                Some(expected_version),
+            )
+            .await,
+    )
+    .inspect_err(|e| error!("Error removing player: {}", e))?)
+}
+
+#[server]
+pub async fn delete_row(
+    row_id: Uuid,
+    expected_version: i32,
+) -> Result<Versioned<()>, ServerFnError> {
+    require_row_admin(row_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .delete_row(row_id, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error deleting row: {}", e))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::SlotPlayer;
+
+    fn entry(names: &[&str]) -> LocalQueueEntry {
+        let players = names
+            .iter()
+            .zip([Side::Left, Side::Right])
+            .map(|(name, side)| SlotPlayer {
+                slot: Slot::new(side, 0),
+                player: Player {
+                    id: Uuid::new_v4(),
+                    display_name: name.to_string(),
+                    tag: None,
+                },
+                checked_in: false,
+            })
+            .collect();
+        QueueEntry {
+            id: Uuid::new_v4(),
+            queue_id: Uuid::nil(),
+            order: 0,
+            version: 0,
+            called_at: None,
+            estimated_start: None,
+            players,
+        }
+        .into()
+    }
+
+    fn names(entry_store_signal: RwSignal<EntryStore>) -> Vec<Vec<String>> {
+        entry_store_signal.with_untracked(|es| {
+            es.iter()
+                .map(|e| {
+                    e.players
+                        .get_untracked()
+                        .into_values()
+                        .map(|p| p.display_name)
+                        .collect()
+                })
+                .collect()
+        })
+    }
+
+    /// The optimistic removal unmounts the button that started it, so the
+    /// rollback has to work after the button's owner is gone.
+    #[test]
+    fn rejected_removals_are_undone_after_the_button_is_gone() {
+        let rows = Owner::new();
+        rows.with(|| {
+            let entry_store_signal =
+                RwSignal::new(vec![entry(&["ana", "ben"]), entry(&["cy"])]);
+            let queue_version = QueueVersion::new(
+                entry_store_signal,
+                RwSignal::new(Vec::new()),
+            );
+            let remover = Remover::new(entry_store_signal, queue_version);
+            let [pair, single] =
+                [0, 1].map(|i| entry_store_signal.get_untracked()[i]);
+
+            let button = rows.child();
+            button.with(|| {
+                let removal =
+                    remover.take_player(pair, Slot::new(Side::Right, 0));
+                remover
+                    .player_removal
+                    .set_value(removal);
+            });
+            button.cleanup();
+            assert_eq!(
+                names(entry_store_signal),
+                [vec!["ana"], vec!["cy"]]
+            );
+            settle_removal::<Option<QueueEntry>>(
+                Some(Err(ServerFnError::new("rejected"))),
+                remover.player_removal,
+                entry_store_signal,
+                queue_version,
+            );
+            assert_eq!(
+                names(entry_store_signal),
+                [vec!["ana", "ben"], vec!["cy"]]
+            );
+
+            let button = rows.child();
+            button.with(|| {
+                let removal = remover.take_row(single);
+                remover
+                    .row_removal
+                    .set_value(removal);
+            });
+            button.cleanup();
+            assert_eq!(names(entry_store_signal), [vec!["ana", "ben"]]);
+            settle_removal::<()>(
+                Some(Err(ServerFnError::new("rejected"))),
+                remover.row_removal,
+                entry_store_signal,
+                queue_version,
+            );
+            assert_eq!(
+                names(entry_store_signal),
+                [vec!["ana", "ben"], vec!["cy"]]
+            );
+        });
+    }
+}
@@ -0,0 +1,196 @@
+#[cfg(feature = "ssr")]
+use crate::admin::require_row_admin;
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::rows::versions::versioned;
+use crate::pages::queue::rows::versions::{QueueVersion, Versioned};
+use crate::pages::queue::rows::{EntryStore, LocalQueueEntry, LocalUuidState};
+use crate::queue::Slot;
+use leptos::ev::{DragEvent, KeyboardEvent};
+use leptos::logging::error;
+use leptos::prelude::*;
+use uuid::Uuid;
+
+/// What is currently being dragged around the queue, if anything.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DragState {
+    Idle,
+    Row(LocalQueueEntry),
+    Player { entry: LocalQueueEntry, slot: Slot },
+}
+
+/// Prepares a native drag so it behaves the same in every browser.
+pub fn start_drag(ev: &DragEvent) {
+    if let Some(data_transfer) = ev.data_transfer() {
+        // Firefox won't start a drag without some data attached.
+        let _ = data_transfer.set_data("text/plain", "");
+        data_transfer.set_effect_allowed("move");
+    }
+}
+
+/// Reorders the entry store optimistically and syncs the move to the server,
+/// restoring the previous order if the server rejects it. Only one move is in
+/// flight at a time, so the order restored is always the one it replaced.
+#[derive(Copy, Clone)]
+pub struct RowMover {
+    move_row: ServerAction<MoveRow>,
+    previous: StoredValue<Option<EntryStore>>,
+    entry_store_signal: RwSignal<EntryStore>,
+    queue_version: QueueVersion,
+}
+
+impl RowMover {
+    pub fn new(
+        entry_store_signal: RwSignal<EntryStore>,
+        queue_version: QueueVersion,
+    ) -> Self {
+        let move_row = ServerAction::<MoveRow>::new();
+        let value = move_row.value();
+        let previous = StoredValue::new(None::<EntryStore>);
+
+        Effect::new(move |_| {
+            let Some(result) = value.get() else { return };
+            let previous = previous
+                .try_update_value(Option::take)
+                .flatten();
+            match result {
+                Ok(Versioned::UpToDate(())) => (),
+                Ok(Versioned::Conflict(current)) => {
+                    queue_version.refresh(current)
+                }
+                Err(e) => {
+                    error!("Error moving row: {}", e);
+                    if let Some(previous) = previous {
+                        entry_store_signal.set(previous);
+                    }
+                }
+            }
+        });
+
+        RowMover {
+            move_row,
+            previous,
+            entry_store_signal,
+            queue_version,
+        }
+    }
+
+    /// Whether a move is waiting on the server.
+    pub fn is_pending(&self) -> bool {
+        self.move_row.pending().get()
+    }
+
+    pub fn move_to(&self, entry: LocalQueueEntry, new_position: usize) {
+        // Rows the server doesn't know about yet can't be moved.
+        let LocalUuidState::Resolved(row_id) = entry.id.get_untracked() else {
+            return;
+        };
+        if self
+            .move_row
+            .pending()
+            .get_untracked()
+        {
+            return;
+        }
+        let snapshot = self
+            .entry_store_signal
+            .get_untracked();
+        let Some(old_position) = snapshot
+            .iter()
+            .position(|e| *e == entry)
+        else {
+            return;
+        };
+        // Pending rows are always at the back, so keep resolved rows in front
+        // of them.
+        let last_resolved = snapshot
+            .iter()
+            .rposition(|e| {
+                matches!(e.id.get_untracked(), LocalUuidState::Resolved(_))
+            })
+            .unwrap_or_default();
+        let new_position = new_position.min(last_resolved);
+        if new_position == old_position {
+            return;
+        }
+
+        self.entry_store_signal
+            .update(|es| {
+                let moved = es.remove(old_position);
+                es.insert(new_position, moved);
+            });
+        self.previous
+            .set_value(Some(snapshot));
+        self.move_row.dispatch(MoveRow {
+            row_id,
+            new_position: new_position as i32,
+            expected_version: self.queue_version.get_untracked(),
+        });
+    }
+}
+
+/// Handle for reordering a row, either by dragging it onto another row or by
+/// focusing it and pressing the arrow keys.
+#[component]
+pub fn DragHandle(
+    entry: LocalQueueEntry,
+    order: Signal<usize>,
+    is_inactive: Signal<bool>,
+) -> impl IntoView {
+    let drag_state = expect_context::<RwSignal<DragState>>();
+    let row_mover = expect_context::<RowMover>();
+    let is_inactive =
+        Signal::derive(move || is_inactive.get() || row_mover.is_pending());
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        let new_position = match ev.key().as_str() {
+            "ArrowUp" => order
+                .get_untracked()
+                .checked_sub(1),
+            "ArrowDown" => Some(order.get_untracked() + 1),
+            _ => None,
+        };
+        if let Some(new_position) = new_position {
+            ev.prevent_default();
+            row_mover.move_to(entry, new_position);
+        }
+    };
+
+    view! {
+        <div
+            class="drag-handle"
+            class:inactive=is_inactive
+            role="button"
+            aria-label="Reorder row"
+            title="Drag or use the arrow keys to reorder"
+            tabindex=move || if is_inactive.get() { "-1" } else { "0" }
+            draggable=move || if is_inactive.get() { "false" } else { "true" }
+            on:dragstart=move |ev: DragEvent| {
+                start_drag(&ev);
+                drag_state.set(DragState::Row(entry));
+            }
+            on:dragend=move |_| drag_state.set(DragState::Idle)
+            on:keydown=on_keydown
+        >
+            "⠿"
+        </div>
+    }
+}
+
+#[server]
+pub async fn move_row(
+    row_id: Uuid,
+    new_position: i32,
+    expected_version: i32,
+) -> Result<Versioned<()>, ServerFnError> {
+    require_row_admin(row_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .move_row(row_id, new_position, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error moving row: {}", e))?)
+}
@@ -0,0 +1,31 @@
+use crate::pages::queue::rows::check_in::Clock;
+use crate::pages::queue::rows::LocalQueueEntry;
+use leptos::prelude::*;
+
+/// Roughly how long until a waiting row gets to play, shown next to its
+/// order.
+#[component]
+pub fn StartEstimate(entry: LocalQueueEntry) -> impl IntoView {
+    let clock = expect_context::<Clock>();
+    let estimate = move || {
+        let estimated_start = entry.estimated_start.get()?;
+        // Called rows show their check-in countdown instead.
+        if entry.called_at.get().is_some() {
+            return None;
+        }
+        let minutes = (estimated_start - clock.now()).num_minutes();
+        Some(if minutes > 0 {
+            format!("~{minutes} min")
+        } else {
+            "Soon".to_string()
+        })
+    };
+
+    view! {
+        <Show when=move || estimate().is_some()>
+            <div class="start-estimate" title="Estimated wait">
+                {estimate}
+            </div>
+        </Show>
+    }
+}
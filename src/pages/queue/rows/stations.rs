@@ -0,0 +1,269 @@
+#[cfg(feature = "ssr")]
+use crate::admin::{require_admin, require_station_admin};
+#[cfg(feature = "ssr")]
+use crate::db::Store;
+use crate::pages::queue::rows::check_in::Clock;
+#[cfg(feature = "ssr")]
+use crate::pages::queue::rows::versions::versioned;
+use crate::pages::queue::rows::versions::{QueueVersion, Versioned};
+use crate::pages::queue::rows::{EntryStore, LocalUuidState};
+use crate::pages::queue::IsAdmin;
+use crate::queue::{QueueEntry, QueueInfo, Side, Station};
+use leptos::logging::error;
+use leptos::prelude::*;
+use uuid::Uuid;
+
+/// What's playing on each of the queue's stations, above the rows waiting
+/// for them. Admins finish matches and set up stations here.
+#[component]
+pub fn NowPlaying() -> impl IntoView {
+    let stations = expect_context::<RwSignal<Vec<Station>>>();
+    let IsAdmin(is_admin) = expect_context::<IsAdmin>();
+
+    view! {
+        <Show when=move || is_admin.get() || !stations.with(Vec::is_empty)>
+            <section class="now-playing">
+                <h2>"Now playing"</h2>
+                <div class="stations">
+                    <For
+                        each=move || stations.get()
+                        key=|s| (s.id, s.row_id, s.started_at)
+                        children=move |station| view! { <StationCard station /> }
+                    />
+                </div>
+                <Show when=move || is_admin.get()>
+                    <AddStationForm />
+                </Show>
+            </section>
+        </Show>
+    }
+}
+
+#[component]
+fn StationCard(station: Station) -> impl IntoView {
+    let entry_store_signal = expect_context::<RwSignal<EntryStore>>();
+    let queue_version = expect_context::<QueueVersion>();
+    let IsAdmin(is_admin) = expect_context::<IsAdmin>();
+    let clock = expect_context::<Clock>();
+    let free_station = ServerAction::<FreeStation>::new();
+    let assign_station = ServerAction::<AssignStation>::new();
+    let delete_station = ServerAction::<DeleteStation>::new();
+    let free_value = free_station.value();
+    let assign_value = assign_station.value();
+    let delete_value = delete_station.value();
+
+    Effect::new(move |_| match free_value.get() {
+        Some(Ok(Versioned::Conflict(current))) => {
+            queue_version.refresh(current)
+        }
+        Some(Err(e)) => error!("Error finishing match: {}", e),
+        _ => (),
+    });
+    Effect::new(move |_| match assign_value.get() {
+        Some(Ok(Versioned::Conflict(current))) => {
+            queue_version.refresh(current)
+        }
+        Some(Err(e)) => error!("Error starting match: {}", e),
+        _ => (),
+    });
+    Effect::new(move |_| match delete_value.get() {
+        Some(Ok(Versioned::Conflict(current))) => {
+            queue_version.refresh(current)
+        }
+        Some(Err(e)) => error!("Error removing station: {}", e),
+        _ => (),
+    });
+
+    let entry = Memo::new(move |_| {
+        let row_id = LocalUuidState::Resolved(station.row_id?);
+        entry_store_signal.with(|es| {
+            es.iter()
+                .find(|e| e.id.get() == row_id)
+                .copied()
+        })
+    });
+    let teams = move || {
+        let entry = entry.get()?;
+        Some((
+            entry.team_names(Side::Left),
+            entry.team_names(Side::Right),
+        ))
+    };
+    let elapsed = move || {
+        let started_at = station.started_at?;
+        let seconds = (clock.now() - started_at)
+            .num_seconds()
+            .max(0);
+        Some(format!("{}:{:02}", seconds / 60, seconds % 60))
+    };
+    let station_id = station.id;
+    let playing = station.row_id.is_some();
+
+    view! {
+        <div class="station" class:free=!playing>
+            <h3>{station.name}</h3>
+            {move || match teams() {
+                Some((left, right)) => {
+                    view! {
+                        <p class="matchup">
+                            {left.unwrap_or_default()}" vs "{right.unwrap_or_default()}
+                        </p>
+                        <p class="elapsed">{elapsed}</p>
+                    }
+                        .into_any()
+                }
+                None => view! { <p class="matchup">"Free"</p> }.into_any(),
+            }}
+            <Show when=move || is_admin.get()>
+                {
+                    if playing {
+                        view! {
+                            <ActionForm action=free_station>
+                                <input type="hidden" name="station_id" value=station_id.to_string() />
+                                <input
+                                    type="hidden"
+                                    name="expected_version"
+                                    value=move || queue_version.get()
+                                />
+                                {move || {
+                                    teams()
+                                        .and_then(|(left, right)| Some((left?, right?)))
+                                        .map(|(left, right)| {
+                                            view! {
+                                                <button type="submit" name="winner" value="Left">
+                                                    {left}" won"
+                                                </button>
+                                                <button type="submit" name="winner" value="Right">
+                                                    {right}" won"
+                                                </button>
+                                            }
+                                        })
+                                }}
+                                <button type="submit">"Match Finished"</button>
+                            </ActionForm>
+                        }
+                            .into_any()
+                    } else {
+                        view! {
+                            <ActionForm action=assign_station>
+                                <input type="hidden" name="station_id" value=station_id.to_string() />
+                                <input
+                                    type="hidden"
+                                    name="expected_version"
+                                    value=move || queue_version.get()
+                                />
+                                <button type="submit">"Start next match"</button>
+                            </ActionForm>
+                        }
+                            .into_any()
+                    }
+                }
+                <ActionForm action=delete_station>
+                    <input type="hidden" name="station_id" value=station_id.to_string() />
+                    <input
+                        type="hidden"
+                        name="expected_version"
+                        value=move || queue_version.get()
+                    />
+                    <button type="submit" class="remove-station">"Remove station"</button>
+                </ActionForm>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn AddStationForm() -> impl IntoView {
+    let queue_info = expect_context::<QueueInfo>();
+    let queue_version = expect_context::<QueueVersion>();
+    let add_station = ServerAction::<AddStation>::new();
+    let value = add_station.value();
+
+    Effect::new(move |_| match value.get() {
+        Some(Ok(Versioned::Conflict(current))) => {
+            queue_version.refresh(current)
+        }
+        Some(Err(e)) => error!("Error adding station: {}", e),
+        _ => (),
+    });
+
+    view! {
+        <ActionForm action=add_station attr:class="add-station">
+            <input type="hidden" name="queue_id" value=queue_info.id.to_string() />
+            <input type="hidden" name="expected_version" value=move || queue_version.get() />
+            <input type="text" name="name" placeholder="Station name" />
+            <button type="submit">"Add station"</button>
+        </ActionForm>
+    }
+}
+
+#[server]
+/// Adds a station to a queue, named after how many it has if `name` is
+/// blank.
+pub async fn add_station(
+    queue_id: Uuid,
+    name: String,
+    expected_version: i32,
+) -> Result<Versioned<Station>, ServerFnError> {
+    require_admin(queue_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .add_station(queue_id, name, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error adding station: {}", e))?)
+}
+
+#[server]
+/// Removes a station, leaving any row playing there waiting in its place.
+pub async fn delete_station(
+    station_id: Uuid,
+    expected_version: i32,
+) -> Result<Versioned<()>, ServerFnError> {
+    require_station_admin(station_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .delete_station(station_id, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error removing station: {}", e))?)
+}
+
+#[server]
+/// Starts the first waiting row on a free station.
+pub async fn assign_station(
+    station_id: Uuid,
+    expected_version: i32,
+) -> Result<Versioned<Option<QueueEntry>>, ServerFnError> {
+    require_station_admin(station_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .assign_station(station_id, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error starting match: {}", e))?)
+}
+
+#[server]
+/// Finishes the match on a station and starts the next one there.
+pub async fn free_station(
+    station_id: Uuid,
+    expected_version: i32,
+    winner: Option<Side>,
+) -> Result<Versioned<Option<QueueEntry>>, ServerFnError> {
+    require_station_admin(station_id).await?;
+    let store =
+        use_context::<Store>().expect("there to be a `store` provided.");
+    Ok(versioned(
+        store
+            .free_station(station_id, winner, Some(expected_version))
+            .await,
+    )
+    .inspect_err(|e| error!("Error finishing match: {}", e))?)
+}
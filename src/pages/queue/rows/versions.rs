@@ -0,0 +1,100 @@
+#[cfg(feature = "ssr")]
+use crate::db::api::ApiError;
+use crate::pages::queue::rows::live_updates::merge_entries;
+use crate::pages::queue::rows::EntryStore;
+use crate::queue::{QueueState, Station};
+use leptos::prelude::*;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long the notice stays up after a refresh.
+const NOTICE_DURATION: Duration = Duration::from_secs(4);
+
+/// What came of a change made at a queue version.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Versioned<T> {
+    /// The queue was still at that version, so the change was made.
+    UpToDate(T),
+    /// The queue changed in the meantime, so nothing was changed. Not an
+    /// error for the client, which shows the current state instead.
+    Conflict(QueueState),
+}
+
+#[cfg(feature = "ssr")]
+pub fn versioned<T>(
+    result: Result<T, ApiError>,
+) -> Result<Versioned<T>, ApiError> {
+    match result {
+        Ok(value) => Ok(Versioned::UpToDate(value)),
+        Err(ApiError::Conflict { current }) => Ok(Versioned::Conflict(current)),
+        Err(e) => Err(e),
+    }
+}
+
+/// The queue version the entry store matches, which changes are made at.
+#[derive(Copy, Clone)]
+pub struct QueueVersion {
+    version: RwSignal<i32>,
+    refreshed: RwSignal<bool>,
+    entry_store_signal: RwSignal<EntryStore>,
+    stations: RwSignal<Vec<Station>>,
+}
+
+impl QueueVersion {
+    pub fn new(
+        entry_store_signal: RwSignal<EntryStore>,
+        stations: RwSignal<Vec<Station>>,
+    ) -> Self {
+        QueueVersion {
+            version: RwSignal::new(0),
+            refreshed: RwSignal::new(false),
+            entry_store_signal,
+            stations,
+        }
+    }
+
+    pub fn get(&self) -> i32 {
+        self.version.get()
+    }
+
+    pub fn get_untracked(&self) -> i32 {
+        self.version.get_untracked()
+    }
+
+    /// Brings the entry store and stations up to date with the server,
+    /// ignoring states older than the one they already show.
+    pub fn sync(&self, state: QueueState) {
+        if state.version < self.get_untracked() {
+            return;
+        }
+        self.version.set(state.version);
+        merge_entries(self.entry_store_signal, state.entries);
+        if self
+            .stations
+            .with_untracked(|stations| *stations != state.stations)
+        {
+            self.stations.set(state.stations);
+        }
+    }
+
+    /// Replaces whatever the user was looking at with the server's state
+    /// after one of their changes was refused, and tells them why.
+    pub fn refresh(&self, current: QueueState) {
+        self.sync(current);
+        self.refreshed.set(true);
+        let refreshed = self.refreshed;
+        set_timeout(move || refreshed.set(false), NOTICE_DURATION);
+    }
+}
+
+#[component]
+pub fn RefreshedNotice() -> impl IntoView {
+    let queue_version = expect_context::<QueueVersion>();
+    view! {
+        <Show when=move || queue_version.refreshed.get()>
+            <p class="notice" role="status">
+                "Queue changed, refreshed"
+            </p>
+        </Show>
+    }
+}
@@ -1,46 +1,241 @@
+use chrono::{DateTime, Utc};
 use leptos::server_fn::serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use uuid::Uuid;
 
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct QueueInfo {
     pub id: Uuid,
     pub url_name: String,
     pub display_name: String,
+    pub mode: QueueMode,
+    /// Matches in a row a player can stay on for before going to the back,
+    /// in the modes where someone stays. No limit if `None`.
+    pub max_streak: Option<i32>,
+    /// Whether players join alone and get paired up automatically, instead
+    /// of picking a row and side.
+    pub singles: bool,
+    /// How many players make up each side of a row, e.g. 2 for doubles.
+    pub team_size: i32,
+    /// Seconds the players of the front row have to check in once it's
+    /// called. Rows are never called if `None`.
+    pub no_show_timeout: Option<i32>,
+    /// Places a row moves back when its players don't all check in in time.
+    /// The row leaves the queue instead if `None`.
+    pub no_show_move_back: Option<i32>,
+    /// Who may join, and how much of the queue they may take up.
+    pub settings: QueueSettings,
 }
 
+/// Limits on joining a queue.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(
+    Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub struct QueueSettings {
+    /// The most rows the queue holds at once. No limit if `None`.
+    pub max_rows: Option<i32>,
+    /// The most rows of the queue one player can be in at once. No limit if
+    /// `None`.
+    pub max_entries_per_player: Option<i32>,
+    pub status: QueueStatus,
+    /// Whether a player already waiting in the queue is turned away instead
+    /// of joining another row.
+    pub unique_players: bool,
+}
+
+/// Whether a queue is taking new players. Rows already in it keep playing
+/// either way.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(
+    Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum QueueStatus {
+    /// Anyone can join.
+    #[default]
+    Open,
+    /// Nobody can join for now, e.g. while a tournament is on.
+    Paused,
+    /// Nobody can join until an admin opens the queue again.
+    Closed,
+}
+
+impl Display for QueueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueStatus::Open => write!(f, "Open"),
+            QueueStatus::Paused => write!(f, "Paused"),
+            QueueStatus::Closed => write!(f, "Closed"),
+        }
+    }
+}
+
+/// The most players a side of a row can hold.
+pub const MAX_TEAM_SIZE: i32 = 8;
+
+/// How the front row changes when its match ends.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(
+    Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum QueueMode {
+    /// Both players leave and the next row plays.
+    #[default]
+    Pairs,
+    /// The winner stays on and plays the next waiting player.
+    WinnerStays,
+    /// The loser stays on and plays the next waiting player.
+    LoserStays,
+}
+
+impl QueueMode {
+    /// The side of the front row that stays on after `winner` wins, if
+    /// anyone does. Without a winner everyone leaves, like in
+    /// [`QueueMode::Pairs`].
+    pub fn staying_side(self, winner: Option<Side>) -> Option<Side> {
+        match self {
+            QueueMode::Pairs => None,
+            QueueMode::WinnerStays => winner,
+            QueueMode::LoserStays => winner.map(Side::other),
+        }
+    }
+}
+
+impl Display for QueueMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueMode::Pairs => write!(f, "Pairs"),
+            QueueMode::WinnerStays => write!(f, "WinnerStays"),
+            QueueMode::LoserStays => write!(f, "LoserStays"),
+        }
+    }
+}
+
+/// A newly created queue along with the secret token needed to manage it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreatedQueue {
+    pub info: QueueInfo,
+    pub admin_token: String,
+}
+
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QueueEntry {
     pub id: Uuid,
     pub queue_id: Uuid,
     pub order: i32,
-    pub players: EntryPlayers,
+    /// Goes up every time the row changes, including when it moves.
+    pub version: i32,
+    /// When the row was called to play, if it's at the front of a queue with
+    /// a no-show timeout.
+    #[cfg_attr(feature = "ssr", schema(value_type = Option<String>, format = DateTime))]
+    pub called_at: Option<DateTime<Utc>>,
+    /// When the row should start playing, going by how long the queue's
+    /// recent matches took. `None` once it's playing, or before any match
+    /// has finished. See [`crate::estimate`].
+    #[cfg_attr(feature = "ssr", schema(value_type = Option<String>, format = DateTime))]
+    pub estimated_start: Option<DateTime<Utc>>,
+    /// The filled slots of the row, left side first. Never empty.
+    pub players: Vec<SlotPlayer>,
 }
 
+impl QueueEntry {
+    /// The player in the given slot, if it's filled.
+    pub fn player(&self, slot: Slot) -> Option<&Player> {
+        self.players
+            .iter()
+            .find(|p| p.slot == slot)
+            .map(|p| &p.player)
+    }
+
+    /// The players on one side of the row, in slot order.
+    pub fn team(&self, side: Side) -> impl Iterator<Item = &Player> {
+        self.players
+            .iter()
+            .filter(move |p| p.slot.side == side)
+            .map(|p| &p.player)
+    }
+}
+
+/// A player and the slot they fill in a row.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SlotPlayer {
+    pub slot: Slot,
+    pub player: Player,
+    /// Whether the player checked in since the row was called.
+    pub checked_in: bool,
+}
+
+/// Everyone waiting in a queue, as of the given queue version.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
-// TODO: Update this when players are added to database.
-pub enum EntryPlayers {
-    LeftOnly(String),
-    RightOnly(String),
-    Both(String, String),
+pub struct QueueState {
+    /// Goes up every time anything in the queue changes.
+    pub version: i32,
+    pub entries: Vec<QueueEntry>,
+    /// The setups the queue plays on at once, oldest first. Empty if the
+    /// queue plays one match at a time at its front row.
+    pub stations: Vec<Station>,
 }
 
-impl EntryPlayers {
-    pub fn players_tuple(self) -> (Option<String>, Option<String>) {
-        match self {
-            EntryPlayers::LeftOnly(left) => (Some(left), None),
-            EntryPlayers::RightOnly(right) => (None, Some(right)),
-            EntryPlayers::Both(left, right) => (Some(left), Some(right)),
-        }
+/// A setup a queue plays on alongside its others.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Station {
+    pub id: Uuid,
+    pub name: String,
+    /// The row playing here, which is still in the queue's entries. The
+    /// station is free if `None`.
+    pub row_id: Option<Uuid>,
+    /// When the row started playing here.
+    #[cfg_attr(feature = "ssr", schema(value_type = Option<String>, format = DateTime))]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Player {
+    pub id: Uuid,
+    pub display_name: String,
+    pub tag: Option<String>,
+}
+
+impl Display for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_name)
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
 pub enum Side {
     Left,
     Right,
 }
 
+impl Side {
+    /// The opposite side of a row.
+    pub fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
 impl Display for Side {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -50,6 +245,54 @@ impl Display for Side {
     }
 }
 
+/// A place for one player in a row: a side, and a position on that side
+/// counting from 0. Slots sort left side first.
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub struct Slot {
+    pub side: Side,
+    pub index: i32,
+}
+
+impl Slot {
+    pub fn new(side: Side, index: i32) -> Self {
+        Slot { side, index }
+    }
+
+    /// Every slot of a row whose sides hold `team_size` players.
+    pub fn all(team_size: i32) -> impl Iterator<Item = Slot> {
+        [Side::Left, Side::Right]
+            .into_iter()
+            .flat_map(move |side| {
+                (0..team_size).map(move |index| Slot::new(side, index))
+            })
+    }
+}
+
+/// The first slot of a side, the only one when players play alone.
+impl From<Side> for Slot {
+    fn from(side: Side) -> Self {
+        Slot::new(side, 0)
+    }
+}
+
+impl Display for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.side, self.index + 1)
+    }
+}
+
 #[cfg(feature = "ssr")]
 use crate::db;
 #[cfg(feature = "ssr")]
@@ -69,37 +312,91 @@ pub enum ConversionError {
 #[cfg(feature = "ssr")]
 impl From<db::Queue> for QueueInfo {
     fn from(queue: db::Queue) -> Self {
+        let mode = match queue.mode.as_str() {
+            "WinnerStays" => QueueMode::WinnerStays,
+            "LoserStays" => QueueMode::LoserStays,
+            _ => QueueMode::Pairs,
+        };
+        let status = match queue.status.as_str() {
+            "Paused" => QueueStatus::Paused,
+            "Closed" => QueueStatus::Closed,
+            _ => QueueStatus::Open,
+        };
         QueueInfo {
             id: queue.id,
             url_name: queue.url_name,
             display_name: queue.display_name,
+            mode,
+            max_streak: queue.max_streak,
+            singles: queue.singles,
+            team_size: queue.team_size,
+            no_show_timeout: queue.no_show_timeout,
+            no_show_move_back: queue.no_show_move_back,
+            settings: QueueSettings {
+                max_rows: queue.max_rows,
+                max_entries_per_player: queue.max_entries_per_player,
+                status,
+                unique_players: queue.unique_players,
+            },
         }
     }
 }
 
 #[cfg(feature = "ssr")]
-impl TryFrom<db::QueueRow> for QueueEntry {
+impl From<db::Station> for Station {
+    fn from(station: db::Station) -> Self {
+        Station {
+            id: station.id,
+            name: station.name,
+            row_id: station.row_id,
+            started_at: station.started_at,
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<db::Player> for Player {
+    fn from(player: db::Player) -> Self {
+        Player {
+            id: player.id,
+            display_name: player.display_name,
+            tag: player.tag,
+        }
+    }
+}
+
+// Rows only hold player ids, so the players are loaded alongside them.
+#[cfg(feature = "ssr")]
+impl TryFrom<(db::QueueRow, Vec<(Slot, db::Player)>)> for QueueEntry {
     type Error = ConversionError;
 
-    fn try_from(db_row: db::QueueRow) -> Result<Self, ConversionError> {
-        let player_state =
-            match (db_row.left_player_name, db_row.right_player_name) {
-                (Some(left), Some(right)) => {
-                    Ok(EntryPlayers::Both(left, right))
-                }
-                (Some(left), None) => Ok(EntryPlayers::LeftOnly(left)),
-                (None, Some(right)) => Ok(EntryPlayers::RightOnly(right)),
-                (None, None) => Err(ConversionError::EmptyRow {
-                    row_id: db_row.id,
-                    queue_id: db_row.queue_id,
-                    order: db_row.queue_order,
-                }),
-            }?;
+    fn try_from(
+        (db_row, players): (db::QueueRow, Vec<(Slot, db::Player)>),
+    ) -> Result<Self, ConversionError> {
+        if players.is_empty() {
+            return Err(ConversionError::EmptyRow {
+                row_id: db_row.id,
+                queue_id: db_row.queue_id,
+                order: db_row.queue_order,
+            });
+        }
         Ok(QueueEntry {
             id: db_row.id,
             queue_id: db_row.queue_id,
             order: db_row.queue_order,
-            players: player_state,
+            version: db_row.version,
+            called_at: db_row.called_at,
+            estimated_start: None,
+            players: players
+                .into_iter()
+                .map(|(slot, player)| SlotPlayer {
+                    slot,
+                    checked_in: db_row
+                        .checked_in
+                        .contains(&player.id),
+                    player: player.into(),
+                })
+                .collect(),
         })
     }
 }
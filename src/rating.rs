@@ -0,0 +1,118 @@
+#[cfg(feature = "ssr")]
+use crate::db;
+use crate::queue::Player;
+use chrono::{DateTime, Utc};
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Elo rating of a player who hasn't played a rated match in a queue yet.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// The most a rating can move in one match.
+pub const K_FACTOR: f64 = 32.0;
+
+/// The chance a side rated `rating` beats a side rated `opponent`.
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+/// How one player's rating moves in a match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RatingUpdate {
+    pub player_id: Uuid,
+    pub won: bool,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// Rates a match `winners` won against `losers`, given everyone's current
+/// `ratings`. Players missing from `ratings` start at [`INITIAL_RATING`].
+///
+/// Each team is rated as the average of its players, and everyone on a team
+/// gains or loses what the team does. Nobody is rated if either team is
+/// empty, and anyone who played on both sides is left out.
+pub fn rate_match(
+    winners: &[Uuid],
+    losers: &[Uuid],
+    ratings: &HashMap<Uuid, f64>,
+) -> Vec<RatingUpdate> {
+    let on_both_sides = |id: &Uuid| winners.contains(id) && losers.contains(id);
+    let (winners, losers): (Vec<Uuid>, Vec<Uuid>) = (
+        winners
+            .iter()
+            .filter(|id| !on_both_sides(id))
+            .copied()
+            .collect(),
+        losers
+            .iter()
+            .filter(|id| !on_both_sides(id))
+            .copied()
+            .collect(),
+    );
+    if winners.is_empty() || losers.is_empty() {
+        return Vec::new();
+    }
+    let rating = |id: &Uuid| {
+        ratings
+            .get(id)
+            .copied()
+            .unwrap_or(INITIAL_RATING)
+    };
+    let average = |team: &[Uuid]| {
+        team.iter()
+            .map(rating)
+            .sum::<f64>()
+            / team.len() as f64
+    };
+    let gain =
+        K_FACTOR * (1.0 - expected_score(average(&winners), average(&losers)));
+    let update = |won: bool, change: f64| {
+        move |id: &Uuid| RatingUpdate {
+            player_id: *id,
+            won,
+            before: rating(id),
+            after: rating(id) + change,
+        }
+    };
+    winners
+        .iter()
+        .map(update(true, gain))
+        .chain(
+            losers
+                .iter()
+                .map(update(false, -gain)),
+        )
+        .collect()
+}
+
+/// A player's place on a queue's leaderboard.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Standing {
+    pub player: Player,
+    pub rating: f64,
+    /// Rated matches played, which are the ones with a winner.
+    pub games: i32,
+    pub wins: i32,
+}
+
+/// A player's rating right after a match ended.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RatingPoint {
+    pub player_id: Uuid,
+    pub ended_at: DateTime<Utc>,
+    pub rating: f64,
+}
+
+// Ratings only hold player ids, so the players are loaded alongside them.
+#[cfg(feature = "ssr")]
+impl From<(db::Rating, db::Player)> for Standing {
+    fn from((rating, player): (db::Rating, db::Player)) -> Self {
+        Standing {
+            player: player.into(),
+            rating: rating.rating,
+            games: rating.games,
+            wins: rating.wins,
+        }
+    }
+}
@@ -0,0 +1,805 @@
+//! JSON REST API for scripts and overlays that can't call server functions.
+//!
+//! Queues are addressed by their url_name. Reading and adding players is
+//! open to everyone, while removing players, advancing and reordering need
+//! the queue's admin token in the `x-admin-token` header, an admin cookie or
+//! a session for the user who created the queue.
+//!
+//! Every change bumps the queue's version, which `GET /queues/{url_name}`
+//! returns in its body and `ETag` header. Send it back in `If-Match` when
+//! removing, deleting, moving or advancing, and the change is refused with a
+//! 412 and the queue's current state if anything changed in between.
+//!
+//! Errors, including requests that can't be read, come back as an
+//! [`ErrorBody`].
+
+use crate::admin::{admin_token, ADMIN_TOKEN_HEADER};
+use crate::auth::CurrentUser;
+use crate::db::api::ApiError;
+use crate::db::Store;
+use crate::queue::{Player, QueueEntry, QueueInfo, Side, Slot, Station};
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{
+    FromRequest, FromRequestParts, OptionalFromRequest, Path, Query, Request,
+    State,
+};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Extension, Json, Router};
+use leptos::logging::error;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_scalar::{Scalar, Servable};
+use uuid::Uuid;
+
+/// Builds the `/api/v1` routes.
+pub fn router<S>(store: Store) -> Router<S> {
+    Router::new()
+        .route("/queues", get(list_queues))
+        .route("/queues/{url_name}", get(get_queue))
+        .route("/queues/{url_name}/players", post(add_player))
+        .route("/queues/{url_name}/advance", post(advance))
+        .route(
+            "/queues/{url_name}/rows/{row_id}",
+            delete(delete_row),
+        )
+        .route(
+            "/queues/{url_name}/rows/{row_id}/move",
+            post(move_row),
+        )
+        .route(
+            "/queues/{url_name}/rows/{row_id}/players/{side}",
+            delete(remove_player),
+        )
+        .with_state(store)
+}
+
+/// OpenAPI document for the `/api/v1` routes.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "itq", description = "Queues of players waiting to play."),
+    paths(
+        list_queues,
+        get_queue,
+        add_player,
+        remove_player,
+        delete_row,
+        move_row,
+        advance
+    ),
+    modifiers(&AdminToken),
+    tags((name = "queues"))
+)]
+pub struct ApiDoc;
+
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi
+            .components
+            .get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(
+                ADMIN_TOKEN_HEADER,
+            ))),
+        );
+    }
+}
+
+/// Serves the OpenAPI document at `/api/openapi.json` and a page to browse
+/// it at `/api/docs`.
+pub fn docs_router<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route(
+            "/api/openapi.json",
+            get(|| async { Json(ApiDoc::openapi()) }),
+        )
+        .merge(Scalar::with_url("/api/docs", ApiDoc::openapi()))
+}
+
+/// An [`ApiError`] sent as a JSON body with a matching status code.
+pub struct RestError(ApiError);
+
+impl From<ApiError> for RestError {
+    fn from(e: ApiError) -> Self {
+        RestError(e)
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        let message = self.0.to_string();
+        let (status, code, details) = match self.0 {
+            ApiError::NotFound => {
+                (StatusCode::NOT_FOUND, "not_found", json!({}))
+            }
+            ApiError::Occupied {
+                row_id,
+                order,
+                slot,
+            } => (
+                StatusCode::CONFLICT,
+                "occupied",
+                json!({
+                    "row_id": row_id,
+                    "order": order,
+                    "side": slot.side,
+                    "slot_index": slot.index,
+                }),
+            ),
+            ApiError::Conflict { current } => (
+                StatusCode::PRECONDITION_FAILED,
+                "conflict",
+                json!({ "current": current }),
+            ),
+            ApiError::QueueBusy { queue_id } => (
+                StatusCode::CONFLICT,
+                "queue_busy",
+                json!({ "queue_id": queue_id }),
+            ),
+            ApiError::EmptySlot { row_id, slot } => (
+                StatusCode::CONFLICT,
+                "empty_slot",
+                json!({
+                    "row_id": row_id,
+                    "side": slot.side,
+                    "slot_index": slot.index,
+                }),
+            ),
+            ApiError::NotCalled { row_id } => (
+                StatusCode::CONFLICT,
+                "not_called",
+                json!({ "row_id": row_id }),
+            ),
+            ApiError::StationRequired { queue_id } => (
+                StatusCode::CONFLICT,
+                "station_required",
+                json!({ "queue_id": queue_id }),
+            ),
+            ApiError::DifferentQueues => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "different_queues",
+                json!({}),
+            ),
+            ApiError::InvalidSlot { slot, team_size } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_slot",
+                json!({
+                    "side": slot.side,
+                    "slot_index": slot.index,
+                    "team_size": team_size,
+                }),
+            ),
+            ApiError::InvalidOrder { expected, got } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_order",
+                json!({ "expected": expected, "got": got }),
+            ),
+            ApiError::BlankPlayerName => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "blank_player_name",
+                json!({}),
+            ),
+            ApiError::QueueFull { max_rows } => (
+                StatusCode::CONFLICT,
+                "queue_full",
+                json!({ "max_rows": max_rows }),
+            ),
+            ApiError::PlayerLimit { max_entries } => (
+                StatusCode::CONFLICT,
+                "player_limit",
+                json!({ "max_entries": max_entries }),
+            ),
+            ApiError::QueueClosed { status } => (
+                StatusCode::CONFLICT,
+                "queue_closed",
+                json!({ "status": status }),
+            ),
+            ApiError::AlreadyQueued { row_id, order } => (
+                StatusCode::CONFLICT,
+                "already_queued",
+                json!({ "row_id": row_id, "order": order }),
+            ),
+            ApiError::UrlNameTaken => {
+                (StatusCode::CONFLICT, "url_name_taken", json!({}))
+            }
+            ApiError::NotAdmin { queue_id } => (
+                StatusCode::FORBIDDEN,
+                "not_admin",
+                json!({ "queue_id": queue_id }),
+            ),
+            ApiError::BlankUsername
+            | ApiError::UsernameTaken
+            | ApiError::WeakPassword
+            | ApiError::InvalidCredentials => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_account",
+                json!({}),
+            ),
+            e @ (ApiError::PoolError(_)
+            | ApiError::DieselError(_)
+            | ApiError::PasswordHashError(_)) => {
+                // Don't leak database details to clients.
+                error!("REST API error: {e}");
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorBody {
+                        code: "internal",
+                        message: "internal server error".to_string(),
+                        details: json!({}),
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        let body = ErrorBody {
+            code,
+            message,
+            details,
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Machine-readable error kind, e.g. `occupied` or `not_found`.
+    code: &'static str,
+    message: String,
+    /// Fields specific to the error kind, e.g. the occupied row and slot.
+    #[schema(value_type = Object)]
+    details: serde_json::Value,
+}
+
+type RestResult<T> = Result<T, RestError>;
+
+/// A request that couldn't be read, sent as an [`ErrorBody`] like any other
+/// error instead of axum's plain text.
+pub struct Rejected {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl IntoResponse for Rejected {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code,
+            message: self.message,
+            details: json!({}),
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<JsonRejection> for Rejected {
+    fn from(e: JsonRejection) -> Self {
+        Rejected {
+            status: e.status(),
+            code: "invalid_body",
+            message: e.body_text(),
+        }
+    }
+}
+
+impl From<PathRejection> for Rejected {
+    fn from(e: PathRejection) -> Self {
+        Rejected {
+            status: e.status(),
+            code: "invalid_path",
+            message: e.body_text(),
+        }
+    }
+}
+
+impl From<QueryRejection> for Rejected {
+    fn from(e: QueryRejection) -> Self {
+        Rejected {
+            status: e.status(),
+            code: "invalid_query",
+            message: e.body_text(),
+        }
+    }
+}
+
+/// [`Json`] that's rejected with an [`ErrorBody`].
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Rejected;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Rejected> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(JsonBody(value))
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for JsonBody<T>
+where
+    Json<T>: OptionalFromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Rejected;
+
+    async fn from_request(
+        req: Request,
+        state: &S,
+    ) -> Result<Option<Self>, Rejected> {
+        let value =
+            <Json<T> as OptionalFromRequest<S>>::from_request(req, state)
+                .await?;
+        Ok(value.map(|Json(value)| JsonBody(value)))
+    }
+}
+
+/// [`Path`] that's rejected with an [`ErrorBody`], e.g. for a row id that
+/// isn't a UUID.
+pub struct PathParams<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for PathParams<T>
+where
+    Path<T>: FromRequestParts<S, Rejection = PathRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Rejected;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Rejected> {
+        let Path(value) = Path::<T>::from_request_parts(parts, state).await?;
+        Ok(PathParams(value))
+    }
+}
+
+/// [`Query`] that's rejected with an [`ErrorBody`].
+pub struct QueryParams<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for QueryParams<T>
+where
+    Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Rejected;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Rejected> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        Ok(QueryParams(value))
+    }
+}
+
+/// The queue version in the `If-Match` header, quoted like an `ETag` or not.
+/// Without the header, or with `*`, the version isn't pinned. Anything else
+/// is rejected, so a change isn't made when the client meant to guard it.
+pub struct IfMatch(pub Option<i32>);
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = Rejected;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Rejected> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Ok(IfMatch(None));
+        };
+        let value = value
+            .to_str()
+            .unwrap_or_default()
+            .trim();
+        if value == "*" {
+            return Ok(IfMatch(None));
+        }
+        value
+            .trim_start_matches("W/")
+            .trim_matches('"')
+            .parse()
+            .map(|version| IfMatch(Some(version)))
+            .map_err(|_| Rejected {
+                status: StatusCode::BAD_REQUEST,
+                code: "invalid_if_match",
+                message: "If-Match must be a queue version or *".to_string(),
+            })
+    }
+}
+
+/// A queue along with everyone waiting in it.
+#[derive(Serialize, ToSchema)]
+pub struct QueueWithEntries {
+    pub queue: QueueInfo,
+    /// Also sent as the `ETag`. Pass it in `If-Match` to only make a change
+    /// if the queue is still like this.
+    pub version: i32,
+    pub entries: Vec<QueueEntry>,
+    /// The setups the queue plays on at once, if it has any.
+    pub stations: Vec<Station>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddPlayerRequest {
+    /// Name of the player, matched against existing players ignoring case.
+    pub name: String,
+    /// Tells apart players who share a name. Players without a tag are
+    /// matched if left out or blank.
+    pub tag: Option<String>,
+    /// Side to join, `Left` if left out.
+    pub side: Option<Side>,
+    /// Slot on that side to join, counting from 0. The first slot if left
+    /// out.
+    pub slot_index: Option<i32>,
+    /// Row to join. A new row at the back of the queue is created if this is
+    /// left out.
+    pub row_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AddPlayerResponse {
+    pub row_id: Uuid,
+    pub side: Side,
+    pub slot_index: i32,
+    pub player: Player,
+}
+
+/// Picks a slot on the side given in the path.
+#[derive(Deserialize)]
+pub struct SlotQuery {
+    /// Counting from 0, the first slot if left out.
+    pub slot_index: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MoveRowRequest {
+    /// New 0-indexed position of the row.
+    pub position: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AdvanceRequest {
+    /// Side of the front row that won the match, if anyone did.
+    pub winner: Option<Side>,
+    /// Station whose match finished, in queues with stations. The front row
+    /// finishes if left out.
+    pub station_id: Option<Uuid>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/queues",
+    tag = "queues",
+    responses((status = 200, body = Vec<QueueInfo>))
+)]
+async fn list_queues(
+    State(store): State<Store>,
+) -> RestResult<Json<Vec<QueueInfo>>> {
+    Ok(Json(store.get_all_queues().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/queues/{url_name}",
+    tag = "queues",
+    params(
+        ("url_name" = String, Path, description = "The queue's url_name"),
+    ),
+    responses(
+        (status = 200, body = QueueWithEntries,
+            headers(("ETag" = String, description = "The queue's version"))),
+        (status = 404, body = ErrorBody)
+    )
+)]
+async fn get_queue(
+    PathParams(url_name): PathParams<String>,
+    State(store): State<Store>,
+) -> RestResult<([(HeaderName, String); 1], Json<QueueWithEntries>)> {
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    let state = store
+        .get_queue_state(queue.id)
+        .await?;
+    Ok((
+        [(header::ETAG, format!("\"{}\"", state.version))],
+        Json(QueueWithEntries {
+            queue,
+            version: state.version,
+            entries: state.entries,
+            stations: state.stations,
+        }),
+    ))
+}
+
+/// Adds a player to a row, or to a new row at the back of the queue.
+///
+/// In singles queues the slot and row are ignored, and the player is paired
+/// with whoever has been waiting for a partner the longest instead.
+#[utoipa::path(
+    post,
+    path = "/api/v1/queues/{url_name}/players",
+    tag = "queues",
+    params(
+        ("url_name" = String, Path, description = "The queue's url_name"),
+    ),
+    request_body = AddPlayerRequest,
+    responses(
+        (status = 201, body = AddPlayerResponse),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The slot is taken, the queue changed at the same time, or the queue's settings don't let the player join", body = ErrorBody),
+        (status = 422, description = "The name is blank, or the slot is past the queue's team size", body = ErrorBody)
+    )
+)]
+async fn add_player(
+    PathParams(url_name): PathParams<String>,
+    State(store): State<Store>,
+    JsonBody(request): JsonBody<AddPlayerRequest>,
+) -> RestResult<(StatusCode, Json<AddPlayerResponse>)> {
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    let player = store
+        .get_or_create_player(request.name, request.tag)
+        .await?;
+    let slot = Slot::new(
+        request.side.unwrap_or(Side::Left),
+        request.slot_index.unwrap_or(0),
+    );
+    let (row_id, slot) = match request.row_id {
+        _ if queue.singles => {
+            let entry = store
+                .join_queue(queue.id, player.id)
+                .await?;
+            let slot = entry
+                .players
+                .iter()
+                .find(|p| p.player.id == player.id)
+                .map_or(slot, |p| p.slot);
+            (entry.id, slot)
+        }
+        Some(row_id) => {
+            require_row_in_queue(row_id, queue.id, &store).await?;
+            let entry = store
+                .add_player_to_row(row_id, player.id, slot)
+                .await?;
+            (entry.id, slot)
+        }
+        None => {
+            let row_id = store
+                .add_row(queue.id, player.id, slot)
+                .await?;
+            (row_id, slot)
+        }
+    };
+    Ok((
+        StatusCode::CREATED,
+        Json(AddPlayerResponse {
+            row_id,
+            side: slot.side,
+            slot_index: slot.index,
+            player,
+        }),
+    ))
+}
+
+/// Removes a player from a row, deleting the row if it ends up empty.
+/// Returns the updated entry, or `null` if the row was deleted.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/queues/{url_name}/rows/{row_id}/players/{side}",
+    tag = "queues",
+    params(
+        ("url_name" = String, Path, description = "The queue's url_name"),
+        ("row_id" = Uuid, Path),
+        ("side" = Side, Path),
+        ("slot_index" = Option<i32>, Query, description = "Slot on that side, counting from 0. The first slot if left out"),
+        ("If-Match" = Option<String>, Header, description = "Only make the change at this queue version")
+    ),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Option<QueueEntry>),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 412, description = "The queue has changed since the If-Match version", body = ErrorBody)
+    )
+)]
+async fn remove_player(
+    PathParams((url_name, row_id, side)): PathParams<(String, Uuid, Side)>,
+    QueryParams(query): QueryParams<SlotQuery>,
+    State(store): State<Store>,
+    Extension(user): Extension<CurrentUser>,
+    headers: HeaderMap,
+    IfMatch(expected_version): IfMatch,
+) -> RestResult<Json<Option<QueueEntry>>> {
+    let queue = require_admin(url_name, &headers, user, &store).await?;
+    require_row_in_queue(row_id, queue.id, &store).await?;
+    Ok(Json(
+        store
+            .remove_player_from_row(
+                row_id,
+                Slot::new(side, query.slot_index.unwrap_or(0)),
+                expected_version,
+            )
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/queues/{url_name}/rows/{row_id}",
+    tag = "queues",
+    params(
+        ("url_name" = String, Path, description = "The queue's url_name"),
+        ("row_id" = Uuid, Path),
+        ("If-Match" = Option<String>, Header, description = "Only make the change at this queue version")
+    ),
+    security(("admin_token" = [])),
+    responses(
+        (status = 204),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 412, description = "The queue has changed since the If-Match version", body = ErrorBody)
+    )
+)]
+async fn delete_row(
+    PathParams((url_name, row_id)): PathParams<(String, Uuid)>,
+    State(store): State<Store>,
+    Extension(user): Extension<CurrentUser>,
+    headers: HeaderMap,
+    IfMatch(expected_version): IfMatch,
+) -> RestResult<StatusCode> {
+    let queue = require_admin(url_name, &headers, user, &store).await?;
+    require_row_in_queue(row_id, queue.id, &store).await?;
+    store
+        .delete_row(row_id, expected_version)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Moves a row to a new position, shifting the rows in between.
+#[utoipa::path(
+    post,
+    path = "/api/v1/queues/{url_name}/rows/{row_id}/move",
+    tag = "queues",
+    params(
+        ("url_name" = String, Path, description = "The queue's url_name"),
+        ("row_id" = Uuid, Path),
+        ("If-Match" = Option<String>, Header, description = "Only make the change at this queue version")
+    ),
+    request_body = MoveRowRequest,
+    security(("admin_token" = [])),
+    responses(
+        (status = 204),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 412, description = "The queue has changed since the If-Match version", body = ErrorBody),
+        (status = 422, description = "The position is past the end of the queue", body = ErrorBody)
+    )
+)]
+async fn move_row(
+    PathParams((url_name, row_id)): PathParams<(String, Uuid)>,
+    State(store): State<Store>,
+    Extension(user): Extension<CurrentUser>,
+    headers: HeaderMap,
+    IfMatch(expected_version): IfMatch,
+    JsonBody(request): JsonBody<MoveRowRequest>,
+) -> RestResult<StatusCode> {
+    let queue = require_admin(url_name, &headers, user, &store).await?;
+    require_row_in_queue(row_id, queue.id, &store).await?;
+    store
+        .move_row(row_id, request.position, expected_version)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Finishes the match at the front of the queue and records it in the
+/// queue's history, returning the entry that played, or `null` if the queue
+/// was empty. In the winner or loser stays modes, whoever stays keeps the
+/// front row and the next waiting player joins them.
+///
+/// With a `station_id`, finishes the match on that station instead, and the
+/// first waiting row starts there unless someone stays on. It returns `null`
+/// if the station was free. A queue with stations needs a `station_id`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/queues/{url_name}/advance",
+    tag = "queues",
+    params(
+        ("url_name" = String, Path, description = "The queue's url_name"),
+        ("If-Match" = Option<String>, Header, description = "Only make the change at this queue version")
+    ),
+    request_body(content = Option<AdvanceRequest>, description = "Optional, to record who won"),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Option<QueueEntry>),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The winning side has no players, or the queue has stations and no `station_id` was given", body = ErrorBody),
+        (status = 412, description = "The queue has changed since the If-Match version", body = ErrorBody)
+    )
+)]
+async fn advance(
+    PathParams(url_name): PathParams<String>,
+    State(store): State<Store>,
+    Extension(user): Extension<CurrentUser>,
+    headers: HeaderMap,
+    IfMatch(expected_version): IfMatch,
+    request: Option<JsonBody<AdvanceRequest>>,
+) -> RestResult<Json<Option<QueueEntry>>> {
+    let queue = require_admin(url_name, &headers, user, &store).await?;
+    let (winner, station_id) = request
+        .map_or((None, None), |JsonBody(request)| {
+            (request.winner, request.station_id)
+        });
+    let Some(station_id) = station_id else {
+        return Ok(Json(
+            store
+                .complete_front_row(queue.id, winner, expected_version)
+                .await?,
+        ));
+    };
+    // A queue's admin token can't be used on another queue's stations.
+    if store
+        .get_station_queue_id(station_id)
+        .await?
+        != queue.id
+    {
+        return Err(ApiError::NotFound.into());
+    }
+    Ok(Json(
+        store
+            .free_station(station_id, winner, expected_version)
+            .await?,
+    ))
+}
+
+/// Loads a queue, failing unless the request may manage it.
+async fn require_admin(
+    url_name: String,
+    headers: &HeaderMap,
+    CurrentUser(user): CurrentUser,
+    store: &Store,
+) -> Result<QueueInfo, ApiError> {
+    let queue = store
+        .get_queue_info(url_name)
+        .await?;
+    let is_admin = store
+        .is_queue_admin(
+            queue.id,
+            admin_token(headers, queue.id),
+            user.map(|u| u.id),
+        )
+        .await?;
+    if !is_admin {
+        return Err(ApiError::NotAdmin { queue_id: queue.id });
+    }
+    Ok(queue)
+}
+
+/// Treats rows from other queues as missing, so a queue's admin token can't
+/// be used on another queue's rows.
+async fn require_row_in_queue(
+    row_id: Uuid,
+    queue_id: Uuid,
+    store: &Store,
+) -> Result<(), ApiError> {
+    if store
+        .get_row_queue_id(row_id)
+        .await?
+        != queue_id
+    {
+        return Err(ApiError::NotFound);
+    }
+    Ok(())
+}
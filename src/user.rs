@@ -0,0 +1,21 @@
+#[cfg(feature = "ssr")]
+use crate::db;
+use leptos::server_fn::serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// An organizer account. Queues remember which user created them.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+}
+
+#[cfg(feature = "ssr")]
+impl From<db::User> for User {
+    fn from(user: db::User) -> Self {
+        User {
+            id: user.id,
+            username: user.username,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Adding rows from many requests at once must still number them 0, 1, 2...
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::Store;
+use itq::queue::Side;
+
+const PARALLEL_ADDS: usize = 32;
+
+/// Adds a row for each of [`PARALLEL_ADDS`] players at once, then checks
+/// every add succeeded and the orders have no gaps or repeats.
+async fn assert_parallel_adds_are_contiguous(store: Store) {
+    let queue = common::new_queue(&store, "Parallel adds").await;
+
+    let mut players = Vec::new();
+    for i in 0..PARALLEL_ADDS {
+        players.push(common::new_player(&store, &format!("player {i}")).await);
+    }
+
+    let adds: Vec<_> = players
+        .into_iter()
+        .map(|player| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                store
+                    .add_row(queue.id, player.id, Side::Left.into())
+                    .await
+            })
+        })
+        .collect();
+    for add in adds {
+        add.await
+            .expect("add to finish")
+            .expect("add to succeed");
+    }
+
+    let orders: Vec<i32> = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("entries to load")
+        .entries
+        .iter()
+        .map(|entry| entry.order)
+        .collect();
+    common::delete_queue(&store, queue.id).await;
+    assert_eq!(
+        orders,
+        (0..PARALLEL_ADDS as i32).collect::<Vec<_>>()
+    );
+}
+
+on_every_backend!(parallel_adds => assert_parallel_adds_are_contiguous);
@@ -0,0 +1,147 @@
+//! Fixtures shared by the integration tests, which run each scenario against
+//! every backend with [`on_every_backend!`].
+//!
+//! The Postgres runs need `DATABASE_URL` pointing at a migrated database and
+//! are skipped otherwise. Scenarios clean up their queues, but leave their
+//! players.
+// Each test binary uses its own subset of these.
+#![allow(dead_code)]
+
+use itq::db::memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+use itq::db::notify::QueueChanges;
+use itq::db::{self, PgStore, Store};
+use itq::queue::{Player, QueueInfo, Side};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Runs a scenario, an `async fn(Store)`, against the in-memory, Postgres
+/// and SQLite backends, as `name::in_memory` and so on.
+#[macro_export]
+macro_rules! on_every_backend {
+    ($($name:ident => $scenario:ident),+ $(,)?) => {$(
+        mod $name {
+            #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+            async fn in_memory() {
+                super::$scenario($crate::common::memory()).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+            async fn in_postgres() {
+                let Some(store) = $crate::common::postgres().await else {
+                    return;
+                };
+                super::$scenario(store).await;
+            }
+
+            #[cfg(feature = "sqlite")]
+            #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+            async fn in_sqlite() {
+                let sqlite = $crate::common::Sqlite::open();
+                super::$scenario(sqlite.store.clone()).await;
+            }
+        }
+    )+};
+}
+
+pub fn memory() -> Store {
+    Arc::new(MemoryStore::new())
+}
+
+/// `DATABASE_URL`, or `None` if it isn't a Postgres database.
+pub fn postgres_url() -> Option<String> {
+    dotenvy::dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL")
+        .ok()
+        .filter(|url| url.starts_with("postgres"));
+    if database_url.is_none() {
+        eprintln!("skipping: DATABASE_URL isn't a Postgres database");
+    }
+    database_url
+}
+
+/// The database `DATABASE_URL` points at, or `None` if it isn't Postgres.
+pub async fn postgres() -> Option<Store> {
+    let pool = db::create_pool(&postgres_url()?).await;
+    Some(Arc::new(PgStore::new(pool)))
+}
+
+/// A SQLite store in a temporary file, removed again when dropped.
+#[cfg(feature = "sqlite")]
+pub struct Sqlite {
+    pub store: Store,
+    pub changes: QueueChanges,
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "sqlite")]
+impl Sqlite {
+    pub fn open() -> Self {
+        use itq::db::sqlite::SqliteStore;
+        let path = std::env::temp_dir()
+            .join(format!("itq-test-{}.db", Uuid::new_v4()));
+        let store = SqliteStore::open(
+            path.to_str()
+                .expect("temp path to be UTF-8"),
+        )
+        .expect("database to open");
+        Sqlite {
+            changes: store.changes(),
+            store: Arc::new(store),
+            path,
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Drop for Sqlite {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A new queue with a unique url name.
+pub async fn new_queue(store: &Store, display_name: &str) -> QueueInfo {
+    let url_name = format!("test-{}", Uuid::new_v4().simple());
+    store
+        .add_queue(display_name.to_string(), url_name, None)
+        .await
+        .expect("queue to be created")
+        .info
+}
+
+/// A new player, named `name` plus something unique so runs don't share
+/// players.
+pub async fn new_player(store: &Store, name: &str) -> Player {
+    let name = format!("{name} {}", Uuid::new_v4().simple());
+    store
+        .get_or_create_player(name, None)
+        .await
+        .expect("player to be created")
+}
+
+/// Adds a row at the back of a queue with `left` and `right` in the first
+/// slot of each side.
+pub async fn add_pair(
+    store: &Store,
+    queue_id: Uuid,
+    left: Uuid,
+    right: Uuid,
+) -> Uuid {
+    let row_id = store
+        .add_row(queue_id, left, Side::Left.into())
+        .await
+        .expect("row to be added");
+    store
+        .add_player_to_row(row_id, right, Side::Right.into())
+        .await
+        .expect("player to join the row");
+    row_id
+}
+
+pub async fn delete_queue(store: &Store, queue_id: Uuid) {
+    store
+        .delete_queue(queue_id)
+        .await
+        .expect("queue to be deleted");
+}
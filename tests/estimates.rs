@@ -0,0 +1,99 @@
+//! Waiting rows must get start estimates from how long recent matches took,
+//! later the further back they are, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use chrono::{TimeDelta, Utc};
+use itq::db::Store;
+use itq::estimate::median_duration;
+use itq::queue::{QueueState, Side};
+use std::time::Duration;
+
+/// Adds four rows, plays a match so there's something to go on, then puts a
+/// station in and checks the row playing on it has no estimate.
+async fn assert_rows_get_estimates(store: Store) {
+    let queue = common::new_queue(&store, "Estimates").await;
+    for _ in 0..4 {
+        let ana = common::new_player(&store, "ana").await;
+        let ben = common::new_player(&store, "ben").await;
+        common::add_pair(&store, queue.id, ana.id, ben.id).await;
+    }
+    let state = || async {
+        store
+            .get_queue_state(queue.id)
+            .await
+            .expect("state to load")
+    };
+
+    let before = state().await;
+    // Give the match a length to go on.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    store
+        .complete_front_row(queue.id, Some(Side::Left), None)
+        .await
+        .expect("match to finish");
+    let after = state().await;
+    let station = store
+        .add_station(queue.id, "Court A".to_string(), None)
+        .await
+        .expect("station to be added");
+    let playing = store
+        .assign_station(station.id, None)
+        .await
+        .expect("station to start")
+        .expect("a row to start");
+    let with_station = state().await;
+    common::delete_queue(&store, queue.id).await;
+
+    let estimates = |state: &QueueState| {
+        state
+            .entries
+            .iter()
+            .map(|e| e.estimated_start)
+            .collect::<Vec<_>>()
+    };
+    // Nothing to go on before the first match ends.
+    assert!(estimates(&before)
+        .iter()
+        .all(Option::is_none));
+    // The front row is playing, everyone behind it waits a match longer
+    // than the row ahead.
+    let after = estimates(&after);
+    assert_eq!(after.len(), 3);
+    assert!(after[0].is_none());
+    assert!(after[1].is_some());
+    assert!(after[1] < after[2]);
+    let on_station: Vec<bool> = with_station
+        .entries
+        .iter()
+        .map(|e| e.id == playing.id)
+        .collect();
+    assert_eq!(on_station, vec![true, false, false]);
+    let with_station = estimates(&with_station);
+    assert!(with_station[0].is_none());
+    assert!(with_station[1].is_some());
+    assert!(with_station[1] < with_station[2]);
+}
+
+on_every_backend!(rows_get_estimates => assert_rows_get_estimates);
+
+/// A match that ran on over a break doesn't stretch the median.
+#[test]
+fn long_matches_dont_skew_the_median() {
+    let start = Utc::now();
+    let matches: Vec<_> = [10, 12, 11, 14, 60 * 12]
+        .into_iter()
+        .map(|minutes| (start, start + TimeDelta::minutes(minutes)))
+        .collect();
+
+    assert_eq!(median_duration(&[]), None);
+    assert_eq!(
+        median_duration(&matches),
+        Some(TimeDelta::minutes(12))
+    );
+    assert_eq!(
+        median_duration(&matches[..4]),
+        Some(TimeDelta::seconds(11 * 60 + 30))
+    );
+}
@@ -0,0 +1,126 @@
+//! Finishing a match must leave it in the queue's history, timed from when
+//! it could start, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use chrono::{TimeDelta, Utc};
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{Side, Slot};
+use uuid::Uuid;
+
+/// Plays two matches, the first with a winner, and checks they come back
+/// newest first with everyone's games counted.
+async fn assert_matches_are_recorded(store: Store) {
+    let queue = common::new_queue(&store, "History").await;
+    let ana = &common::new_player(&store, "ana").await;
+    let ben = &common::new_player(&store, "ben").await;
+    common::add_pair(&store, queue.id, ana.id, ben.id).await;
+    store
+        .add_row(queue.id, ana.id, Side::Left.into())
+        .await
+        .expect("row to be added");
+
+    store
+        .complete_front_row(queue.id, Some(Side::Left), None)
+        .await
+        .expect("first match to finish");
+    // Nobody played on the right in the second match.
+    let empty_winner = store
+        .complete_front_row(queue.id, Some(Side::Right), None)
+        .await;
+    store
+        .complete_front_row(queue.id, None, None)
+        .await
+        .expect("second match to finish");
+
+    let newest = store
+        .get_matches(queue.id, 0, 1)
+        .await
+        .expect("matches to load");
+    let older = store
+        .get_matches(queue.id, 1, 10)
+        .await
+        .expect("matches to load");
+    let games = store
+        .get_games_played(queue.id, Utc::now() - TimeDelta::hours(1))
+        .await
+        .expect("games to be counted");
+    common::delete_queue(&store, queue.id).await;
+
+    assert!(matches!(
+        empty_winner,
+        Err(ApiError::EmptySlot {
+            slot: Slot {
+                side: Side::Right,
+                ..
+            },
+            ..
+        })
+    ));
+    assert_eq!(newest.len(), 1);
+    assert_eq!(newest[0].left, vec![ana.clone()]);
+    assert!(newest[0].right.is_empty());
+    assert_eq!(newest[0].winner, None);
+    assert_eq!(older.len(), 1);
+    assert_eq!(older[0].right, vec![ben.clone()]);
+    assert_eq!(older[0].winner, Some(Side::Left));
+    // The second match started when the first one ended.
+    assert_eq!(newest[0].started_at, older[0].ended_at);
+    let counts: Vec<(Uuid, u32)> = games
+        .iter()
+        .map(|g| (g.player.id, g.games))
+        .collect();
+    assert_eq!(counts, vec![(ana.id, 2), (ben.id, 1)]);
+}
+
+/// Plays a row that was called to play some time after the last match
+/// ended, and checks the wait before it was called isn't counted.
+async fn assert_called_rows_start_when_called(store: Store) {
+    let queue = common::new_queue(&store, "Called history").await;
+    let ana = common::new_player(&store, "ana").await;
+    let ben = common::new_player(&store, "ben").await;
+    for _ in 0..2 {
+        common::add_pair(&store, queue.id, ana.id, ben.id).await;
+    }
+    store
+        .complete_front_row(queue.id, None, None)
+        .await
+        .expect("first match to finish");
+    store
+        .set_no_show_rule(queue.id, Some(600), None, None)
+        .await
+        .expect("rule to be set");
+    let called_at = Utc::now() + TimeDelta::milliseconds(50);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    store
+        .check_no_shows(called_at)
+        .await
+        .expect("check to run");
+    let called = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    store
+        .complete_front_row(queue.id, None, None)
+        .await
+        .expect("second match to finish");
+    let matches = store
+        .get_matches(queue.id, 0, 10)
+        .await
+        .expect("matches to load");
+    common::delete_queue(&store, queue.id).await;
+
+    let called_at = called.entries[0]
+        .called_at
+        .expect("front row to be called");
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].started_at, called_at);
+    assert!(matches[0].started_at > matches[1].ended_at);
+}
+
+on_every_backend!(
+    matches_are_recorded => assert_matches_are_recorded,
+    called_rows_start_when_called => assert_called_rows_start_when_called,
+);
@@ -0,0 +1,210 @@
+//! The front row of a queue with a no-show timeout must be called, and moved
+//! back or dropped if its players don't all check in in time, the same way
+//! on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use chrono::{TimeDelta, Utc};
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{QueueEntry, QueueState, Side};
+
+/// Calls a row, lets half of it check in, moves it back once time's up, then
+/// drops the next row under a rule without moving back.
+async fn assert_no_shows_are_handled(store: Store) {
+    let queue = common::new_queue(&store, "No-shows").await;
+    let queue = store
+        .set_no_show_rule(queue.id, Some(60), Some(1), None)
+        .await
+        .expect("rule to be set");
+    let mut rows = Vec::new();
+    for [left, right] in [["ana", "ben"], ["cy", "dee"]] {
+        let left = common::new_player(&store, left).await;
+        let right = common::new_player(&store, right).await;
+        rows.push(common::add_pair(&store, queue.id, left.id, right.id).await);
+    }
+    let (first, second) = (rows[0], rows[1]);
+    let queue_id = queue.id;
+    let entries = |store: Store| async move {
+        store
+            .get_queue_state(queue_id)
+            .await
+            .expect("state to load")
+            .entries
+    };
+    let check = |seconds: i64| {
+        let store = store.clone();
+        let start = Utc::now();
+        async move {
+            store
+                .check_no_shows(start + TimeDelta::seconds(seconds))
+                .await
+                .expect("check to run");
+        }
+    };
+
+    check(0).await;
+    let called = entries(store.clone()).await;
+    let early = store
+        .check_in(second, Side::Left.into())
+        .await;
+    let checked = store
+        .check_in(first, Side::Left.into())
+        .await
+        .expect("player to check in");
+    check(30).await;
+    let waiting = entries(store.clone()).await;
+    check(61).await;
+    let moved = entries(store.clone()).await;
+    check(62).await;
+    for side in [Side::Left, Side::Right] {
+        store
+            .check_in(second, side.into())
+            .await
+            .expect("player to check in");
+    }
+    check(200).await;
+    let ready = entries(store.clone()).await;
+
+    let queue = store
+        .set_no_show_rule(queue.id, Some(60), None, None)
+        .await
+        .expect("rule to be changed");
+    let reset = entries(store.clone()).await;
+    check(0).await;
+    check(61).await;
+    let dropped = entries(store.clone()).await;
+    common::delete_queue(&store, queue.id).await;
+
+    let ids = |entries: &[QueueEntry]| {
+        entries
+            .iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+    };
+    let called_rows = |entries: &[QueueEntry]| {
+        entries
+            .iter()
+            .map(|e| e.called_at.is_some())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(called_rows(&called), vec![true, false]);
+    assert!(matches!(
+        early,
+        Err(ApiError::NotCalled { row_id }) if row_id == second
+    ));
+    let check_ins: Vec<bool> = checked
+        .players
+        .iter()
+        .map(|p| p.checked_in)
+        .collect();
+    assert_eq!(check_ins, vec![true, false]);
+    assert_eq!(ids(&waiting), vec![first, second]);
+    assert_eq!(called_rows(&waiting), vec![true, false]);
+    // Ben never checked in, so their row goes behind the next one.
+    assert_eq!(ids(&moved), vec![second, first]);
+    assert_eq!(called_rows(&moved), vec![false, false]);
+    assert!(moved[1]
+        .players
+        .iter()
+        .all(|p| !p.checked_in));
+    // Everyone in the second row checked in, so it keeps its place.
+    assert_eq!(ids(&ready), vec![second, first]);
+    assert_eq!(called_rows(&ready), vec![true, false]);
+    assert_eq!(queue.no_show_move_back, None);
+    assert_eq!(called_rows(&reset), vec![false, false]);
+    assert_eq!(ids(&dropped), vec![first]);
+}
+
+/// Leaves the row on a station alone while the rows waiting behind it are
+/// called, moved back and dropped.
+async fn assert_playing_rows_are_never_no_shows(store: Store) {
+    let queue = common::new_queue(&store, "No-shows with a station").await;
+    let station = store
+        .add_station(queue.id, "Court A".to_string(), None)
+        .await
+        .expect("station to be added");
+    store
+        .set_no_show_rule(queue.id, Some(60), Some(1), None)
+        .await
+        .expect("rule to be set");
+    let mut rows = Vec::new();
+    for [left, right] in [["ana", "ben"], ["cy", "dee"], ["eve", "fay"]] {
+        let left = common::new_player(&store, left).await;
+        let right = common::new_player(&store, right).await;
+        rows.push(common::add_pair(&store, queue.id, left.id, right.id).await);
+    }
+    let (playing, first, second) = (rows[0], rows[1], rows[2]);
+    let assigned = store
+        .assign_station(station.id, None)
+        .await
+        .expect("station to start");
+    let queue_id = queue.id;
+    let state = |store: Store| async move {
+        store
+            .get_queue_state(queue_id)
+            .await
+            .expect("state to load")
+    };
+    let check = |seconds: i64| {
+        let store = store.clone();
+        let start = Utc::now();
+        async move {
+            store
+                .check_no_shows(start + TimeDelta::seconds(seconds))
+                .await
+                .expect("check to run");
+        }
+    };
+
+    check(0).await;
+    let called = state(store.clone()).await;
+    check(61).await;
+    let moved = state(store.clone()).await;
+    store
+        .set_no_show_rule(queue.id, Some(60), None, None)
+        .await
+        .expect("rule to be changed");
+    check(0).await;
+    check(61).await;
+    let dropped = state(store.clone()).await;
+    common::delete_queue(&store, queue.id).await;
+
+    let ids = |state: &QueueState| {
+        state
+            .entries
+            .iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+    };
+    let called_rows = |state: &QueueState| {
+        state
+            .entries
+            .iter()
+            .map(|e| e.called_at.is_some())
+            .collect::<Vec<_>>()
+    };
+    let on_station = |state: &QueueState| {
+        state
+            .stations
+            .iter()
+            .map(|s| s.row_id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(assigned.map(|e| e.id), Some(playing));
+    // The first waiting row is called, not the one already playing.
+    assert_eq!(ids(&called), vec![playing, first, second]);
+    assert_eq!(called_rows(&called), vec![false, true, false]);
+    // Moving back skips over the playing row too.
+    assert_eq!(ids(&moved), vec![playing, second, first]);
+    assert_eq!(on_station(&moved), vec![Some(playing)]);
+    // Dropping takes the waiting row and never the one on the station.
+    assert_eq!(ids(&dropped), vec![playing, first]);
+    assert_eq!(on_station(&dropped), vec![Some(playing)]);
+}
+
+on_every_backend!(
+    no_shows_are_handled => assert_no_shows_are_handled,
+    playing_rows_are_never_no_shows => assert_playing_rows_are_never_no_shows,
+);
@@ -0,0 +1,536 @@
+//! The in-memory and SQLite stores stand in for Postgres, so the same changes
+//! must leave each of them in the same state, at the same versions, with the
+//! same errors and the same notifications to listeners.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use chrono::{TimeDelta, Utc};
+use itq::db::api::ApiError;
+use itq::db::memory::MemoryStore;
+use itq::db::notify::{self, QueueChanges};
+use itq::db::Store;
+use itq::queue::{
+    QueueMode, QueueSettings, QueueState, QueueStatus, Side, Slot,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use uuid::Uuid;
+
+/// A store and the changes it announces.
+struct Backend {
+    store: Store,
+    changes: QueueChanges,
+    /// How long to wait for a change to be announced. Postgres only
+    /// announces changes once they're committed, through a separate
+    /// connection.
+    wait: Duration,
+}
+
+/// How a step went and how it left the queue.
+#[derive(Debug, PartialEq)]
+struct Step {
+    name: &'static str,
+    outcome: String,
+    notified: bool,
+    /// How far the queue's version has moved since it was created.
+    versions: i32,
+    rows: Vec<Row>,
+    /// The index of the row playing on each station.
+    stations: Vec<Option<usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Row {
+    players: Vec<(Slot, String, bool)>,
+    called: bool,
+    estimated: bool,
+}
+
+/// Runs the steps of a scenario against one backend, writing down how each
+/// one went.
+struct Transcript {
+    store: Store,
+    changes: Receiver<Uuid>,
+    wait: Duration,
+    queue_id: Uuid,
+    created_at_version: i32,
+    steps: Vec<Step>,
+}
+
+impl Transcript {
+    async fn start(backend: Backend) -> Self {
+        let mut changes = backend.changes.subscribe();
+        let queue = common::new_queue(&backend.store, "Parity").await;
+        // Postgres starts listening in the background, so wait until it hears
+        // about a change.
+        let mut listening = false;
+        for _ in 0..20 {
+            backend
+                .store
+                .set_queue_settings(queue.id, QueueSettings::default(), None)
+                .await
+                .expect("settings to be saved");
+            if heard(&mut changes, queue.id, backend.wait * 4).await {
+                listening = true;
+                break;
+            }
+        }
+        assert!(listening, "the store to announce changes");
+        let created_at_version = state(&backend.store, queue.id)
+            .await
+            .version;
+        Transcript {
+            store: backend.store,
+            changes,
+            wait: backend.wait,
+            queue_id: queue.id,
+            created_at_version,
+            steps: Vec::new(),
+        }
+    }
+
+    async fn record<T>(
+        &mut self,
+        name: &'static str,
+        result: Result<T, ApiError>,
+    ) {
+        let outcome = match result {
+            Ok(_) => "ok".to_string(),
+            // Just the variant, since ids differ between runs.
+            Err(e) => format!("{e:?}")
+                .split([' ', '(', '{'])
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        };
+        let notified = heard(&mut self.changes, self.queue_id, self.wait).await;
+        let state = state(&self.store, self.queue_id).await;
+        let position = |row_id: Uuid| {
+            state
+                .entries
+                .iter()
+                .position(|e| e.id == row_id)
+        };
+        self.steps.push(Step {
+            name,
+            outcome,
+            notified,
+            versions: state.version - self.created_at_version,
+            rows: state
+                .entries
+                .iter()
+                .map(|e| Row {
+                    players: e
+                        .players
+                        .iter()
+                        .map(|p| {
+                            (
+                                p.slot,
+                                name_of(&p.player.display_name),
+                                p.checked_in,
+                            )
+                        })
+                        .collect(),
+                    called: e.called_at.is_some(),
+                    estimated: e.estimated_start.is_some(),
+                })
+                .collect(),
+            stations: state
+                .stations
+                .iter()
+                .map(|s| s.row_id.and_then(position))
+                .collect(),
+        });
+    }
+
+    /// The id of the row at `position`, as things stand.
+    async fn row(&self, position: usize) -> Uuid {
+        state(&self.store, self.queue_id)
+            .await
+            .entries[position]
+            .id
+    }
+}
+
+async fn state(store: &Store, queue_id: Uuid) -> QueueState {
+    store
+        .get_queue_state(queue_id)
+        .await
+        .expect("state to load")
+}
+
+/// A player's name without what [`common::new_player`] adds to keep it
+/// unique.
+fn name_of(display_name: &str) -> String {
+    display_name
+        .split(' ')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Whether `queue_id` was announced as changed, taking every announcement
+/// made so far.
+async fn heard(
+    changes: &mut Receiver<Uuid>,
+    queue_id: Uuid,
+    wait: Duration,
+) -> bool {
+    let mut heard = false;
+    while let Ok(received) = tokio::time::timeout(wait, changes.recv()).await {
+        match received {
+            Ok(id) => heard |= id == queue_id,
+            Err(RecvError::Lagged(_)) => heard = true,
+            Err(RecvError::Closed) => break,
+        }
+    }
+    heard
+}
+
+/// Runs a queue through most of what can happen to it: limits, moves, stale
+/// and refused changes, check-ins, matches, stations and settings changes.
+async fn play(backend: Backend) -> Vec<Step> {
+    let mut t = Transcript::start(backend).await;
+    let store = t.store.clone();
+    let queue_id = t.queue_id;
+    let mut players = Vec::new();
+    for name in ["ana", "ben", "cy", "dee", "eve"] {
+        players.push(
+            common::new_player(&store, name)
+                .await
+                .id,
+        );
+    }
+    let [ana, ben, cy, dee, eve] = players[..] else {
+        unreachable!()
+    };
+    let left = Slot::from(Side::Left);
+    let right = Slot::from(Side::Right);
+
+    let limits = QueueSettings {
+        max_rows: Some(3),
+        max_entries_per_player: Some(2),
+        ..Default::default()
+    };
+    t.record(
+        "limit",
+        store
+            .set_queue_settings(queue_id, limits, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "add ana",
+        store
+            .add_row(queue_id, ana, left)
+            .await,
+    )
+    .await;
+    let first = t.row(0).await;
+    t.record(
+        "add ben",
+        store
+            .add_player_to_row(first, ben, right)
+            .await,
+    )
+    .await;
+    t.record(
+        "add cy",
+        store
+            .add_row(queue_id, cy, left)
+            .await,
+    )
+    .await;
+    let second = t.row(1).await;
+    t.record(
+        "taken",
+        store
+            .add_player_to_row(second, dee, left)
+            .await,
+    )
+    .await;
+    t.record(
+        "add dee",
+        store
+            .add_player_to_row(second, dee, right)
+            .await,
+    )
+    .await;
+    t.record(
+        "add ana again",
+        store
+            .add_row(queue_id, ana, left)
+            .await,
+    )
+    .await;
+    t.record(
+        "full",
+        store
+            .add_row(queue_id, eve, left)
+            .await,
+    )
+    .await;
+    let third = t.row(2).await;
+    t.record(
+        "too many",
+        store
+            .add_player_to_row(third, ana, right)
+            .await,
+    )
+    .await;
+    let version = state(&store, queue_id)
+        .await
+        .version;
+    t.record(
+        "move",
+        store
+            .move_row(third, 0, Some(version))
+            .await,
+    )
+    .await;
+    t.record(
+        "stale",
+        store
+            .delete_row(third, Some(version))
+            .await,
+    )
+    .await;
+    t.record(
+        "occupied",
+        store
+            .move_player(second, left, first, right, false, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "swap",
+        store
+            .move_player(second, left, first, right, true, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "bad position",
+        store
+            .move_row(first, 5, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "pause",
+        store
+            .set_queue_settings(
+                queue_id,
+                QueueSettings {
+                    status: QueueStatus::Paused,
+                    ..limits
+                },
+                None,
+            )
+            .await,
+    )
+    .await;
+    t.record(
+        "paused",
+        store
+            .add_player_to_row(third, eve, right)
+            .await,
+    )
+    .await;
+    t.record(
+        "open",
+        store
+            .set_queue_settings(queue_id, QueueSettings::default(), None)
+            .await,
+    )
+    .await;
+    t.record(
+        "add eve",
+        store
+            .add_player_to_row(third, eve, right)
+            .await,
+    )
+    .await;
+
+    t.record(
+        "check-ins",
+        store
+            .set_no_show_rule(queue_id, Some(60), Some(1), None)
+            .await,
+    )
+    .await;
+    t.record(
+        "call",
+        store
+            .check_no_shows(Utc::now())
+            .await,
+    )
+    .await;
+    let front = t.row(0).await;
+    t.record("check in", store.check_in(front, left).await)
+        .await;
+    t.record("not called", store.check_in(first, left).await)
+        .await;
+    t.record(
+        "no-show",
+        store
+            .check_no_shows(Utc::now() + TimeDelta::seconds(61))
+            .await,
+    )
+    .await;
+    t.record(
+        "no check-ins",
+        store
+            .set_no_show_rule(queue_id, None, None, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "empty winner",
+        store
+            .complete_front_row(queue_id, Some(Side::Right), Some(0))
+            .await,
+    )
+    .await;
+    t.record(
+        "finish",
+        store
+            .complete_front_row(queue_id, Some(Side::Left), None)
+            .await,
+    )
+    .await;
+    t.record(
+        "winner stays",
+        store
+            .set_queue_mode(
+                queue_id,
+                QueueMode::WinnerStays,
+                Some(2),
+                false,
+                1,
+                None,
+            )
+            .await,
+    )
+    .await;
+    t.record(
+        "stay on",
+        store
+            .complete_front_row(queue_id, Some(Side::Left), None)
+            .await,
+    )
+    .await;
+
+    let station = store
+        .add_station(queue_id, "Court A".to_string(), None)
+        .await;
+    let station_id = station
+        .as_ref()
+        .map(|s| s.id)
+        .unwrap_or_default();
+    t.record("station", station).await;
+    t.record(
+        "front only",
+        store
+            .complete_front_row(queue_id, None, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "start",
+        store
+            .assign_station(station_id, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "still playing",
+        store
+            .assign_station(station_id, None)
+            .await,
+    )
+    .await;
+    t.record(
+        "free",
+        store
+            .free_station(station_id, Some(Side::Right), None)
+            .await,
+    )
+    .await;
+    let last = t.row(0).await;
+    t.record(
+        "remove",
+        store
+            .remove_player_from_row(last, right, None)
+            .await,
+    )
+    .await;
+    t.record("delete", store.delete_row(last, None).await)
+        .await;
+    t.record("gone", store.delete_row(last, None).await)
+        .await;
+    t.record(
+        "drop station",
+        store
+            .delete_station(station_id, None)
+            .await,
+    )
+    .await;
+    common::delete_queue(&store, queue_id).await;
+
+    t.steps
+}
+
+/// Checks two transcripts step by step, so a failure names the step where
+/// the backends first went apart.
+fn assert_same(expected: &[Step], actual: &[Step]) {
+    for (expected, actual) in expected.iter().zip(actual) {
+        assert_eq!(expected, actual);
+    }
+    assert_eq!(expected.len(), actual.len());
+}
+
+fn memory() -> Backend {
+    let store = MemoryStore::new();
+    Backend {
+        changes: store.changes(),
+        store: Arc::new(store),
+        wait: Duration::from_millis(10),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn memory_matches_postgres() {
+    let Some(database_url) = common::postgres_url() else {
+        return;
+    };
+    let Some(store) = common::postgres().await else {
+        return;
+    };
+    let postgres = Backend {
+        store,
+        changes: notify::listen_for_changes(database_url),
+        wait: Duration::from_millis(300),
+    };
+
+    let expected = play(postgres).await;
+    let actual = play(memory()).await;
+    assert_same(&expected, &actual);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn sqlite_matches_memory() {
+    let sqlite = common::Sqlite::open();
+    let sqlite = Backend {
+        store: sqlite.store.clone(),
+        changes: sqlite.changes.clone(),
+        wait: Duration::from_millis(10),
+    };
+
+    let expected = play(memory()).await;
+    let actual = play(sqlite).await;
+    assert_same(&expected, &actual);
+}
@@ -0,0 +1,39 @@
+//! Players who share a name are told apart by their tags, and a blank tag is
+//! the same as none, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::Store;
+use uuid::Uuid;
+
+/// Looks up the same name without a tag, with two different tags, in other
+/// case and with a blank tag.
+async fn assert_tags_tell_players_apart(store: Store) {
+    let name = format!("Alex {}", Uuid::new_v4().simple());
+    let player = |name: String, tag: Option<&str>| {
+        let store = store.clone();
+        let tag = tag.map(str::to_string);
+        async move {
+            store
+                .get_or_create_player(name, tag)
+                .await
+                .expect("player to be found or created")
+        }
+    };
+    let untagged = player(name.clone(), None).await;
+    let red = player(name.clone(), Some("red")).await;
+    let blue = player(name.clone(), Some(" blue ")).await;
+    let red_again = player(name.to_uppercase(), Some("red")).await;
+    let blank = player(name.clone(), Some("  ")).await;
+
+    assert_eq!(untagged.tag, None);
+    assert_eq!(red.tag.as_deref(), Some("red"));
+    assert_eq!(blue.tag.as_deref(), Some("blue"));
+    assert_ne!(red.id, untagged.id);
+    assert_ne!(red.id, blue.id);
+    assert_eq!(red_again.id, red.id);
+    assert_eq!(blank.id, untagged.id);
+}
+
+on_every_backend!(tags_tell_players_apart => assert_tags_tell_players_apart);
@@ -0,0 +1,92 @@
+//! In winner-stays mode the winner keeps the front row until their streak
+//! runs out, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::Store;
+use itq::queue::{QueueMode, Side};
+
+/// Queues up two pairs, then lets the same player win twice with a max
+/// streak of two.
+async fn assert_winner_stays_until_streak_ends(store: Store) {
+    let queue = common::new_queue(&store, "Winner stays").await;
+    store
+        .set_queue_mode(
+            queue.id,
+            QueueMode::WinnerStays,
+            Some(2),
+            false,
+            1,
+            None,
+        )
+        .await
+        .expect("mode to be set");
+    let mut names = Vec::new();
+    for pair in [["ana", "ben"], ["cy", "dee"]] {
+        let mut row_id = None;
+        for (name, side) in pair
+            .into_iter()
+            .zip([Side::Left, Side::Right])
+        {
+            let player = common::new_player(&store, name).await;
+            names.push(player.display_name);
+            row_id = Some(match row_id {
+                None => store
+                    .add_row(queue.id, player.id, side.into())
+                    .await
+                    .expect("row to be added"),
+                Some(row_id) => {
+                    store
+                        .add_player_to_row(row_id, player.id, side.into())
+                        .await
+                        .expect("player to join the row");
+                    row_id
+                }
+            });
+        }
+    }
+    let rows = |store: Store| async move {
+        store
+            .get_queue_state(queue.id)
+            .await
+            .expect("state to load")
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let name = |side: Side| {
+                    entry
+                        .player(side.into())
+                        .map(|p| p.display_name.clone())
+                };
+                (name(Side::Left), name(Side::Right))
+            })
+            .collect::<Vec<_>>()
+    };
+    let name = |i: usize| Some(names[i].clone());
+
+    store
+        .complete_front_row(queue.id, Some(Side::Left), None)
+        .await
+        .expect("first match to finish");
+    let after_first = rows(store.clone()).await;
+    store
+        .complete_front_row(queue.id, Some(Side::Left), None)
+        .await
+        .expect("second match to finish");
+    let after_second = rows(store.clone()).await;
+    common::delete_queue(&store, queue.id).await;
+
+    // Ana stays on and Dee moves up to take Ben's side.
+    assert_eq!(
+        after_first,
+        vec![(name(0), name(3)), (name(2), None)]
+    );
+    // Two wins in a row sends Ana to the back.
+    assert_eq!(
+        after_second,
+        vec![(name(2), None), (name(0), None)]
+    );
+}
+
+on_every_backend!(winner_stays => assert_winner_stays_until_streak_ends);
@@ -0,0 +1,327 @@
+//! Queues must turn players away once they're full, a player is in too many
+//! rows, or joining is paused, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{QueueMode, QueueSettings, QueueStatus, Side};
+
+/// Fills a queue limited to three rows and two rows per player, then pauses
+/// it and checks nobody can join until it opens again.
+async fn assert_settings_limit_joining(store: Store) {
+    let queue = common::new_queue(&store, "Settings").await;
+    let player = |name: &'static str| {
+        let store = store.clone();
+        async move {
+            common::new_player(&store, name)
+                .await
+                .id
+        }
+    };
+    let (ana, ben, cat) = (
+        player("ana").await,
+        player("ben").await,
+        player("cat").await,
+    );
+    let limited = QueueSettings {
+        max_rows: Some(3),
+        max_entries_per_player: Some(2),
+        status: QueueStatus::Open,
+        unique_players: false,
+    };
+    let info = store
+        .set_queue_settings(queue.id, limited, None)
+        .await
+        .expect("settings to be saved");
+
+    let first = store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await
+        .expect("row to be added");
+    store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await
+        .expect("row to be added");
+    let over_limit = store
+        .join_queue(queue.id, ana)
+        .await;
+    store
+        .add_row(queue.id, ben, Side::Left.into())
+        .await
+        .expect("row to be added");
+    let full = store
+        .add_row(queue.id, cat, Side::Left.into())
+        .await;
+    // Filling a slot of a row that's already there doesn't add a row.
+    let joined = store
+        .add_player_to_row(first, cat, Side::Right.into())
+        .await;
+
+    let paused = store
+        .set_queue_settings(
+            queue.id,
+            QueueSettings {
+                status: QueueStatus::Paused,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .expect("settings to be saved");
+    let while_paused = store
+        .add_row(queue.id, cat, Side::Left.into())
+        .await;
+    store
+        .set_queue_settings(queue.id, QueueSettings::default(), None)
+        .await
+        .expect("settings to be saved");
+    let reopened = store
+        .add_row(queue.id, cat, Side::Left.into())
+        .await;
+    let reloaded = store
+        .get_queue_info(queue.url_name.clone())
+        .await
+        .expect("queue to load");
+    common::delete_queue(&store, queue.id).await;
+
+    assert_eq!(info.settings, limited);
+    assert!(matches!(
+        full,
+        Err(ApiError::QueueFull { max_rows: 3 })
+    ));
+    assert!(joined.is_ok());
+    assert!(matches!(
+        over_limit,
+        Err(ApiError::PlayerLimit { max_entries: 2 })
+    ));
+    assert_eq!(paused.settings.status, QueueStatus::Paused);
+    assert!(matches!(
+        while_paused,
+        Err(ApiError::QueueClosed {
+            status: QueueStatus::Paused
+        })
+    ));
+    assert!(reopened.is_ok());
+    assert_eq!(reloaded.settings, QueueSettings::default());
+}
+
+/// Fills a queue to exactly one row and one row per player, and checks the
+/// next join is refused until a row leaves.
+async fn assert_limits_hold_at_the_boundary(store: Store) {
+    let queue = common::new_queue(&store, "Boundary").await;
+    let ana = common::new_player(&store, "ana")
+        .await
+        .id;
+    let ben = common::new_player(&store, "ben")
+        .await
+        .id;
+    store
+        .set_queue_settings(
+            queue.id,
+            QueueSettings {
+                max_rows: Some(1),
+                max_entries_per_player: Some(1),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .expect("settings to be saved");
+
+    let only = store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await
+        .expect("the last free row to be taken");
+    let full = store
+        .add_row(queue.id, ben, Side::Left.into())
+        .await;
+    let twice = store
+        .add_player_to_row(only, ana, Side::Right.into())
+        .await;
+    // Ben still fits in the row that's there.
+    let filled = store
+        .add_player_to_row(only, ben, Side::Right.into())
+        .await;
+    store
+        .complete_front_row(queue.id, None, None)
+        .await
+        .expect("match to finish");
+    let freed = store
+        .add_row(queue.id, ben, Side::Left.into())
+        .await;
+    common::delete_queue(&store, queue.id).await;
+
+    assert!(matches!(
+        full,
+        Err(ApiError::QueueFull { max_rows: 1 })
+    ));
+    assert!(matches!(
+        twice,
+        Err(ApiError::PlayerLimit { max_entries: 1 })
+    ));
+    assert!(filled.is_ok());
+    assert!(freed.is_ok());
+}
+
+/// Pauses and then closes a singles queue with a match waiting, and checks
+/// every way of joining is refused while the match can still be played.
+async fn assert_paused_queues_keep_playing(store: Store) {
+    let queue = common::new_queue(&store, "Paused").await;
+    let ana = common::new_player(&store, "ana")
+        .await
+        .id;
+    let ben = common::new_player(&store, "ben")
+        .await
+        .id;
+    let cy = common::new_player(&store, "cy")
+        .await
+        .id;
+    let row = common::add_pair(&store, queue.id, ana, ben).await;
+    let waiting = store
+        .add_row(queue.id, cy, Side::Left.into())
+        .await
+        .expect("row to be added");
+
+    let mut refused = Vec::new();
+    for status in [QueueStatus::Paused, QueueStatus::Closed] {
+        store
+            .set_queue_settings(
+                queue.id,
+                QueueSettings {
+                    status,
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .expect("settings to be saved");
+        let dee = common::new_player(&store, "dee")
+            .await
+            .id;
+        refused.push((
+            status,
+            [
+                store
+                    .add_row(queue.id, dee, Side::Left.into())
+                    .await
+                    .map(|_| ()),
+                store
+                    .add_player_to_row(waiting, dee, Side::Right.into())
+                    .await
+                    .map(|_| ()),
+            ],
+        ));
+    }
+    store
+        .set_queue_mode(queue.id, QueueMode::Pairs, None, true, 1, None)
+        .await
+        .expect("singles to be turned on");
+    let dee = common::new_player(&store, "dee")
+        .await
+        .id;
+    let joined = store
+        .join_queue(queue.id, dee)
+        .await;
+    let played = store
+        .complete_front_row(queue.id, Some(Side::Left), None)
+        .await
+        .expect("match to finish while closed");
+    common::delete_queue(&store, queue.id).await;
+
+    for (status, results) in refused {
+        for result in results {
+            assert!(
+                matches!(result, Err(ApiError::QueueClosed { status: s }) if s == status),
+                "expected {status} to refuse, got {result:?}"
+            );
+        }
+    }
+    assert!(matches!(
+        joined,
+        Err(ApiError::QueueClosed {
+            status: QueueStatus::Closed
+        })
+    ));
+    assert_eq!(played.map(|e| e.id), Some(row));
+}
+
+/// Tries to move a player from one queue into a paused one, which would
+/// skip its join checks, and checks both queues are left as they were.
+async fn assert_players_stay_in_their_queue(store: Store) {
+    let open = common::new_queue(&store, "Open").await;
+    let paused = common::new_queue(&store, "Paused moves").await;
+    let ana = common::new_player(&store, "ana")
+        .await
+        .id;
+    let ben = common::new_player(&store, "ben")
+        .await
+        .id;
+    let from = store
+        .add_row(open.id, ana, Side::Left.into())
+        .await
+        .expect("row to be added");
+    let to = store
+        .add_row(paused.id, ben, Side::Left.into())
+        .await
+        .expect("row to be added");
+    store
+        .set_queue_settings(
+            paused.id,
+            QueueSettings {
+                status: QueueStatus::Paused,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .expect("settings to be saved");
+    let state = |queue_id| {
+        let store = store.clone();
+        async move {
+            let state = store
+                .get_queue_state(queue_id)
+                .await
+                .expect("state to load");
+            let lineups: Vec<_> = state
+                .entries
+                .iter()
+                .map(|e| {
+                    let players: Vec<_> = e
+                        .players
+                        .iter()
+                        .map(|p| (p.slot, p.player.id))
+                        .collect();
+                    (e.id, players)
+                })
+                .collect();
+            (state.version, lineups)
+        }
+    };
+    let before = [state(open.id).await, state(paused.id).await];
+
+    let moved = store
+        .move_player(
+            from,
+            Side::Left.into(),
+            to,
+            Side::Right.into(),
+            false,
+            None,
+        )
+        .await;
+    let after = [state(open.id).await, state(paused.id).await];
+    common::delete_queue(&store, open.id).await;
+    common::delete_queue(&store, paused.id).await;
+
+    assert!(matches!(moved, Err(ApiError::DifferentQueues)));
+    assert_eq!(after, before);
+}
+
+on_every_backend!(
+    settings_limit_joining => assert_settings_limit_joining,
+    limits_hold_at_the_boundary => assert_limits_hold_at_the_boundary,
+    paused_queues_keep_playing => assert_paused_queues_keep_playing,
+    players_stay_in_their_queue => assert_players_stay_in_their_queue,
+);
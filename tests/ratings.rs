@@ -0,0 +1,109 @@
+//! Finishing a match with a winner must move everyone's Elo rating in that
+//! queue and record the change, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::Store;
+use itq::queue::{QueueMode, Side, Slot};
+use itq::rating::INITIAL_RATING;
+use uuid::Uuid;
+
+/// Plays a singles match with a winner, one without, and a doubles match the
+/// underdogs win, then checks the leaderboard and rating history.
+async fn assert_matches_are_rated(store: Store) {
+    let queue = common::new_queue(&store, "Ratings").await;
+    store
+        .set_queue_mode(queue.id, QueueMode::Pairs, None, false, 2, None)
+        .await
+        .expect("team size to be set");
+    let mut ids = Vec::new();
+    for name in ["ana", "ben", "cy", "dee"] {
+        ids.push(
+            common::new_player(&store, name)
+                .await
+                .id,
+        );
+    }
+    let (ana, ben, cy, dee) = (ids[0], ids[1], ids[2], ids[3]);
+
+    let lineups = [
+        vec![
+            (ana, Side::Left.into()),
+            (ben, Side::Right.into()),
+        ],
+        vec![
+            (ana, Side::Left.into()),
+            (ben, Side::Right.into()),
+        ],
+        vec![
+            (ana, Slot::new(Side::Left, 0)),
+            (cy, Slot::new(Side::Left, 1)),
+            (ben, Slot::new(Side::Right, 0)),
+            (dee, Slot::new(Side::Right, 1)),
+        ],
+    ];
+    for (lineup, winner) in
+        lineups
+            .into_iter()
+            .zip([Some(Side::Left), None, Some(Side::Right)])
+    {
+        let (first, slot) = lineup[0];
+        let row_id = store
+            .add_row(queue.id, first, slot)
+            .await
+            .expect("row to be added");
+        for &(player, slot) in &lineup[1..] {
+            store
+                .add_player_to_row(row_id, player, slot)
+                .await
+                .expect("player to join the row");
+        }
+        store
+            .complete_front_row(queue.id, winner, None)
+            .await
+            .expect("match to finish");
+    }
+
+    let leaderboard = store
+        .get_leaderboard(queue.id)
+        .await
+        .expect("leaderboard to load");
+    let history = store
+        .get_rating_history(queue.id)
+        .await
+        .expect("history to load");
+    common::delete_queue(&store, queue.id).await;
+
+    // Ana won the first match as an equal, so gained half of the K factor.
+    let first_ratings: Vec<(Uuid, f64)> = history[..2]
+        .iter()
+        .map(|point| (point.player_id, point.rating))
+        .collect();
+    assert!(first_ratings.contains(&(ana, INITIAL_RATING + 16.0)));
+    assert!(first_ratings.contains(&(ben, INITIAL_RATING - 16.0)));
+    // The match without a winner isn't rated.
+    assert_eq!(history.len(), 6);
+    let order: Vec<Uuid> = leaderboard
+        .iter()
+        .map(|s| s.player.id)
+        .collect();
+    assert_eq!(order, vec![dee, ben, ana, cy]);
+    let records: Vec<(i32, i32)> = leaderboard
+        .iter()
+        .map(|s| (s.wins, s.games))
+        .collect();
+    assert_eq!(records, vec![(1, 1), (1, 2), (1, 2), (0, 1)]);
+    // Dee's team was rated lower, so won more than half of the K factor.
+    let dee_gain = leaderboard[0].rating - INITIAL_RATING;
+    assert!(
+        dee_gain > 16.0 && dee_gain < 17.0,
+        "gained {dee_gain}"
+    );
+    assert!(
+        (leaderboard[1].rating - (INITIAL_RATING - 16.0 + dee_gain)).abs()
+            < 1e-9
+    );
+}
+
+on_every_backend!(matches_are_rated => assert_matches_are_rated);
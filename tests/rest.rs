@@ -0,0 +1,190 @@
+//! The REST handlers turn bad requests and store errors into the right
+//! status and JSON error body.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Method, Request, StatusCode};
+use axum::{Extension, Router};
+use itq::admin::ADMIN_TOKEN_HEADER;
+use itq::auth::CurrentUser;
+use itq::db::Store;
+use itq::queue::{QueueInfo, Side};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// The `/api/v1` routes over `store`, with nobody logged in.
+fn router(store: Store) -> Router {
+    itq::rest::router(store).layer(Extension(CurrentUser(None)))
+}
+
+/// Sends a request and returns its status and JSON body.
+async fn send(store: &Store, request: Request<Body>) -> (StatusCode, Value) {
+    let response = router(store.clone())
+        .oneshot(request)
+        .await
+        .expect("router to respond");
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body to be read");
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    (status, body)
+}
+
+/// A request with a JSON body.
+fn json_request(method: Method, uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("request to build")
+}
+
+/// A new queue with one row, and the token to administer it.
+async fn queue_with_row(store: &Store) -> (QueueInfo, String, Uuid) {
+    let created = store
+        .add_queue(
+            "REST".to_string(),
+            format!("test-{}", Uuid::new_v4().simple()),
+            None,
+        )
+        .await
+        .expect("queue to be created");
+    let player = common::new_player(store, "ana").await;
+    let row_id = store
+        .add_row(created.info.id, player.id, Side::Left.into())
+        .await
+        .expect("row to be added");
+    (created.info, created.admin_token, row_id)
+}
+
+#[tokio::test]
+async fn malformed_bodies_are_invalid_body() {
+    let store = common::memory();
+    let (queue, _, _) = queue_with_row(&store).await;
+
+    let (status, body) = send(
+        &store,
+        json_request(
+            Method::POST,
+            &format!("/queues/{}/players", queue.url_name),
+            "{\"name\": ",
+        ),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_body");
+    assert_eq!(body["details"], json!({}));
+}
+
+#[tokio::test]
+async fn bad_uuids_in_the_path_are_invalid_path() {
+    let store = common::memory();
+    let (queue, token, _) = queue_with_row(&store).await;
+
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!(
+            "/queues/{}/rows/not-a-uuid",
+            queue.url_name
+        ))
+        .header(ADMIN_TOKEN_HEADER, token)
+        .body(Body::empty())
+        .expect("request to build");
+    let (status, body) = send(&store, request).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_path");
+}
+
+#[tokio::test]
+async fn stale_if_match_is_a_conflict() {
+    let store = common::memory();
+    let (queue, token, row_id) = queue_with_row(&store).await;
+    let version = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load")
+        .version;
+
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!(
+            "/queues/{}/rows/{row_id}",
+            queue.url_name
+        ))
+        .header(ADMIN_TOKEN_HEADER, token)
+        .header(header::IF_MATCH, format!("\"{}\"", version - 1))
+        .body(Body::empty())
+        .expect("request to build");
+    let (status, body) = send(&store, request).await;
+
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(body["code"], "conflict");
+    assert_eq!(body["details"]["current"]["version"], version);
+    assert_eq!(
+        body["details"]["current"]["entries"][0]["id"],
+        row_id.to_string()
+    );
+}
+
+#[tokio::test]
+async fn missing_queues_are_not_found() {
+    let store = common::memory();
+
+    let request = Request::builder()
+        .uri("/queues/no-such-queue")
+        .body(Body::empty())
+        .expect("request to build");
+    let (status, body) = send(&store, request).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["details"], json!({}));
+}
+
+#[tokio::test]
+async fn taken_slots_are_occupied() {
+    let store = common::memory();
+    let (queue, _, row_id) = queue_with_row(&store).await;
+
+    let (status, body) = send(
+        &store,
+        json_request(
+            Method::POST,
+            &format!("/queues/{}/players", queue.url_name),
+            &json!({ "name": "ben", "row_id": row_id, "side": "Left" })
+                .to_string(),
+        ),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "occupied");
+    assert_eq!(body["details"]["row_id"], row_id.to_string());
+    assert_eq!(body["details"]["slot_index"], 0);
+}
+
+#[tokio::test]
+async fn blank_names_are_unprocessable() {
+    let store = common::memory();
+    let (queue, _, _) = queue_with_row(&store).await;
+
+    let (status, body) = send(
+        &store,
+        json_request(
+            Method::POST,
+            &format!("/queues/{}/players", queue.url_name),
+            &json!({ "name": "  " }).to_string(),
+        ),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "blank_player_name");
+}
@@ -0,0 +1,85 @@
+//! Players joining a singles queue must be paired with whoever has waited
+//! alone the longest, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::Store;
+use itq::queue::{QueueMode, Side};
+use uuid::Uuid;
+
+/// Lets four players join one by one, including someone joining twice and a
+/// partner dropping out, and checks who ends up paired with whom.
+async fn assert_players_are_paired(store: Store) {
+    let queue = common::new_queue(&store, "Singles").await;
+    let queue = store
+        .set_queue_mode(queue.id, QueueMode::Pairs, None, true, 1, None)
+        .await
+        .expect("singles to be turned on");
+    let mut ids = Vec::new();
+    for name in ["ana", "ben", "cy", "dee"] {
+        ids.push(
+            common::new_player(&store, name)
+                .await
+                .id,
+        );
+    }
+    let (ana, ben, cy, dee) = (ids[0], ids[1], ids[2], ids[3]);
+
+    let mut joined = Vec::new();
+    for player in [ana, ben, cy] {
+        joined.push(
+            store
+                .join_queue(queue.id, player)
+                .await
+                .expect("player to join"),
+        );
+    }
+    // Nobody gets paired with themselves.
+    let second_cy = store
+        .join_queue(queue.id, cy)
+        .await
+        .expect("player to join again");
+    let second_ben = store
+        .join_queue(queue.id, ben)
+        .await
+        .expect("player to join again");
+    store
+        .remove_player_from_row(joined[1].id, Side::Right.into(), None)
+        .await
+        .expect("player to leave");
+    // The front row has waited longest now that its partner left.
+    let paired = store
+        .join_queue(queue.id, dee)
+        .await
+        .expect("player to join");
+
+    let state = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    common::delete_queue(&store, queue.id).await;
+    assert!(queue.singles);
+    assert_eq!(joined[1].id, joined[0].id);
+    assert_eq!(second_cy.order, 2);
+    assert_eq!(second_ben.order, 1);
+    assert_eq!(paired.order, 0);
+    let pairs: Vec<(Option<Uuid>, Option<Uuid>)> = state
+        .entries
+        .into_iter()
+        .map(|e| {
+            let id = |side: Side| e.player(side.into()).map(|p| p.id);
+            (id(Side::Left), id(Side::Right))
+        })
+        .collect();
+    assert_eq!(
+        pairs,
+        vec![
+            (Some(ana), Some(dee)),
+            (Some(cy), Some(ben)),
+            (Some(cy), None),
+        ]
+    );
+}
+
+on_every_backend!(players_are_paired => assert_players_are_paired);
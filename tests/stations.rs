@@ -0,0 +1,172 @@
+//! Stations must each play one row at a time, never the same one, and pick
+//! up the next waiting row when their match ends, and only finish matches
+//! through a station, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use chrono::Utc;
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{QueueState, Side};
+use uuid::Uuid;
+
+/// Starts two stations at once with one row waiting, fills the other once
+/// more rows join, then finishes both matches until the queue runs dry.
+async fn assert_stations_take_turns(store: Store) {
+    let queue = common::new_queue(&store, "Stations").await;
+    let first_station = store
+        .add_station(queue.id, " ".to_string(), None)
+        .await
+        .expect("station to be added");
+    let second_station = store
+        .add_station(queue.id, "Court B".to_string(), None)
+        .await
+        .expect("station to be added");
+    let add_row = |[left, right]: [&'static str; 2]| {
+        let store = store.clone();
+        async move {
+            let left = common::new_player(&store, left).await;
+            let right = common::new_player(&store, right).await;
+            common::add_pair(&store, queue.id, left.id, right.id).await
+        }
+    };
+    let state = |store: Store| async move {
+        store
+            .get_queue_state(queue.id)
+            .await
+            .expect("state to load")
+    };
+    let playing = |state: &QueueState| {
+        state
+            .stations
+            .iter()
+            .map(|s| s.row_id)
+            .collect::<Vec<_>>()
+    };
+
+    let first = add_row(["ana", "ben"]).await;
+    let (a, b) = tokio::join!(
+        store.assign_station(first_station.id, None),
+        store.assign_station(second_station.id, None),
+    );
+    let started: Vec<Uuid> = [a, b]
+        .into_iter()
+        .filter_map(|r| {
+            r.expect("station to start")
+                .map(|e| e.id)
+        })
+        .collect();
+    let raced = state(store.clone()).await;
+    let (busy, idle) = if raced.stations[0].row_id.is_some() {
+        (first_station.id, second_station.id)
+    } else {
+        (second_station.id, first_station.id)
+    };
+    let second = add_row(["cy", "dee"]).await;
+    let third = add_row(["eve", "fay"]).await;
+    let assigned = store
+        .assign_station(idle, None)
+        .await
+        .expect("station to start");
+    let again = store
+        .assign_station(idle, None)
+        .await
+        .expect("station to keep its row");
+    store
+        .set_no_show_rule(queue.id, Some(60), Some(1), None)
+        .await
+        .expect("rule to be set");
+    store
+        .check_no_shows(Utc::now())
+        .await
+        .expect("check to run");
+    let called = state(store.clone()).await;
+    store
+        .set_no_show_rule(queue.id, None, None, None)
+        .await
+        .expect("rule to be cleared");
+    let before = state(store.clone()).await;
+    let front_only = store
+        .complete_front_row(queue.id, None, None)
+        .await;
+    let after = state(store.clone()).await;
+
+    let won = store
+        .free_station(idle, Some(Side::Left), None)
+        .await
+        .expect("match to finish");
+    let moved_on = state(store.clone()).await;
+    let stale = store
+        .free_station(busy, None, Some(moved_on.version - 1))
+        .await;
+    let finished = store
+        .free_station(busy, None, Some(moved_on.version))
+        .await
+        .expect("match to finish");
+    let idle_again = store
+        .free_station(busy, None, None)
+        .await
+        .expect("free station to stay free");
+    store
+        .delete_station(idle, None)
+        .await
+        .expect("station to be removed");
+    let removed = state(store.clone()).await;
+    let matches = store
+        .get_matches(queue.id, 0, 10)
+        .await
+        .expect("matches to load");
+    common::delete_queue(&store, queue.id).await;
+
+    let ids = |state: &QueueState| {
+        state
+            .entries
+            .iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(first_station.name, "Station 1");
+    assert_eq!(second_station.name, "Court B");
+    // Only one of the stations got the only row.
+    assert_eq!(started, vec![first]);
+    assert_eq!(
+        playing(&raced)
+            .iter()
+            .flatten()
+            .count(),
+        1
+    );
+    assert_eq!(assigned.map(|e| e.id), Some(second));
+    assert_eq!(again.map(|e| e.id), Some(second));
+    // Rows on stations aren't called, only the one waiting.
+    let calls: Vec<bool> = called
+        .entries
+        .iter()
+        .map(|e| e.called_at.is_some())
+        .collect();
+    assert_eq!(calls, vec![false, false, true]);
+    // Only a station can finish a match once the queue has them.
+    assert!(matches!(
+        front_only,
+        Err(ApiError::StationRequired { queue_id }) if queue_id == queue.id
+    ));
+    assert_eq!(ids(&after), ids(&before));
+    assert_eq!(playing(&after), playing(&before));
+    assert_eq!(after.version, before.version);
+    assert_eq!(won.map(|e| e.id), Some(second));
+    assert_eq!(ids(&moved_on), vec![first, third]);
+    assert!(moved_on
+        .stations
+        .iter()
+        .any(|s| s.id == idle && s.row_id == Some(third)));
+    assert!(matches!(stale, Err(ApiError::Conflict { .. })));
+    assert_eq!(finished.map(|e| e.id), Some(first));
+    assert!(idle_again.is_none());
+    // The row that was playing on the removed station waits again.
+    assert_eq!(ids(&removed), vec![third]);
+    assert_eq!(playing(&removed), vec![None]);
+    assert_eq!(matches.len(), 2);
+}
+
+on_every_backend!(stations_take_turns => assert_stations_take_turns);
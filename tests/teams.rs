@@ -0,0 +1,101 @@
+//! Rows in a doubles queue must hold two players a side, refuse slots that
+//! are taken or don't exist, and record both teams when their match ends,
+//! the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{QueueMode, Side, Slot};
+
+/// Fills every slot of a doubles row, tries a taken and a missing slot, then
+/// finishes the row's match.
+async fn assert_teams_fill_rows(store: Store) {
+    let queue = common::new_queue(&store, "Doubles").await;
+    let queue = store
+        .set_queue_mode(queue.id, QueueMode::Pairs, None, false, 2, None)
+        .await
+        .expect("team size to be set");
+    let mut players = Vec::new();
+    for name in ["ana", "ben", "cy", "dee"] {
+        players.push(common::new_player(&store, name).await);
+    }
+    let slots = [
+        Slot::new(Side::Left, 0),
+        Slot::new(Side::Right, 1),
+        Slot::new(Side::Left, 1),
+        Slot::new(Side::Right, 0),
+    ];
+
+    let row_id = store
+        .add_row(queue.id, players[0].id, slots[0])
+        .await
+        .expect("row to be added");
+    let mut entry = None;
+    for (player, &slot) in players.iter().zip(&slots).skip(1) {
+        entry = Some(
+            store
+                .add_player_to_row(row_id, player.id, slot)
+                .await
+                .expect("player to join the row"),
+        );
+    }
+    let entry = entry.expect("players to have joined");
+    let taken = store
+        .add_player_to_row(row_id, players[0].id, slots[1])
+        .await;
+    let missing = store
+        .add_player_to_row(row_id, players[0].id, Slot::new(Side::Left, 2))
+        .await;
+    store
+        .complete_front_row(queue.id, Some(Side::Right), None)
+        .await
+        .expect("match to finish");
+    let played = store
+        .get_matches(queue.id, 0, 10)
+        .await
+        .expect("matches to load");
+    let state = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    common::delete_queue(&store, queue.id).await;
+
+    assert_eq!(queue.team_size, 2);
+    let ids = |side: Side| {
+        entry
+            .team(side)
+            .map(|p| p.id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        ids(Side::Left),
+        vec![players[0].id, players[2].id]
+    );
+    assert_eq!(
+        ids(Side::Right),
+        vec![players[3].id, players[1].id]
+    );
+    assert!(matches!(
+        taken,
+        Err(ApiError::Occupied { slot, .. }) if slot == slots[1]
+    ));
+    assert!(matches!(
+        missing,
+        Err(ApiError::InvalidSlot { team_size: 2, .. })
+    ));
+    assert_eq!(played.len(), 1);
+    assert_eq!(
+        played[0].team(Side::Left),
+        &[players[0].clone(), players[2].clone()]
+    );
+    assert_eq!(
+        played[0].team(Side::Right),
+        &[players[3].clone(), players[1].clone()]
+    );
+    assert_eq!(played[0].winner, Some(Side::Right));
+    assert!(state.entries.is_empty());
+}
+
+on_every_backend!(teams_fill_rows => assert_teams_fill_rows);
@@ -0,0 +1,166 @@
+//! Queues that turn away players already waiting must say which row they're
+//! in, and let them back in once they've left, the same way on every
+//! backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{QueueSettings, Side};
+
+const PARALLEL_JOINS: usize = 16;
+
+/// Puts a player in the second row, then tries to add them again every way
+/// there is before and after the rule is turned on.
+async fn assert_players_queue_once(store: Store) {
+    let queue = common::new_queue(&store, "Unique").await;
+    let player = |name: &'static str| {
+        let store = store.clone();
+        async move {
+            common::new_player(&store, name)
+                .await
+                .id
+        }
+    };
+    let (ana, ben) = (player("ana").await, player("ben").await);
+    let first = store
+        .add_row(queue.id, ben, Side::Left.into())
+        .await
+        .expect("row to be added");
+    let second = store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await
+        .expect("row to be added");
+    // Off by default.
+    let duplicate = store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await
+        .expect("row to be added");
+    store
+        .delete_row(duplicate, None)
+        .await
+        .expect("row to be deleted");
+
+    let info = store
+        .set_queue_settings(
+            queue.id,
+            QueueSettings {
+                unique_players: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .expect("settings to be saved");
+    let new_row = store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await;
+    let other_row = store
+        .add_player_to_row(first, ana, Side::Right.into())
+        .await;
+    let paired = store
+        .join_queue(queue.id, ana)
+        .await;
+    let others = store
+        .add_player_to_row(first, player("cat").await, Side::Right.into())
+        .await;
+    store
+        .delete_row(second, None)
+        .await
+        .expect("row to be deleted");
+    let rejoined = store
+        .add_row(queue.id, ana, Side::Left.into())
+        .await;
+    common::delete_queue(&store, queue.id).await;
+
+    assert!(info.settings.unique_players);
+    for result in [
+        new_row.map(|_| ()),
+        other_row.map(|_| ()),
+        paired.map(|_| ()),
+    ] {
+        assert!(
+            matches!(
+                result,
+                Err(ApiError::AlreadyQueued { row_id, order: 1 }) if row_id == second
+            ),
+            "expected AlreadyQueued, got {result:?}"
+        );
+    }
+    assert!(others.is_ok());
+    assert!(rejoined.is_ok());
+}
+
+/// Tries to add the same player [`PARALLEL_JOINS`] times at once, half as
+/// new rows and half into someone else's row, and checks only one gets in.
+async fn assert_parallel_joins_queue_once(store: Store) {
+    let queue = common::new_queue(&store, "Unique parallel").await;
+    let ana = common::new_player(&store, "ana")
+        .await
+        .id;
+    let ben = common::new_player(&store, "ben")
+        .await
+        .id;
+    let first = store
+        .add_row(queue.id, ben, Side::Left.into())
+        .await
+        .expect("row to be added");
+    store
+        .set_queue_settings(
+            queue.id,
+            QueueSettings {
+                unique_players: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .expect("settings to be saved");
+
+    let joins: Vec<_> = (0..PARALLEL_JOINS)
+        .map(|i| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                if i % 2 == 0 {
+                    store
+                        .add_row(queue.id, ana, Side::Left.into())
+                        .await
+                        .map(|_| ())
+                } else {
+                    store
+                        .add_player_to_row(first, ana, Side::Right.into())
+                        .await
+                        .map(|_| ())
+                }
+            })
+        })
+        .collect();
+    let mut joined = 0;
+    for join in joins {
+        match join.await.expect("join to finish") {
+            Ok(()) => joined += 1,
+            Err(ApiError::AlreadyQueued { .. }) => {}
+            Err(e) => panic!("expected AlreadyQueued, got {e:?}"),
+        }
+    }
+    let rows_with_ana = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load")
+        .entries
+        .iter()
+        .filter(|e| {
+            e.players
+                .iter()
+                .any(|p| p.player.id == ana)
+        })
+        .count();
+    common::delete_queue(&store, queue.id).await;
+
+    assert_eq!(joined, 1);
+    assert_eq!(rows_with_ana, 1);
+}
+
+on_every_backend!(players_queue_once => assert_players_queue_once);
+on_every_backend!(parallel_joins => assert_parallel_joins_queue_once);
@@ -0,0 +1,333 @@
+//! Changes made at an old queue version must be refused with the current
+//! state, the same way on every backend.
+#![cfg(feature = "ssr")]
+
+mod common;
+
+use itq::db::api::ApiError;
+use itq::db::Store;
+use itq::queue::{QueueMode, QueueSettings, QueueState, QueueStatus, Side};
+
+/// Makes two changes at the same version, as two tablets would, and checks
+/// only the first one goes through.
+async fn assert_stale_changes_conflict(store: Store) {
+    let queue = common::new_queue(&store, "Versions").await;
+    for i in 0..2 {
+        let player = common::new_player(&store, &format!("player {i}")).await;
+        store
+            .add_row(queue.id, player.id, Side::Left.into())
+            .await
+            .expect("row to be added");
+    }
+
+    let seen = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    assert_eq!(seen.version, 2);
+    store
+        .complete_front_row(queue.id, None, Some(seen.version))
+        .await
+        .expect("first change to go through");
+    let stale = store
+        .complete_front_row(queue.id, None, Some(seen.version))
+        .await;
+
+    let current = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    common::delete_queue(&store, queue.id).await;
+    match stale {
+        Err(ApiError::Conflict { current: conflict }) => {
+            assert_eq!(conflict.version, seen.version + 1);
+            assert_eq!(conflict.entries.len(), 1);
+        }
+        other => panic!("expected a conflict, got {other:?}"),
+    }
+    assert_eq!(current.version, seen.version + 1);
+    // The row left behind moved to the front, which is a change to it too.
+    assert_eq!(current.entries[0].order, 0);
+    assert_eq!(current.entries[0].version, 1);
+}
+
+/// Tries every change that takes an expected version at a stale one, and
+/// checks each is refused without touching the queue, its stations or its
+/// settings.
+async fn assert_every_change_checks_its_version(store: Store) {
+    let queue = common::new_queue(&store, "Stale changes").await;
+    let mut rows = Vec::new();
+    for [left, right] in [["ana", "ben"], ["cy", "dee"]] {
+        let left = common::new_player(&store, left).await;
+        let right = common::new_player(&store, right).await;
+        rows.push(common::add_pair(&store, queue.id, left.id, right.id).await);
+    }
+    let (first, second) = (rows[0], rows[1]);
+    let station = store
+        .add_station(queue.id, "Court A".to_string(), None)
+        .await
+        .expect("station to be added");
+    let spare = store
+        .add_station(queue.id, "Court B".to_string(), None)
+        .await
+        .expect("station to be added");
+    let state = || async {
+        store
+            .get_queue_state(queue.id)
+            .await
+            .expect("state to load")
+    };
+    let info = || async {
+        store
+            .get_queue_info(queue.url_name.clone())
+            .await
+            .expect("info to load")
+    };
+    let before = state().await;
+    let info_before = info().await;
+    let stale = Some(before.version - 1);
+
+    let results = [
+        store
+            .remove_player_from_row(first, Side::Left.into(), stale)
+            .await
+            .map(|_| ()),
+        store
+            .delete_row(first, stale)
+            .await,
+        store
+            .move_row(second, 0, stale)
+            .await,
+        store
+            .move_player(
+                first,
+                Side::Left.into(),
+                second,
+                Side::Left.into(),
+                true,
+                stale,
+            )
+            .await,
+        store
+            .complete_front_row(queue.id, None, stale)
+            .await
+            .map(|_| ()),
+        store
+            .free_station(station.id, None, stale)
+            .await
+            .map(|_| ()),
+        store
+            .set_queue_settings(
+                queue.id,
+                QueueSettings {
+                    status: QueueStatus::Closed,
+                    ..Default::default()
+                },
+                stale,
+            )
+            .await
+            .map(|_| ()),
+        store
+            .set_queue_mode(
+                queue.id,
+                QueueMode::WinnerStays,
+                None,
+                true,
+                2,
+                stale,
+            )
+            .await
+            .map(|_| ()),
+        store
+            .set_no_show_rule(queue.id, Some(60), Some(1), stale)
+            .await
+            .map(|_| ()),
+        store
+            .add_station(queue.id, "Court C".to_string(), stale)
+            .await
+            .map(|_| ()),
+        store
+            .delete_station(spare.id, stale)
+            .await,
+        store
+            .assign_station(spare.id, stale)
+            .await
+            .map(|_| ()),
+    ];
+    let after = state().await;
+    let info_after = info().await;
+    common::delete_queue(&store, queue.id).await;
+
+    for result in results {
+        assert!(
+            matches!(&result, Err(ApiError::Conflict { current }) if current.version == before.version),
+            "expected a conflict, got {result:?}"
+        );
+    }
+    let lineups = |state: &QueueState| {
+        state
+            .entries
+            .iter()
+            .map(|e| (e.id, e.players.clone()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(after.version, before.version);
+    assert_eq!(lineups(&after), lineups(&before));
+    assert_eq!(after.stations, before.stations);
+    assert_eq!(info_after, info_before);
+}
+
+/// Deletes the same row from two requests at once, and removes the last
+/// player of another row while it's being deleted, and checks exactly one
+/// of each pair goes through and the other finds the row gone.
+async fn assert_concurrent_deletes_find_the_row_gone(store: Store) {
+    let queue = common::new_queue(&store, "Concurrent deletes").await;
+    let mut rows = Vec::new();
+    for name in ["ana", "ben"] {
+        let player = common::new_player(&store, name).await;
+        rows.push(
+            store
+                .add_row(queue.id, player.id, Side::Left.into())
+                .await
+                .expect("row to be added"),
+        );
+    }
+    let (first, second) = (rows[0], rows[1]);
+
+    let (a, b) = tokio::join!(
+        store.delete_row(first, None),
+        store.delete_row(first, None),
+    );
+    let (deleted, removed) = tokio::join!(
+        store.delete_row(second, None),
+        store.remove_player_from_row(second, Side::Left.into(), None),
+    );
+    let state = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    common::delete_queue(&store, queue.id).await;
+
+    let outcomes = |results: [Result<(), ApiError>; 2]| {
+        let gone = results
+            .iter()
+            .filter(|r| matches!(r, Err(ApiError::NotFound)))
+            .count();
+        let ok = results
+            .iter()
+            .filter(|r| r.is_ok())
+            .count();
+        (ok, gone)
+    };
+    assert_eq!(outcomes([a, b]), (1, 1));
+    assert_eq!(outcomes([deleted, removed.map(|_| ())]), (1, 1));
+    assert!(state.entries.is_empty());
+}
+
+/// Moves a row that was deleted first, then races a move against a delete:
+/// the move reports the row gone instead of failing.
+async fn assert_moving_a_deleted_row_finds_it_gone(store: Store) {
+    let queue = common::new_queue(&store, "Deleted moves").await;
+    let mut rows = Vec::new();
+    for name in ["ana", "ben", "cy"] {
+        let player = common::new_player(&store, name).await;
+        rows.push(
+            store
+                .add_row(queue.id, player.id, Side::Left.into())
+                .await
+                .expect("row to be added"),
+        );
+    }
+
+    store
+        .delete_row(rows[0], None)
+        .await
+        .expect("row to be deleted");
+    let moved = store
+        .move_row(rows[0], 1, None)
+        .await;
+    let (raced, deleted) = tokio::join!(
+        store.move_row(rows[1], 1, None),
+        store.delete_row(rows[1], None),
+    );
+    let state = store
+        .get_queue_state(queue.id)
+        .await
+        .expect("state to load");
+    common::delete_queue(&store, queue.id).await;
+
+    assert!(matches!(moved, Err(ApiError::NotFound)));
+    assert!(
+        matches!(raced, Ok(()) | Err(ApiError::NotFound)),
+        "unexpected move result: {raced:?}"
+    );
+    assert!(deleted.is_ok());
+    let ids: Vec<_> = state
+        .entries
+        .iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(ids, vec![rows[2]]);
+}
+
+/// Changes each of a queue's settings and checks every change moves the
+/// version on, so a change made before it is refused.
+async fn assert_settings_changes_move_the_version(store: Store) {
+    let queue = common::new_queue(&store, "Settings versions").await;
+    let player = common::new_player(&store, "ana").await;
+    let row = store
+        .add_row(queue.id, player.id, Side::Left.into())
+        .await
+        .expect("row to be added");
+    let version = || async {
+        store
+            .get_queue_state(queue.id)
+            .await
+            .expect("state to load")
+            .version
+    };
+
+    let mut versions = vec![version().await];
+    store
+        .set_queue_settings(
+            queue.id,
+            QueueSettings {
+                status: QueueStatus::Paused,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .expect("settings to be saved");
+    versions.push(version().await);
+    store
+        .set_queue_mode(queue.id, QueueMode::Pairs, None, false, 1, None)
+        .await
+        .expect("mode to be saved");
+    versions.push(version().await);
+    store
+        .set_no_show_rule(queue.id, Some(60), None, None)
+        .await
+        .expect("rule to be set");
+    versions.push(version().await);
+    let stale = store
+        .delete_row(row, Some(versions[0]))
+        .await;
+    common::delete_queue(&store, queue.id).await;
+
+    assert!(versions
+        .windows(2)
+        .all(|pair| pair[0] < pair[1]));
+    assert!(matches!(
+        stale,
+        Err(ApiError::Conflict { current }) if current.version == versions[3]
+    ));
+}
+
+on_every_backend!(
+    stale_changes_conflict => assert_stale_changes_conflict,
+    every_change_checks_its_version => assert_every_change_checks_its_version,
+    concurrent_deletes => assert_concurrent_deletes_find_the_row_gone,
+    deleted_moves => assert_moving_a_deleted_row_finds_it_gone,
+    settings_changes => assert_settings_changes_move_the_version,
+);